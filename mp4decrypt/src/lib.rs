@@ -1,7 +1,9 @@
 //! This crate provides a safe function to decrypt,
 //! encrypted mp4 data stream using [Bento4](https://github.com/axiomatic-systems/Bento4).
 //!
-//! Maximum supported stream size is around `4.29` G.B i.e. [u32::MAX](u32::MAX).
+//! Maximum supported stream size for [mp4decrypt](mp4decrypt) is around `4.29` G.B i.e. [u32::MAX](u32::MAX).
+//! Use [mp4decrypt_file](mp4decrypt_file) for decrypting larger files,
+//! it streams data from input file to output file without reading it into memory.
 //!
 //! ## Environment Variables
 //!
@@ -16,9 +18,10 @@
 
 #![allow(improper_ctypes)]
 
-use core::ffi::{c_char, c_int, c_uchar, c_uint};
-use std::collections::HashMap;
-use std::ffi::CString;
+use core::ffi::{c_char, c_int, c_uchar, c_uint, c_void};
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 extern "C" {
     fn decrypt_in_memory(
@@ -42,6 +45,19 @@ extern "C" {
         fragments_info_data: *const c_uchar,
         fragments_info_data_size: c_uint,
     ) -> c_int;
+
+    fn decrypt_file(
+        input_path: *const c_char,
+        output_path: *const c_char,
+        keyids: *mut *const c_char,
+        keys: *mut *const c_char,
+        nkeys: c_int,
+        fragments_info_path: *const c_char,
+        progress_data: *mut c_void,
+        progress_callback: Option<extern "C" fn(*mut c_void, c_uint, c_uint)>,
+    ) -> c_int;
+
+    fn result_text(result: c_int) -> *const c_char;
 }
 
 extern "C" fn decrypt_callback(decrypted_stream: *mut Vec<u8>, data: *const c_uchar, size: c_uint) {
@@ -50,64 +66,142 @@ extern "C" fn decrypt_callback(decrypted_stream: *mut Vec<u8>, data: *const c_uc
     }
 }
 
+extern "C" fn progress_callback(progress: *mut c_void, step: c_uint, total: c_uint) {
+    unsafe {
+        (*(progress as *mut &mut dyn FnMut(u32, u32)))(step, total);
+    }
+}
+
+/// Key used for decrypting a track.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    /// 128-bit `key` in hex for track with ID `id`.
+    /// For dcf files, use 1 as the track index.
+    TrackId { id: u32, key: String },
+    /// 128-bit `key` in hex for track having 128-bit `kid` in hex.
+    /// KIDs are only applicable to some encryption methods like MPEG-CENC.
+    Kid { kid: String, key: String },
+}
+
+impl Key {
+    fn id(&self) -> String {
+        match self {
+            Self::TrackId { id, .. } => id.to_string(),
+            Self::Kid { kid, .. } => kid.to_owned(),
+        }
+    }
+
+    fn key(&self) -> &str {
+        match self {
+            Self::TrackId { key, .. } | Self::Kid { key, .. } => key,
+        }
+    }
+}
+
+/// Errors returned while decrypting mp4 data stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Data stream is larger than [u32::MAX](u32::MAX) bytes.
+    DataTooLarge,
+    /// Fragments info is larger than [u32::MAX](u32::MAX) bytes.
+    FragmentsInfoTooLarge,
+    /// File path is not valid utf-8 or contains a nul byte.
+    InvalidPath(PathBuf),
+    /// Key id is not a valid 128-bit hex KID.
+    InvalidKid,
+    /// Track ID is 0.
+    InvalidTrackId,
+    /// Key is not a valid 128-bit hex key.
+    InvalidKey,
+    /// Bento4 failed with this `AP4_Result` code.
+    Bento4(i32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DataTooLarge => write!(f, "data stream is too large"),
+            Self::FragmentsInfoTooLarge => write!(f, "fragments info is too large"),
+            Self::InvalidPath(x) => write!(f, "invalid file path {}", x.display()),
+            Self::InvalidKid => write!(f, "invalid hex format for key id"),
+            Self::InvalidTrackId => write!(f, "invalid track id"),
+            Self::InvalidKey => write!(f, "invalid hex format for key"),
+            Self::Bento4(x) => {
+                let text = unsafe { CStr::from_ptr(result_text(*x)) };
+                write!(
+                    f,
+                    "failed to decrypt data with error code {} ({})",
+                    x,
+                    text.to_string_lossy()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<c_int> for Error {
+    fn from(value: c_int) -> Self {
+        match value {
+            100 => Self::InvalidKid,
+            101 => Self::InvalidTrackId,
+            102 => Self::InvalidKey,
+            x => Self::Bento4(x),
+        }
+    }
+}
+
 /// Decrypt encrypted mp4 data stream using given keys.
 ///
 /// # Arguments
 ///
 /// * `data` - Encrypted data stream.
-/// * `keys` - Hashmap of keys for decrypting data stream.
-///            Hashmap `key` is either a track ID in decimal or a 128-bit KID in hex.
-///            Hashmap `value` is a 128-bit key in hex. <br>
-///            1. For dcf files, use 1 as the track index <br>
-///            2. For Marlin IPMP/ACGK, use 0 as the track ID <br>
-///            3. KIDs are only applicable to some encryption methods like MPEG-CENC <br>
+/// * `keys` - Keys for decrypting data stream, all of them are used while decrypting.
 /// * `fragments_info` (optional) - Decrypt the fragments read from data stream, with track info read from this stream.
 ///
 /// # Example
 ///
 /// ```no_run
-/// use std::collections::HashMap;
+/// use mp4decrypt::Key;
 ///
-/// let keys = HashMap::from([(
-///     "eb676abbcb345e96bbcf616630f1a3da".to_owned(),
-///     "100b6c20940f779a4589152b57d2dacb".to_owned(),
-/// )]);
+/// let keys = [
+///     Key::Kid {
+///         kid: "eb676abbcb345e96bbcf616630f1a3da".to_owned(),
+///         key: "100b6c20940f779a4589152b57d2dacb".to_owned(),
+///     },
+///     Key::TrackId {
+///         id: 2,
+///         key: "63cb5f7184dd4b689a5c5ff11ee6a328".to_owned(),
+///     },
+/// ];
 ///
-/// let decrypted_data = mp4decrypt::mp4decrypt(&[0, 0, 0, 112], keys, None).unwrap();
+/// let decrypted_data = mp4decrypt::mp4decrypt(&[0, 0, 0, 112], &keys, None).unwrap();
 /// ```
 pub fn mp4decrypt(
     data: &[u8],
-    keys: HashMap<String, String>,
+    keys: &[Key],
     fragments_info: Option<Vec<u8>>,
-) -> Result<Vec<u8>, String> {
+) -> Result<Vec<u8>, Error> {
     let mut data = data.to_vec();
-    let data_size = u32::try_from(data.len()).map_err(|_| "data stream is too large".to_owned())?;
-
-    let mut c_kids_holder = vec![];
-    let mut c_keys_holder = vec![];
-    let mut c_kids = vec![];
-    let mut c_keys = vec![];
-
-    for (i, (kid, key)) in keys.iter().enumerate() {
-        c_kids_holder.push(CString::new(kid.to_owned()).unwrap());
-        c_keys_holder.push(CString::new(key.to_owned()).unwrap());
-        c_kids.push(c_kids_holder[i].as_ptr());
-        c_keys.push(c_keys_holder[i].as_ptr());
-    }
+    let data_size = u32::try_from(data.len()).map_err(|_| Error::DataTooLarge)?;
 
+    let (c_kids_holder, c_keys_holder) = c_keys(keys)?;
+    let mut c_kids = c_kids_holder.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
+    let mut c_keys = c_keys_holder.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
     let mut decrypted_data: Box<Vec<u8>> = Box::default();
 
     let result = unsafe {
         if let Some(mut fragments_info_data) = fragments_info {
             let fragments_info_data_size = u32::try_from(fragments_info_data.len())
-                .map_err(|_| "fragments info is too large".to_owned())?;
+                .map_err(|_| Error::FragmentsInfoTooLarge)?;
 
             decrypt_in_memory_with_fragments_info(
                 data.as_mut_ptr(),
                 data_size,
                 c_kids.as_mut_ptr(),
                 c_keys.as_mut_ptr(),
-                1,
+                keys.len() as c_int,
                 &mut *decrypted_data,
                 decrypt_callback,
                 fragments_info_data.as_mut_ptr(),
@@ -119,7 +213,7 @@ pub fn mp4decrypt(
                 data_size,
                 c_kids.as_mut_ptr(),
                 c_keys.as_mut_ptr(),
-                1,
+                keys.len() as c_int,
                 &mut *decrypted_data,
                 decrypt_callback,
             )
@@ -129,11 +223,95 @@ pub fn mp4decrypt(
     if result == 0 {
         Ok(*decrypted_data)
     } else {
-        Err(match result {
-            100 => "invalid hex format for key id".to_owned(),
-            101 => "invalid key id".to_owned(),
-            102 => "invalid hex format for key".to_owned(),
-            x => format!("failed to decrypt data with error code {}", x),
-        })
+        Err(result.into())
+    }
+}
+
+/// Decrypt encrypted mp4 file using given keys and write it to output file.
+/// Data is streamed through Bento4 file byte streams, so there is no limit on file size.
+///
+/// # Arguments
+///
+/// * `input` - Path of encrypted file.
+/// * `output` - Path of decrypted output file.
+/// * `keys` - Keys for decrypting file, all of them are used while decrypting.
+/// * `fragments_info` (optional) - Decrypt the fragments read from input file, with track info read from this file.
+/// * `progress` (optional) - Called with processed and total number of steps (fragments or tracks).
+///
+/// # Example
+///
+/// ```no_run
+/// use mp4decrypt::Key;
+///
+/// let keys = [Key::Kid {
+///     kid: "eb676abbcb345e96bbcf616630f1a3da".to_owned(),
+///     key: "100b6c20940f779a4589152b57d2dacb".to_owned(),
+/// }];
+///
+/// mp4decrypt::mp4decrypt_file(
+///     "encrypted.mp4",
+///     "decrypted.mp4",
+///     &keys,
+///     None,
+///     Some(&mut |step, total| println!("{}/{}", step, total)),
+/// )
+/// .unwrap();
+/// ```
+pub fn mp4decrypt_file(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    keys: &[Key],
+    fragments_info: Option<&Path>,
+    mut progress: Option<&mut dyn FnMut(u32, u32)>,
+) -> Result<(), Error> {
+    let input = c_path(input.as_ref())?;
+    let output = c_path(output.as_ref())?;
+    let fragments_info = fragments_info.map(c_path).transpose()?;
+
+    let (c_kids_holder, c_keys_holder) = c_keys(keys)?;
+    let mut c_kids = c_kids_holder.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
+    let mut c_keys = c_keys_holder.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
+
+    let result = unsafe {
+        decrypt_file(
+            input.as_ptr(),
+            output.as_ptr(),
+            c_kids.as_mut_ptr(),
+            c_keys.as_mut_ptr(),
+            keys.len() as c_int,
+            fragments_info
+                .as_ref()
+                .map(|x| x.as_ptr())
+                .unwrap_or(std::ptr::null()),
+            match &mut progress {
+                Some(progress) => progress as *mut &mut dyn FnMut(u32, u32) as *mut c_void,
+                None => std::ptr::null_mut(),
+            },
+            progress.as_ref().map(|_| progress_callback as _),
+        )
+    };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(result.into())
+    }
+}
+
+fn c_keys(keys: &[Key]) -> Result<(Vec<CString>, Vec<CString>), Error> {
+    let mut c_kids = vec![];
+    let mut c_keys = vec![];
+
+    for key in keys {
+        c_kids.push(CString::new(key.id()).map_err(|_| Error::InvalidKid)?);
+        c_keys.push(CString::new(key.key()).map_err(|_| Error::InvalidKey)?);
     }
+
+    Ok((c_kids, c_keys))
+}
+
+fn c_path(path: &Path) -> Result<CString, Error> {
+    path.to_str()
+        .and_then(|x| CString::new(x).ok())
+        .ok_or_else(|| Error::InvalidPath(path.to_owned()))
 }
@@ -12,18 +12,12 @@
 #include "Ap4.h"
 #include "mp4decrypt.h"
 
-int decrypt_in_memory(
-    const unsigned char data[],
-    unsigned int data_size,
+static int create_key_map(
+    AP4_ProtectionKeyMap& key_map,
     const char* keyids[],
     const char* keys[],
-    int nkeys,
-    void* decrypted_data,
-    rust_store_callback callback
+    int nkeys
 ) {
-    // create a key map object to hold keys
-    AP4_ProtectionKeyMap key_map;
-
     for (int i = 0; i < nkeys; i++)
     {
         unsigned char key[16];
@@ -51,20 +45,22 @@ int decrypt_in_memory(
         }
     }
 
-    AP4_MemoryByteStream* input = new AP4_MemoryByteStream(data, data_size);
+    return 0;
+}
 
+static AP4_Processor* create_processor(AP4_ByteStream& stream, AP4_ProtectionKeyMap* key_map) {
     // create the decrypting processor
     AP4_Processor* processor = NULL;
-    AP4_File* input_file = new AP4_File(*input);
+    AP4_File* input_file = new AP4_File(stream);
     // input_file->SetFileType()
     AP4_FtypAtom* ftyp = input_file->GetFileType();
     if (ftyp) {
         if (ftyp->GetMajorBrand() == AP4_OMA_DCF_BRAND_ODCF || ftyp->HasCompatibleBrand(AP4_OMA_DCF_BRAND_ODCF)) {
-            processor = new AP4_OmaDcfDecryptingProcessor(&key_map);
+            processor = new AP4_OmaDcfDecryptingProcessor(key_map);
         } else if (ftyp->GetMajorBrand() == AP4_MARLIN_BRAND_MGSV || ftyp->HasCompatibleBrand(AP4_MARLIN_BRAND_MGSV)) {
-            processor = new AP4_MarlinIpmpDecryptingProcessor(&key_map);
+            processor = new AP4_MarlinIpmpDecryptingProcessor(key_map);
         } else if (ftyp->GetMajorBrand() == AP4_PIFF_BRAND || ftyp->HasCompatibleBrand(AP4_PIFF_BRAND)) {
-            processor = new AP4_CencDecryptingProcessor(&key_map);
+            processor = new AP4_CencDecryptingProcessor(key_map);
         }
     }
     if (processor == NULL) {
@@ -84,7 +80,7 @@ int decrypt_in_memory(
                                 psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CBC1 ||
                                 psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CENS ||
                                 psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CBCS) {
-                                processor = new AP4_CencDecryptingProcessor(&key_map);
+                                processor = new AP4_CencDecryptingProcessor(key_map);
                                 break;
                             }
                         }
@@ -96,12 +92,47 @@ int decrypt_in_memory(
 
     // by default, try a standard decrypting processor
     if (processor == NULL) {
-        processor = new AP4_StandardDecryptingProcessor(&key_map);
+        processor = new AP4_StandardDecryptingProcessor(key_map);
     }
-    
+
     delete input_file;
     input_file = NULL;
-    input->Seek(0);
+    stream.Seek(0);
+    return processor;
+}
+
+class ProgressNotifier : public AP4_Processor::ProgressListener {
+public:
+    ProgressNotifier(void* data, rust_progress_callback callback) : m_Data(data), m_Callback(callback) {}
+
+    AP4_Result OnProgress(unsigned int step, unsigned int total) {
+        m_Callback(m_Data, step, total);
+        return AP4_SUCCESS;
+    }
+
+private:
+    void*                  m_Data;
+    rust_progress_callback m_Callback;
+};
+
+int decrypt_in_memory(
+    const unsigned char data[],
+    unsigned int data_size,
+    const char* keyids[],
+    const char* keys[],
+    int nkeys,
+    void* decrypted_data,
+    rust_store_callback callback
+) {
+    // create a key map object to hold keys
+    AP4_ProtectionKeyMap key_map;
+    int key_result = create_key_map(key_map, keyids, keys, nkeys);
+    if (key_result) {
+        return key_result;
+    }
+
+    AP4_MemoryByteStream* input = new AP4_MemoryByteStream(data, data_size);
+    AP4_Processor* processor = create_processor(*input, &key_map);
 
     AP4_MemoryByteStream* output = new AP4_MemoryByteStream();
     AP4_AtomFactory atom_factory;
@@ -131,85 +162,13 @@ int decrypt_in_memory_with_fragments_info(
 ) {
     // create a key map object to hold keys
     AP4_ProtectionKeyMap key_map;
-
-    for (int i = 0; i < nkeys; i++)
-    {
-        unsigned char key[16];
-        unsigned int  track_id = 0;
-        unsigned char kid[16];
-
-        if (strlen(keyids[i]) == 32) {
-            if (AP4_ParseHex(keyids[i], kid, 16)) {
-                return 100;
-            }
-        } else {
-            track_id = (unsigned int)strtoul(keyids[i], NULL, 10);
-            if (track_id == 0) {
-                return 101;
-            }
-        }
-        if (AP4_ParseHex(keys[i], key, 16)) {
-            return 102;
-        }
-        // set the key in the map
-        if (track_id) {
-            key_map.SetKey(track_id, key, 16);
-        } else {
-            key_map.SetKeyForKid(kid, key, 16);
-        }
+    int key_result = create_key_map(key_map, keyids, keys, nkeys);
+    if (key_result) {
+        return key_result;
     }
 
     AP4_MemoryByteStream* fragments_info = new AP4_MemoryByteStream(fragments_info_data, fragments_info_data_size);
-
-    // create the decrypting processor
-    AP4_Processor* processor = NULL;
-    AP4_File* input_file = new AP4_File(*fragments_info);
-    // input_file->SetFileType()
-    AP4_FtypAtom* ftyp = input_file->GetFileType();
-    if (ftyp) {
-        if (ftyp->GetMajorBrand() == AP4_OMA_DCF_BRAND_ODCF || ftyp->HasCompatibleBrand(AP4_OMA_DCF_BRAND_ODCF)) {
-            processor = new AP4_OmaDcfDecryptingProcessor(&key_map);
-        } else if (ftyp->GetMajorBrand() == AP4_MARLIN_BRAND_MGSV || ftyp->HasCompatibleBrand(AP4_MARLIN_BRAND_MGSV)) {
-            processor = new AP4_MarlinIpmpDecryptingProcessor(&key_map);
-        } else if (ftyp->GetMajorBrand() == AP4_PIFF_BRAND || ftyp->HasCompatibleBrand(AP4_PIFF_BRAND)) {
-            processor = new AP4_CencDecryptingProcessor(&key_map);
-        }
-    }
-    if (processor == NULL) {
-        // no ftyp, look at the sample description of the tracks first
-        AP4_Movie* movie = input_file->GetMovie();
-        if (movie) {
-            AP4_List<AP4_Track>& tracks = movie->GetTracks();
-            for (unsigned int i=0; i<tracks.ItemCount(); i++) {
-                AP4_Track* track = NULL;
-                tracks.Get(i, track);
-                if (track) {
-                    AP4_SampleDescription* sdesc = track->GetSampleDescription(0);
-                    if (sdesc && sdesc->GetType() == AP4_SampleDescription::TYPE_PROTECTED) {
-                        AP4_ProtectedSampleDescription* psdesc = AP4_DYNAMIC_CAST(AP4_ProtectedSampleDescription, sdesc);
-                        if (psdesc) {
-                            if (psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CENC ||
-                                psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CBC1 ||
-                                psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CENS ||
-                                psdesc->GetSchemeType() == AP4_PROTECTION_SCHEME_TYPE_CBCS) {
-                                processor = new AP4_CencDecryptingProcessor(&key_map);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // by default, try a standard decrypting processor
-    if (processor == NULL) {
-        processor = new AP4_StandardDecryptingProcessor(&key_map);
-    }
-    
-    delete input_file;
-    input_file = NULL;
-    fragments_info->Seek(0);
+    AP4_Processor* processor = create_processor(*fragments_info, &key_map);
 
     AP4_MemoryByteStream* input = new AP4_MemoryByteStream(data, data_size);
     AP4_MemoryByteStream* output = new AP4_MemoryByteStream();
@@ -227,3 +186,68 @@ int decrypt_in_memory_with_fragments_info(
     output->Release();
     return 0;
 }
+
+int decrypt_file(
+    const char* input_path,
+    const char* output_path,
+    const char* keyids[],
+    const char* keys[],
+    int nkeys,
+    const char* fragments_info_path,
+    void* progress_data,
+    rust_progress_callback progress_callback
+) {
+    // create a key map object to hold keys
+    AP4_ProtectionKeyMap key_map;
+    int key_result = create_key_map(key_map, keyids, keys, nkeys);
+    if (key_result) {
+        return key_result;
+    }
+
+    // file byte streams use 64-bit positions, so there is no limit on file size
+    AP4_ByteStream* input = NULL;
+    AP4_Result result = AP4_FileByteStream::Create(input_path, AP4_FileByteStream::STREAM_MODE_READ, input);
+    if (AP4_FAILED(result)) {
+        return result;
+    }
+
+    AP4_ByteStream* fragments_info = NULL;
+    if (fragments_info_path) {
+        result = AP4_FileByteStream::Create(fragments_info_path, AP4_FileByteStream::STREAM_MODE_READ, fragments_info);
+        if (AP4_FAILED(result)) {
+            input->Release();
+            return result;
+        }
+    }
+
+    AP4_ByteStream* output = NULL;
+    result = AP4_FileByteStream::Create(output_path, AP4_FileByteStream::STREAM_MODE_WRITE, output);
+    if (AP4_FAILED(result)) {
+        input->Release();
+        if (fragments_info) fragments_info->Release();
+        return result;
+    }
+
+    AP4_Processor* processor = create_processor(fragments_info ? *fragments_info : *input, &key_map);
+    ProgressNotifier* listener = progress_callback ? new ProgressNotifier(progress_data, progress_callback) : NULL;
+    AP4_AtomFactory atom_factory;
+
+    if (fragments_info) {
+        result = processor->Process(*input, *output, *fragments_info, listener, atom_factory);
+    } else {
+        result = processor->Process(*input, *output, listener, atom_factory);
+    }
+
+    // cleanup
+    delete processor;
+    delete listener;
+    input->Release();
+    if (fragments_info) fragments_info->Release();
+    output->Release();
+
+    return AP4_FAILED(result) ? result : 0;
+}
+
+const char* result_text(int result) {
+    return AP4_ResultText(result);
+}
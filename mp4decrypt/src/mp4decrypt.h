@@ -7,6 +7,7 @@ extern "C"
 #endif
 
     typedef void (*rust_store_callback)(void *, const unsigned char *data, unsigned int length);
+    typedef void (*rust_progress_callback)(void *, unsigned int step, unsigned int total);
     int decrypt_in_memory(
         const unsigned char data[],
         unsigned int data_size,
@@ -27,6 +28,17 @@ extern "C"
         const unsigned char fragments_info_data[],
         unsigned int fragments_info_data_size
     );
+    int decrypt_file(
+        const char* input_path,
+        const char* output_path,
+        const char* keyids[],
+        const char* keys[],
+        int nkeys,
+        const char* fragments_info_path,
+        void* progress_data,
+        rust_progress_callback progress_callback
+    );
+    const char* result_text(int result);
 
 #ifdef __cplusplus
 }
@@ -0,0 +1,544 @@
+/*
+    REFERENCES
+    ----------
+
+    1. ISO/IEC 14496-12 (ISO base media file format)
+    2. ISO/IEC 23001-7 (Common encryption in ISO base media file format files)
+    3. https://learn.microsoft.com/en-us/iis/media/smooth-streaming/protected-interoperable-file-format
+
+*/
+
+use crate::{Error, Reader, Result};
+
+/// Extended type of PIFF sample encryption box.
+pub(super) const PIFF_SENC_UUID: [u8; 16] = [
+    0xa2, 0x39, 0x4f, 0x52, 0x5a, 0x9b, 0x4f, 0x14, 0xa2, 0x44, 0x6c, 0x42, 0x7c, 0x64, 0x8d, 0xf4,
+];
+/// Extended type of PIFF track encryption box.
+pub(super) const PIFF_TENC_UUID: [u8; 16] = [
+    0x89, 0x74, 0xdb, 0xce, 0x7b, 0xe7, 0x4c, 0x51, 0x84, 0xf9, 0x71, 0x48, 0xf9, 0x88, 0x25, 0x54,
+];
+
+pub(super) struct TKHDBox {
+    pub(super) track_id: u32,
+}
+
+impl TKHDBox {
+    /// Parses a TKHD Box.
+    pub(super) fn parse(reader: &mut Reader, version: u32) -> Result<Self> {
+        // Skip "creation_time" and "modification_time".
+        let skip = if version == 1 { 16 } else { 8 };
+        reader
+            .skip(skip)
+            .map_err(|_| Error::new_read_err(format!("TKHD box times ({} bytes)", skip)))?;
+
+        Ok(Self {
+            track_id: reader
+                .read_u32()
+                .map_err(|_| Error::new_read_err("TKHD box track id (u32)"))?,
+        })
+    }
+}
+
+pub(super) struct TREXBox {
+    pub(super) track_id: u32,
+    pub(super) default_sample_description_index: u32,
+    pub(super) default_sample_size: u32,
+}
+
+impl TREXBox {
+    /// Parses a TREX Box.
+    pub(super) fn parse(reader: &mut Reader) -> Result<Self> {
+        let track_id = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("TREX box track id (u32)"))?;
+        let default_sample_description_index = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("TREX box default sample description index (u32)"))?;
+        reader
+            .skip(4)
+            .map_err(|_| Error::new_read_err("TREX box default sample duration (4 bytes)"))?;
+        let default_sample_size = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("TREX box default sample size (u32)"))?;
+
+        Ok(Self {
+            track_id,
+            default_sample_description_index,
+            default_sample_size,
+        })
+    }
+}
+
+/// Default encryption parameters of a track,
+/// also used for sample groups of `seig` grouping type.
+#[derive(Clone, Default)]
+pub(super) struct TENCBox {
+    pub(super) crypt_byte_block: u8,
+    pub(super) skip_byte_block: u8,
+    pub(super) is_protected: bool,
+    pub(super) per_sample_iv_size: u8,
+    pub(super) kid: [u8; 16],
+    pub(super) constant_iv: Option<Vec<u8>>,
+}
+
+impl TENCBox {
+    /// Parses a TENC Box.
+    pub(super) fn parse(reader: &mut Reader, version: u32) -> Result<Self> {
+        reader
+            .skip(1)
+            .map_err(|_| Error::new_read_err("TENC box reserved (1 byte)"))?;
+
+        let pattern = reader
+            .read_u8()
+            .map_err(|_| Error::new_read_err("TENC box pattern (u8)"))?;
+        let pattern = if version == 0 { 0 } else { pattern };
+
+        Self::parse_entry(reader, pattern, "TENC")
+    }
+
+    /// Parses a PIFF track encryption box i.e. `uuid` box with [PIFF_TENC_UUID] extended type.
+    pub(super) fn parse_piff(reader: &mut Reader) -> Result<Self> {
+        let algorithm_id = reader
+            .read_bytes_u8(3)
+            .map_err(|_| Error::new_read_err("PIFF TENC box algorithm id (3 bytes)"))?;
+        let per_sample_iv_size = reader
+            .read_u8()
+            .map_err(|_| Error::new_read_err("PIFF TENC box iv size (u8)"))?;
+        let kid = reader
+            .read_bytes_u8(16)
+            .map_err(|_| Error::new_read_err("PIFF TENC box kid (16 bytes)"))?
+            .try_into()
+            .unwrap();
+
+        Ok(Self {
+            is_protected: algorithm_id != [0, 0, 0],
+            per_sample_iv_size,
+            kid,
+            ..Default::default()
+        })
+    }
+
+    /// Parses a `seig` sample group description entry.
+    pub(super) fn parse_seig(reader: &mut Reader) -> Result<Self> {
+        reader
+            .skip(1)
+            .map_err(|_| Error::new_read_err("SEIG entry reserved (1 byte)"))?;
+
+        let pattern = reader
+            .read_u8()
+            .map_err(|_| Error::new_read_err("SEIG entry pattern (u8)"))?;
+
+        Self::parse_entry(reader, pattern, "SEIG")
+    }
+
+    fn parse_entry(reader: &mut Reader, pattern: u8, name: &str) -> Result<Self> {
+        let is_protected = reader
+            .read_u8()
+            .map_err(|_| Error::new_read_err(format!("{} box is protected (u8)", name)))?
+            != 0;
+        let per_sample_iv_size = reader
+            .read_u8()
+            .map_err(|_| Error::new_read_err(format!("{} box per sample iv size (u8)", name)))?;
+        let kid = reader
+            .read_bytes_u8(16)
+            .map_err(|_| Error::new_read_err(format!("{} box kid (16 bytes)", name)))?
+            .try_into()
+            .unwrap();
+        let mut constant_iv = None;
+
+        if is_protected && per_sample_iv_size == 0 {
+            let constant_iv_size = reader
+                .read_u8()
+                .map_err(|_| Error::new_read_err(format!("{} box constant iv size (u8)", name)))?;
+            constant_iv = Some(
+                reader
+                    .read_bytes_u8(constant_iv_size as usize)
+                    .map_err(|_| {
+                        Error::new_read_err(format!(
+                            "{} box constant iv ({} bytes)",
+                            name, constant_iv_size
+                        ))
+                    })?,
+            );
+        }
+
+        Ok(Self {
+            crypt_byte_block: pattern >> 4,
+            skip_byte_block: pattern & 0x0f,
+            is_protected,
+            per_sample_iv_size,
+            kid,
+            constant_iv,
+        })
+    }
+}
+
+pub(super) struct TFHDBox {
+    pub(super) track_id: u32,
+    pub(super) base_data_offset: Option<u64>,
+    pub(super) sample_description_index: Option<u32>,
+    pub(super) default_sample_size: Option<u32>,
+    /// If true, base data offset is the start of `moof` box.
+    pub(super) default_base_is_moof: bool,
+}
+
+impl TFHDBox {
+    /// Parses a TFHD Box.
+    pub(super) fn parse(reader: &mut Reader, flags: u32) -> Result<Self> {
+        let track_id = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("TFHD box track id (u32)"))?;
+        let mut base_data_offset = None;
+        let mut sample_description_index = None;
+        let mut default_sample_size = None;
+
+        if (flags & 0x000001) != 0 {
+            base_data_offset = Some(
+                reader
+                    .read_u64()
+                    .map_err(|_| Error::new_read_err("TFHD box base data offset (u64)"))?,
+            );
+        }
+
+        if (flags & 0x000002) != 0 {
+            sample_description_index = Some(
+                reader
+                    .read_u32()
+                    .map_err(|_| Error::new_read_err("TFHD box sample description index (u32)"))?,
+            );
+        }
+
+        // Skip "default_sample_duration" if present.
+        if (flags & 0x000008) != 0 {
+            reader
+                .skip(4)
+                .map_err(|_| Error::new_read_err("TFHD box default sample duration (4 bytes)"))?;
+        }
+
+        if (flags & 0x000010) != 0 {
+            default_sample_size = Some(
+                reader
+                    .read_u32()
+                    .map_err(|_| Error::new_read_err("TFHD box default sample size (u32)"))?,
+            );
+        }
+
+        Ok(Self {
+            track_id,
+            base_data_offset,
+            sample_description_index,
+            default_sample_size,
+            default_base_is_moof: (flags & 0x020000) != 0,
+        })
+    }
+}
+
+pub(super) struct TRUNBox {
+    /// If specified via flags, this indicate the offset of first sample from base data offset.
+    pub(super) data_offset: Option<i32>,
+    /// Size of each sample, if specified via flags.
+    pub(super) sample_sizes: Vec<Option<u32>>,
+}
+
+impl TRUNBox {
+    /// Parses a TRUN Box.
+    pub(super) fn parse(reader: &mut Reader, flags: u32) -> Result<Self> {
+        let sample_count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("TRUN box sample count (u32)"))?;
+        let mut data_offset = None;
+        let mut sample_sizes = vec![];
+
+        if (flags & 0x000001) != 0 {
+            data_offset = Some(
+                reader
+                    .read_i32()
+                    .map_err(|_| Error::new_read_err("TRUN box data offset (i32)"))?,
+            );
+        }
+
+        // Skip "first_sample_flags" if present.
+        if (flags & 0x000004) != 0 {
+            reader
+                .skip(4)
+                .map_err(|_| Error::new_read_err("TRUN box first sample flags (4 bytes)"))?;
+        }
+
+        for _ in 0..sample_count {
+            // Skip "sample_duration" if present.
+            if (flags & 0x000100) != 0 {
+                reader
+                    .skip(4)
+                    .map_err(|_| Error::new_read_err("TRUN box sample duration (4 bytes)"))?;
+            }
+
+            sample_sizes.push(if (flags & 0x000200) != 0 {
+                Some(
+                    reader
+                        .read_u32()
+                        .map_err(|_| Error::new_read_err("TRUN box sample size (u32)"))?,
+                )
+            } else {
+                None
+            });
+
+            // Skip "sample_flags" and "sample_composition_time_offset" if present.
+            for flag in [0x000400, 0x000800] {
+                if (flags & flag) != 0 {
+                    reader
+                        .skip(4)
+                        .map_err(|_| Error::new_read_err("TRUN box sample data (4 bytes)"))?;
+                }
+            }
+        }
+
+        Ok(Self {
+            data_offset,
+            sample_sizes,
+        })
+    }
+}
+
+/// Sample encryption box, also used for PIFF sample encryption box.
+/// Entries are parsed later, as size of initialization vectors is only known from track and sample groups.
+pub(super) struct SENCBox {
+    pub(super) use_subsamples: bool,
+    /// Overridden size of initialization vectors (PIFF only).
+    pub(super) iv_size: Option<u8>,
+    pub(super) sample_count: u32,
+    pub(super) data: Vec<u8>,
+}
+
+impl SENCBox {
+    /// Parses a SENC Box.
+    pub(super) fn parse(reader: &mut Reader, flags: u32) -> Result<Self> {
+        Self::parse_with_iv_size(reader, flags, None)
+    }
+
+    /// Parses a PIFF sample encryption box i.e. `uuid` box with [PIFF_SENC_UUID] extended type.
+    pub(super) fn parse_piff(reader: &mut Reader, flags: u32) -> Result<Self> {
+        let mut iv_size = None;
+
+        if (flags & 0x000001) != 0 {
+            reader
+                .skip(3)
+                .map_err(|_| Error::new_read_err("PIFF SENC box algorithm id (3 bytes)"))?;
+            iv_size = Some(
+                reader
+                    .read_u8()
+                    .map_err(|_| Error::new_read_err("PIFF SENC box iv size (u8)"))?,
+            );
+            reader
+                .skip(16)
+                .map_err(|_| Error::new_read_err("PIFF SENC box kid (16 bytes)"))?;
+        }
+
+        Self::parse_with_iv_size(reader, flags, iv_size)
+    }
+
+    fn parse_with_iv_size(reader: &mut Reader, flags: u32, iv_size: Option<u8>) -> Result<Self> {
+        let sample_count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SENC box sample count (u32)"))?;
+        let remaining = reader.get_length() - reader.get_position();
+
+        Ok(Self {
+            use_subsamples: (flags & 0x000002) != 0,
+            iv_size,
+            sample_count,
+            data: reader
+                .read_bytes_u8(remaining as usize)
+                .map_err(|_| Error::new_read_err("SENC box entries"))?,
+        })
+    }
+}
+
+pub(super) struct SAIZBox {
+    pub(super) default_sample_info_size: u8,
+    pub(super) sample_info_sizes: Vec<u8>,
+    pub(super) sample_count: u32,
+}
+
+impl SAIZBox {
+    /// Parses a SAIZ Box.
+    pub(super) fn parse(reader: &mut Reader, flags: u32) -> Result<Self> {
+        // Skip "aux_info_type" and "aux_info_type_parameter" if present.
+        if (flags & 0x000001) != 0 {
+            reader
+                .skip(8)
+                .map_err(|_| Error::new_read_err("SAIZ box aux info type (8 bytes)"))?;
+        }
+
+        let default_sample_info_size = reader
+            .read_u8()
+            .map_err(|_| Error::new_read_err("SAIZ box default sample info size (u8)"))?;
+        let sample_count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SAIZ box sample count (u32)"))?;
+        let sample_info_sizes = if default_sample_info_size == 0 {
+            reader
+                .read_bytes_u8(sample_count as usize)
+                .map_err(|_| Error::new_read_err("SAIZ box sample info sizes"))?
+        } else {
+            vec![]
+        };
+
+        Ok(Self {
+            default_sample_info_size,
+            sample_info_sizes,
+            sample_count,
+        })
+    }
+
+    pub(super) fn sample_info_size(&self, index: usize) -> u8 {
+        if self.default_sample_info_size == 0 {
+            self.sample_info_sizes.get(index).copied().unwrap_or(0)
+        } else {
+            self.default_sample_info_size
+        }
+    }
+}
+
+pub(super) struct SAIOBox {
+    /// Offset of auxiliary information of first chunk (track run).
+    pub(super) offset: Option<u64>,
+}
+
+impl SAIOBox {
+    /// Parses a SAIO Box.
+    pub(super) fn parse(reader: &mut Reader, version: u32, flags: u32) -> Result<Self> {
+        // Skip "aux_info_type" and "aux_info_type_parameter" if present.
+        if (flags & 0x000001) != 0 {
+            reader
+                .skip(8)
+                .map_err(|_| Error::new_read_err("SAIO box aux info type (8 bytes)"))?;
+        }
+
+        let entry_count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SAIO box entry count (u32)"))?;
+        let mut offset = None;
+
+        if entry_count > 0 {
+            offset = Some(if version == 0 {
+                reader
+                    .read_u32()
+                    .map_err(|_| Error::new_read_err("SAIO box offset (u32)"))?
+                    as u64
+            } else {
+                reader
+                    .read_u64()
+                    .map_err(|_| Error::new_read_err("SAIO box offset (u64)"))?
+            });
+        }
+
+        Ok(Self { offset })
+    }
+}
+
+/// Sample group description box of `seig` grouping type.
+pub(super) struct SGPDBox {
+    pub(super) entries: Vec<TENCBox>,
+}
+
+impl SGPDBox {
+    /// Parses a SGPD Box. Returns none if grouping type is not `seig`.
+    pub(super) fn parse(reader: &mut Reader, version: u32) -> Result<Option<Self>> {
+        let grouping_type = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SGPD box grouping type (u32)"))?;
+
+        if grouping_type != u32::from_be_bytes(*b"seig") {
+            return Ok(None);
+        }
+
+        let mut default_length = 0;
+
+        if version == 1 {
+            default_length = reader
+                .read_u32()
+                .map_err(|_| Error::new_read_err("SGPD box default length (u32)"))?;
+        }
+
+        if version >= 2 {
+            reader.skip(4).map_err(|_| {
+                Error::new_read_err("SGPD box default sample description index (4 bytes)")
+            })?;
+        }
+
+        let entry_count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SGPD box entry count (u32)"))?;
+        let mut entries = vec![];
+
+        for _ in 0..entry_count {
+            if version == 1 && default_length == 0 {
+                reader
+                    .skip(4)
+                    .map_err(|_| Error::new_read_err("SGPD box description length (4 bytes)"))?;
+            }
+
+            entries.push(TENCBox::parse_seig(reader)?);
+        }
+
+        Ok(Some(Self { entries }))
+    }
+}
+
+/// Sample to group box of `seig` grouping type.
+pub(super) struct SBGPBox {
+    /// Sample count and group description index of each entry.
+    pub(super) entries: Vec<(u32, u32)>,
+}
+
+impl SBGPBox {
+    /// Parses a SBGP Box. Returns none if grouping type is not `seig`.
+    pub(super) fn parse(reader: &mut Reader, version: u32) -> Result<Option<Self>> {
+        let grouping_type = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SBGP box grouping type (u32)"))?;
+
+        if grouping_type != u32::from_be_bytes(*b"seig") {
+            return Ok(None);
+        }
+
+        if version == 1 {
+            reader
+                .skip(4)
+                .map_err(|_| Error::new_read_err("SBGP box grouping type parameter (4 bytes)"))?;
+        }
+
+        let entry_count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("SBGP box entry count (u32)"))?;
+        let mut entries = vec![];
+
+        for _ in 0..entry_count {
+            let sample_count = reader
+                .read_u32()
+                .map_err(|_| Error::new_read_err("SBGP box sample count (u32)"))?;
+            let group_description_index = reader
+                .read_u32()
+                .map_err(|_| Error::new_read_err("SBGP box group description index (u32)"))?;
+            entries.push((sample_count, group_description_index));
+        }
+
+        Ok(Some(Self { entries }))
+    }
+
+    /// Group description index of a sample, 0 if sample is not a member of any group.
+    pub(super) fn group_description_index(&self, index: usize) -> u32 {
+        let mut count = 0;
+
+        for (sample_count, group_description_index) in &self.entries {
+            count += *sample_count as usize;
+
+            if index < count {
+                return *group_description_index;
+            }
+        }
+
+        0
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{Error, Result};
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt},
+    Aes128,
+};
+
+/// Protection scheme of a track, read from scheme type (`schm`) box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Scheme {
+    /// AES-CBC full sample and video NAL subsample encryption.
+    Cbc1,
+    /// AES-CBC subsample pattern encryption with constant iv.
+    Cbcs,
+    /// AES-CTR full sample and video NAL subsample encryption.
+    Cenc,
+    /// AES-CTR subsample pattern encryption.
+    Cens,
+}
+
+impl Scheme {
+    pub(super) fn from_scheme_type(scheme_type: &str) -> Result<Self> {
+        Ok(match scheme_type {
+            "cbc1" => Self::Cbc1,
+            "cbcs" => Self::Cbcs,
+            "cenc" | "piff" => Self::Cenc,
+            "cens" => Self::Cens,
+            x => {
+                return Err(Error::new(format!(
+                    "{} protection scheme is not supported",
+                    x
+                )))
+            }
+        })
+    }
+}
+
+/// Encryption parameters of a single sample.
+pub(super) struct SampleCipher<'a> {
+    pub(super) cipher: &'a Aes128,
+    pub(super) scheme: Scheme,
+    pub(super) iv: &'a [u8],
+    /// Clear and encrypted bytes of each subsample. Whole sample is encrypted if empty.
+    pub(super) subsamples: &'a [(u16, u32)],
+    pub(super) crypt_byte_block: u8,
+    pub(super) skip_byte_block: u8,
+}
+
+impl SampleCipher<'_> {
+    /// Decrypt a sample in place.
+    pub(super) fn decrypt(&self, sample: &mut [u8]) -> Result<()> {
+        let mut iv = [0; 16];
+
+        match self.iv.len() {
+            8 | 16 => iv[..self.iv.len()].copy_from_slice(self.iv),
+            x => return Err(Error::new(format!("{} bytes iv is not supported", x))),
+        }
+
+        let mut ranges = vec![];
+
+        if self.subsamples.is_empty() {
+            ranges.push(0..sample.len());
+        } else {
+            let mut position = 0;
+
+            for (clear_bytes, encrypted_bytes) in self.subsamples {
+                position += *clear_bytes as usize;
+                ranges.push(position..(position + *encrypted_bytes as usize));
+                position += *encrypted_bytes as usize;
+            }
+
+            if position > sample.len() {
+                return Err(Error::new(format!(
+                    "subsamples ({} bytes) are larger than sample ({} bytes)",
+                    position,
+                    sample.len()
+                )));
+            }
+        }
+
+        // Pattern is only applicable for cens and cbcs schemes, 0:0 pattern is same as no pattern.
+        let pattern = matches!(self.scheme, Scheme::Cbcs | Scheme::Cens)
+            && (self.crypt_byte_block != 0 || self.skip_byte_block != 0);
+
+        match self.scheme {
+            Scheme::Cenc | Scheme::Cens => {
+                // Counter continues across subsamples.
+                let mut ctr = Ctr::new(self.cipher, iv);
+
+                for range in ranges {
+                    if pattern {
+                        self.apply_pattern(&mut sample[range], |x| ctr.apply(x));
+                    } else {
+                        ctr.apply(&mut sample[range]);
+                    }
+                }
+            }
+            Scheme::Cbc1 | Scheme::Cbcs => {
+                // Chaining continues across subsamples for cbc1, whereas cbcs resets iv for every subsample.
+                let mut cbc = Cbc::new(self.cipher, iv);
+
+                for range in ranges {
+                    if self.scheme == Scheme::Cbcs {
+                        cbc = Cbc::new(self.cipher, iv);
+                    }
+
+                    if pattern {
+                        self.apply_pattern(&mut sample[range], |x| cbc.apply(x));
+                    } else {
+                        let len = range.len() - (range.len() % 16);
+                        cbc.apply(&mut sample[range.start..(range.start + len)]);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt `crypt_byte_block` blocks followed by `skip_byte_block` clear blocks repeatedly.
+    /// Trailing partial block is always left clear.
+    fn apply_pattern(&self, data: &mut [u8], mut decrypt: impl FnMut(&mut [u8])) {
+        let crypt_bytes = self.crypt_byte_block as usize * 16;
+        let skip_bytes = self.skip_byte_block as usize * 16;
+        let mut position = 0;
+
+        while data.len() - position >= 16 {
+            let len = crypt_bytes.min((data.len() - position) / 16 * 16);
+            decrypt(&mut data[position..(position + len)]);
+            position += len + skip_bytes.min(data.len() - position - len);
+        }
+    }
+}
+
+/// AES-CTR keystream with 64-bit block counter.
+struct Ctr<'a> {
+    cipher: &'a Aes128,
+    counter: [u8; 16],
+    keystream: [u8; 16],
+    position: usize,
+}
+
+impl<'a> Ctr<'a> {
+    fn new(cipher: &'a Aes128, iv: [u8; 16]) -> Self {
+        Self {
+            cipher,
+            counter: iv,
+            keystream: [0; 16],
+            position: 16,
+        }
+    }
+
+    fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            if self.position == 16 {
+                let mut block = GenericArray::from(self.counter);
+                self.cipher.encrypt_block(&mut block);
+                self.keystream = block.into();
+                self.position = 0;
+
+                let count = u64::from_be_bytes(self.counter[8..].try_into().unwrap());
+                self.counter[8..].copy_from_slice(&count.wrapping_add(1).to_be_bytes());
+            }
+
+            *byte ^= self.keystream[self.position];
+            self.position += 1;
+        }
+    }
+}
+
+/// AES-CBC decryptor which keeps chaining state between calls.
+struct Cbc<'a> {
+    cipher: &'a Aes128,
+    previous: [u8; 16],
+}
+
+impl<'a> Cbc<'a> {
+    fn new(cipher: &'a Aes128, iv: [u8; 16]) -> Self {
+        Self {
+            cipher,
+            previous: iv,
+        }
+    }
+
+    /// Decrypt whole blocks of data.
+    fn apply(&mut self, data: &mut [u8]) {
+        for chunk in data.chunks_exact_mut(16) {
+            let encrypted: [u8; 16] = chunk.try_into().unwrap();
+            let block = GenericArray::from_mut_slice(chunk);
+            self.cipher.decrypt_block(block);
+
+            for (x, y) in block.iter_mut().zip(self.previous) {
+                *x ^= y;
+            }
+
+            self.previous = encrypted;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Known answers are generated using python cryptography package.
+
+    use super::*;
+    use aes::cipher::KeyInit;
+
+    const KEY: &str = "100b6c20940f779a4589152b57d2dacb";
+    const IV_8: &str = "0123456789abcdef";
+    const CONSTANT_IV: &str = "00112233445566778899aabbccddeeff";
+
+    /// Decrypt `encrypted` sample and compare it with `0, 1, 2, ...` plain sample.
+    fn check(
+        scheme: Scheme,
+        iv: &str,
+        subsamples: &[(u16, u32)],
+        pattern: (u8, u8),
+        encrypted: &str,
+    ) {
+        let cipher = Aes128::new_from_slice(&hex::decode(KEY).unwrap()).unwrap();
+        let iv = hex::decode(iv).unwrap();
+        let mut sample = hex::decode(encrypted).unwrap();
+
+        SampleCipher {
+            cipher: &cipher,
+            scheme,
+            iv: &iv,
+            subsamples,
+            crypt_byte_block: pattern.0,
+            skip_byte_block: pattern.1,
+        }
+        .decrypt(&mut sample)
+        .unwrap();
+
+        let plain = (0..sample.len()).map(|x| x as u8).collect::<Vec<_>>();
+        assert_eq!(sample, plain);
+    }
+
+    #[test]
+    fn cenc_full_sample() {
+        check(
+            Scheme::Cenc,
+            IV_8,
+            &[],
+            (0, 0),
+            "5e9458e3535d4b5cecd9a547a570601d4d7b77725cd52a52aaa121e23bc4e63c16af2e10b3493605",
+        );
+    }
+
+    #[test]
+    fn cenc_subsamples() {
+        // Counter continues across subsamples.
+        check(
+            Scheme::Cenc,
+            IV_8,
+            &[(5, 20), (3, 36)],
+            (0, 0),
+            "00010203045b935de85e524657e9dea05cb86f7d06487c7279191a1b54dd225a\
+             929919da03fcde041ea72618bb413e0d20616614f12d43534de8ff32cf82f557",
+        );
+    }
+
+    #[test]
+    fn cbcs_pattern_constant_iv() {
+        // 1:9 pattern, only these blocks are encrypted and iv is reset for every subsample.
+        let mut encrypted = (0..400).map(|x| x as u8).collect::<Vec<_>>();
+
+        for (position, block) in [
+            (10, "71bf7015986f42a8069dbce9e583f599"),
+            (170, "a530eddbf5b2523e3cff3c7936889157"),
+            (208, "bd85b15d3a9c95103c29e3303b8d69c1"),
+            (368, "6cace3899d6f457c864011147cacc20b"),
+        ] {
+            encrypted[position..(position + 16)].copy_from_slice(&hex::decode(block).unwrap());
+        }
+
+        check(
+            Scheme::Cbcs,
+            CONSTANT_IV,
+            &[(10, 190), (8, 192)],
+            (1, 9),
+            &hex::encode(encrypted),
+        );
+    }
+
+    #[test]
+    fn cbcs_trailing_partial_block_clear() {
+        // Last 8 bytes are not a whole block, so they are left clear.
+        check(
+            Scheme::Cbcs,
+            CONSTANT_IV,
+            &[],
+            (0, 0),
+            "53901aec273a3c753f321f64a1dbb9569f32be58923b271319271cb0e4c07d162021222324252627",
+        );
+    }
+}
@@ -0,0 +1,770 @@
+/*
+    REFERENCES
+    ----------
+
+    1. ISO/IEC 23001-7 (Common encryption in ISO base media file format files)
+    2. https://github.com/axiomatic-systems/Bento4/blob/master/Source/C%2B%2B/Core/Ap4CommonEncryption.cpp
+    3. https://github.com/shaka-project/shaka-packager/blob/main/packager/media/crypto/aes_decryptor.cc
+
+*/
+
+//! Decrypt common encryption (`cenc`, `cens`, `cbc1` and `cbcs` schemes) protected mp4 streams.
+//!
+//! Only fragmented mp4 streams are supported, which is the case for DASH, HLS (fmp4) and smooth streaming segments.
+
+mod boxes;
+mod cipher;
+
+use crate::{
+    parser::{self, CallbackType, HandlerResult, Mp4Parser, ParsedBox},
+    Error, Result,
+};
+use aes::{cipher::KeyInit, Aes128};
+use boxes::*;
+use cipher::{SampleCipher, Scheme};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Decrypt encrypted mp4 data stream using given keys.
+/// Samples are decrypted in place and protected sample entries are restored to their original format.
+///
+/// # Arguments
+///
+/// * `data` - Encrypted data stream.
+/// * `keys` - Hashmap of keys for decrypting data stream.
+///   Hashmap `key` is either a track ID in decimal or a 128-bit KID in hex.
+///   Hashmap `value` is a 128-bit key in hex.
+/// * `fragments_info` (optional) - Decrypt the fragments read from data stream, with track info read from this stream.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::collections::HashMap;
+///
+/// let keys = HashMap::from([(
+///     "eb676abbcb345e96bbcf616630f1a3da".to_owned(),
+///     "100b6c20940f779a4589152b57d2dacb".to_owned(),
+/// )]);
+///
+/// let decrypted_data = vsd_mp4::decrypt::mp4decrypt(&[0, 0, 0, 112], keys, None).unwrap();
+/// ```
+pub fn mp4decrypt(
+    data: &[u8],
+    keys: HashMap<String, String>,
+    fragments_info: Option<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let keys = Keys::new(keys)?;
+    let tracks = parse_tracks(fragments_info.as_deref().unwrap_or(data))?;
+    let mut data = data.to_vec();
+
+    // Sample entries are only present in data stream when fragments info is not used.
+    if fragments_info.is_none() {
+        for (track_id, track) in &tracks {
+            for entry in track.entries.values() {
+                if keys.get(*track_id, &entry.tenc.kid).is_some() {
+                    rename_box(&mut data, entry.position, &entry.original_format)?;
+
+                    if let Some(position) = entry.sinf_position {
+                        rename_box(&mut data, position, b"free")?;
+                    }
+                }
+            }
+        }
+    }
+
+    decrypt_fragments(data, tracks, keys)
+}
+
+struct Keys {
+    kids: HashMap<[u8; 16], Aes128>,
+    track_ids: HashMap<u32, Aes128>,
+}
+
+impl Keys {
+    fn new(keys: HashMap<String, String>) -> Result<Self> {
+        let mut kids = HashMap::new();
+        let mut track_ids = HashMap::new();
+
+        for (id, key) in keys {
+            let key = hex::decode(key.trim())
+                .ok()
+                .and_then(|x| Aes128::new_from_slice(&x).ok())
+                .ok_or_else(|| Error::new_decode_err(format!("{} as 128-bit hex key", key)))?;
+            let id = id.trim().replace('-', "");
+
+            // 32 characters long ids are always key ids, even if they only contain digits.
+            if id.len() == 32 {
+                let kid = hex::decode(&id)
+                    .ok()
+                    .and_then(|x| x.try_into().ok())
+                    .ok_or_else(|| {
+                        Error::new_decode_err(format!("{} as 128-bit hex key id", id))
+                    })?;
+                kids.insert(kid, key);
+            } else if let Ok(track_id) = id.parse::<u32>() {
+                track_ids.insert(track_id, key);
+            } else {
+                return Err(Error::new_decode_err(format!(
+                    "{} as track id or 128-bit hex key id",
+                    id
+                )));
+            }
+        }
+
+        Ok(Self { kids, track_ids })
+    }
+
+    /// Key for a key id, falling back to key of track.
+    fn get(&self, track_id: u32, kid: &[u8; 16]) -> Option<&Aes128> {
+        self.kids.get(kid).or_else(|| self.track_ids.get(&track_id))
+    }
+}
+
+#[derive(Default)]
+struct Track {
+    default_sample_description_index: u32,
+    default_sample_size: u32,
+    /// Protected sample entries by their index (starting from 1) in `stsd` box.
+    entries: HashMap<u32, ProtectedEntry>,
+    /// Sample groups of `seig` grouping type from `stbl` box.
+    groups: Vec<TENCBox>,
+}
+
+struct ProtectedEntry {
+    scheme: Scheme,
+    tenc: TENCBox,
+    original_format: [u8; 4],
+    /// Absolute position of sample entry box.
+    position: u64,
+    /// Absolute position of `sinf` box.
+    sinf_position: Option<u64>,
+}
+
+#[derive(Default)]
+struct ProtectedEntryBuilder {
+    index: u32,
+    position: u64,
+    original_format: Option<[u8; 4]>,
+    scheme_type: Option<String>,
+    sinf_position: Option<u64>,
+    tenc: Option<TENCBox>,
+}
+
+#[derive(Default)]
+struct InitState {
+    moov: bool,
+    tracks: HashMap<u32, Track>,
+    trexs: Vec<TREXBox>,
+    track_id: u32,
+    track: Track,
+    /// Absolute positions of sample entries in current `stsd` box.
+    entry_positions: Vec<u64>,
+    entry: Option<ProtectedEntryBuilder>,
+}
+
+/// Create a callback which handles a box using parser state.
+fn with_state<T: 'static>(
+    state: &Arc<Mutex<T>>,
+    handler: fn(&mut T, ParsedBox) -> HandlerResult,
+) -> CallbackType {
+    let state = state.clone();
+    Arc::new(move |_box| handler(&mut state.lock().unwrap(), _box))
+}
+
+/// Parse protected sample entries and defaults of all tracks present in `moov` box.
+fn parse_tracks(data: &[u8]) -> Result<HashMap<u32, Track>> {
+    let state = Arc::new(Mutex::new(InitState::default()));
+
+    let state_c = state.clone();
+    let moov = Arc::new(move |_box| {
+        state_c.lock().unwrap().moov = true;
+        parser::children(_box)
+    });
+
+    let state_c = state.clone();
+    let trak = Arc::new(move |_box| {
+        {
+            let mut state = state_c.lock().unwrap();
+            state.track_id = 0;
+            state.track = Track::default();
+        }
+
+        parser::children(_box)?;
+
+        let mut state = state_c.lock().unwrap();
+        let track = std::mem::take(&mut state.track);
+        let track_id = state.track_id;
+        state.tracks.insert(track_id, track);
+        Ok(())
+    });
+
+    let state_c = state.clone();
+    let stsd = Arc::new(move |_box: ParsedBox| {
+        let mut reader = _box.reader.clone();
+        let count = reader
+            .read_u32()
+            .map_err(|_| Error::new_read_err("STSD box entry count (u32)"))?;
+        let mut entry_positions = vec![];
+
+        for _ in 0..count {
+            entry_positions.push(_box.start + _box.header_size() + reader.get_position());
+            let size = reader
+                .read_u32()
+                .map_err(|_| Error::new_read_err("STSD box entry size (u32)"))?;
+
+            if size < 8 {
+                break;
+            }
+
+            reader
+                .skip(size as u64 - 4)
+                .map_err(|_| Error::new_read_err(format!("STSD box entry ({} bytes)", size)))?;
+        }
+
+        state_c.lock().unwrap().entry_positions = entry_positions;
+        parser::sample_description(_box)
+    });
+
+    let protected_entry = |sample_entry: fn(ParsedBox) -> HandlerResult| -> CallbackType {
+        let state = state.clone();
+        Arc::new(move |_box| {
+            {
+                let mut state = state.lock().unwrap();
+                let index = state
+                    .entry_positions
+                    .iter()
+                    .position(|x| *x == _box.start)
+                    .unwrap_or(0) as u32
+                    + 1;
+                state.entry = Some(ProtectedEntryBuilder {
+                    index,
+                    position: _box.start,
+                    ..Default::default()
+                });
+            }
+
+            sample_entry(_box)?;
+
+            let mut state = state.lock().unwrap();
+
+            if let Some(ProtectedEntryBuilder {
+                index,
+                position,
+                original_format: Some(original_format),
+                scheme_type,
+                sinf_position,
+                tenc: Some(tenc),
+            }) = state.entry.take()
+            {
+                let scheme = Scheme::from_scheme_type(scheme_type.as_deref().unwrap_or("cenc"))?;
+                state.track.entries.insert(
+                    index,
+                    ProtectedEntry {
+                        scheme,
+                        tenc,
+                        original_format,
+                        position,
+                        sinf_position,
+                    },
+                );
+            }
+
+            Ok(())
+        })
+    };
+
+    let state_c = state.clone();
+    let sinf = Arc::new(move |_box: ParsedBox| {
+        if let Some(entry) = &mut state_c.lock().unwrap().entry {
+            entry.sinf_position = Some(_box.start);
+        }
+
+        parser::children(_box)
+    });
+
+    Mp4Parser::default()
+        ._box("moov", moov)
+        ._box("trak", trak)
+        .full_box(
+            "tkhd",
+            with_state(&state, |state, mut _box| {
+                state.track_id = TKHDBox::parse(&mut _box.reader, _box.version.unwrap())?.track_id;
+                Ok(())
+            }),
+        )
+        ._box("mdia", Arc::new(parser::children))
+        ._box("minf", Arc::new(parser::children))
+        ._box("stbl", Arc::new(parser::children))
+        .full_box("stsd", stsd)
+        ._box("encv", protected_entry(parser::visual_sample_entry))
+        ._box("enca", protected_entry(parser::audio_sample_entry))
+        ._box("sinf", sinf)
+        ._box(
+            "frma",
+            with_state(&state, |state, mut _box| {
+                let original_format = _box
+                    .reader
+                    .read_bytes_u8(4)
+                    .map_err(|_| Error::new_read_err("FRMA box data format (4 bytes)"))?;
+
+                if let Some(entry) = &mut state.entry {
+                    entry.original_format = original_format.try_into().ok();
+                }
+
+                Ok(())
+            }),
+        )
+        .full_box(
+            "schm",
+            with_state(&state, |state, mut _box| {
+                let scheme_type = _box
+                    .reader
+                    .read_u32()
+                    .map_err(|_| Error::new_read_err("SCHM box scheme type (u32)"))?;
+                let scheme_type = parser::type_to_string(scheme_type as usize)
+                    .map_err(|_| Error::new_decode_err("SCHM box scheme type (u32) to string"))?;
+
+                if let Some(entry) = &mut state.entry {
+                    entry.scheme_type = Some(scheme_type);
+                }
+
+                Ok(())
+            }),
+        )
+        ._box("schi", Arc::new(parser::children))
+        .full_box(
+            "tenc",
+            with_state(&state, |state, mut _box| {
+                let tenc = TENCBox::parse(&mut _box.reader, _box.version.unwrap())?;
+
+                if let Some(entry) = &mut state.entry {
+                    entry.tenc = Some(tenc);
+                }
+
+                Ok(())
+            }),
+        )
+        ._box(
+            "uuid",
+            with_state(&state, |state, mut _box| {
+                let uuid = _box
+                    .reader
+                    .read_bytes_u8(16)
+                    .map_err(|_| Error::new_read_err("UUID box extended type (16 bytes)"))?;
+
+                if uuid == PIFF_TENC_UUID {
+                    _box.reader
+                        .skip(4)
+                        .map_err(|_| Error::new_read_err("PIFF TENC box version and flags"))?;
+                    let tenc = TENCBox::parse_piff(&mut _box.reader)?;
+
+                    if let Some(entry) = &mut state.entry {
+                        entry.tenc.get_or_insert(tenc);
+                    }
+                }
+
+                Ok(())
+            }),
+        )
+        .full_box(
+            "sgpd",
+            with_state(&state, |state, mut _box| {
+                if let Some(sgpd) = SGPDBox::parse(&mut _box.reader, _box.version.unwrap())? {
+                    state.track.groups = sgpd.entries;
+                }
+
+                Ok(())
+            }),
+        )
+        ._box("mvex", Arc::new(parser::children))
+        .full_box(
+            "trex",
+            with_state(&state, |state, mut _box| {
+                let trex = TREXBox::parse(&mut _box.reader)?;
+                state.trexs.push(trex);
+                Ok(())
+            }),
+        )
+        .parse(data, None, None)?;
+
+    let mut state = state.lock().unwrap();
+
+    if !state.moov {
+        return Err(Error::new(
+            "MOOV box not found, use fragments info for decrypting fragments",
+        ));
+    }
+
+    let trexs = std::mem::take(&mut state.trexs);
+    let mut tracks = std::mem::take(&mut state.tracks);
+
+    for track in tracks.values_mut() {
+        track.default_sample_description_index = 1;
+    }
+
+    for trex in trexs {
+        if let Some(track) = tracks.get_mut(&trex.track_id) {
+            track.default_sample_description_index = trex.default_sample_description_index;
+            track.default_sample_size = trex.default_sample_size;
+        }
+    }
+
+    Ok(tracks)
+}
+
+#[derive(Default)]
+struct Traf {
+    tfhd: Option<TFHDBox>,
+    truns: Vec<TRUNBox>,
+    senc: Option<SENCBox>,
+    saiz: Option<SAIZBox>,
+    saio: Option<SAIOBox>,
+    sbgp: Option<SBGPBox>,
+    sgpd: Option<SGPDBox>,
+    /// Absolute positions of encryption related boxes, which are renamed to `free` after decryption.
+    positions: Vec<u64>,
+}
+
+struct FragmentsState {
+    data: Vec<u8>,
+    tracks: HashMap<u32, Track>,
+    keys: Keys,
+    moof_position: u64,
+    /// End of data of previous track fragment in current `moof` box.
+    data_end: Option<u64>,
+    traf: Traf,
+}
+
+/// Decrypt samples of all track fragments present in data.
+fn decrypt_fragments(data: Vec<u8>, tracks: HashMap<u32, Track>, keys: Keys) -> Result<Vec<u8>> {
+    let state = Arc::new(Mutex::new(FragmentsState {
+        data: data.clone(),
+        tracks,
+        keys,
+        moof_position: 0,
+        data_end: None,
+        traf: Traf::default(),
+    }));
+
+    let state_c = state.clone();
+    let traf = Arc::new(move |_box| {
+        state_c.lock().unwrap().traf = Traf::default();
+        parser::children(_box)?;
+        let mut state = state_c.lock().unwrap();
+        let traf = std::mem::take(&mut state.traf);
+        decrypt_traf(&mut state, traf)
+    });
+
+    let state_c = state.clone();
+    let moof = Arc::new(move |_box: ParsedBox| {
+        {
+            let mut state = state_c.lock().unwrap();
+            state.moof_position = _box.start;
+            state.data_end = None;
+        }
+
+        parser::children(_box)
+    });
+
+    // Samples are decrypted in a copy of data, which is not read by parser.
+    Mp4Parser::default()
+        ._box("moof", moof)
+        ._box("traf", traf)
+        .full_box(
+            "tfhd",
+            with_state(&state, |state, mut _box| {
+                state.traf.tfhd = Some(TFHDBox::parse(&mut _box.reader, _box.flags.unwrap())?);
+                Ok(())
+            }),
+        )
+        .full_box(
+            "trun",
+            with_state(&state, |state, mut _box| {
+                let trun = TRUNBox::parse(&mut _box.reader, _box.flags.unwrap())?;
+                state.traf.truns.push(trun);
+                Ok(())
+            }),
+        )
+        .full_box(
+            "senc",
+            with_state(&state, |state, mut _box| {
+                state.traf.senc = Some(SENCBox::parse(&mut _box.reader, _box.flags.unwrap())?);
+                state.traf.positions.push(_box.start);
+                Ok(())
+            }),
+        )
+        ._box(
+            "uuid",
+            with_state(&state, |state, mut _box| {
+                let uuid = _box
+                    .reader
+                    .read_bytes_u8(16)
+                    .map_err(|_| Error::new_read_err("UUID box extended type (16 bytes)"))?;
+
+                if uuid == PIFF_SENC_UUID {
+                    let flags = _box
+                        .reader
+                        .read_u32()
+                        .map_err(|_| Error::new_read_err("PIFF SENC box version and flags"))?
+                        & 0xFFFFFF;
+                    let senc = SENCBox::parse_piff(&mut _box.reader, flags)?;
+                    state.traf.senc.get_or_insert(senc);
+                    state.traf.positions.push(_box.start);
+                }
+
+                Ok(())
+            }),
+        )
+        .full_box(
+            "saiz",
+            with_state(&state, |state, mut _box| {
+                state.traf.saiz = Some(SAIZBox::parse(&mut _box.reader, _box.flags.unwrap())?);
+                state.traf.positions.push(_box.start);
+                Ok(())
+            }),
+        )
+        .full_box(
+            "saio",
+            with_state(&state, |state, mut _box| {
+                state.traf.saio = Some(SAIOBox::parse(
+                    &mut _box.reader,
+                    _box.version.unwrap(),
+                    _box.flags.unwrap(),
+                )?);
+                state.traf.positions.push(_box.start);
+                Ok(())
+            }),
+        )
+        .full_box(
+            "sbgp",
+            with_state(&state, |state, mut _box| {
+                if let Some(sbgp) = SBGPBox::parse(&mut _box.reader, _box.version.unwrap())? {
+                    state.traf.sbgp = Some(sbgp);
+                    state.traf.positions.push(_box.start);
+                }
+
+                Ok(())
+            }),
+        )
+        .full_box(
+            "sgpd",
+            with_state(&state, |state, mut _box| {
+                if let Some(sgpd) = SGPDBox::parse(&mut _box.reader, _box.version.unwrap())? {
+                    state.traf.sgpd = Some(sgpd);
+                    state.traf.positions.push(_box.start);
+                }
+
+                Ok(())
+            }),
+        )
+        .parse(&data, None, None)?;
+
+    let data = std::mem::take(&mut state.lock().unwrap().data);
+    Ok(data)
+}
+
+/// Per sample initialization vector and subsamples.
+type SampleAuxInfo = (Vec<u8>, Vec<(u16, u32)>);
+
+fn decrypt_traf(state: &mut FragmentsState, traf: Traf) -> HandlerResult {
+    let FragmentsState {
+        data,
+        tracks,
+        keys,
+        moof_position,
+        data_end,
+        ..
+    } = state;
+
+    let Some(tfhd) = traf.tfhd else {
+        return Ok(());
+    };
+    let Some(track) = tracks.get(&tfhd.track_id) else {
+        return Ok(());
+    };
+
+    let base_data_offset = tfhd
+        .base_data_offset
+        .unwrap_or(if tfhd.default_base_is_moof {
+            *moof_position
+        } else {
+            data_end.unwrap_or(*moof_position)
+        });
+    let mut samples = vec![];
+    let mut position = base_data_offset;
+
+    for trun in &traf.truns {
+        if let Some(data_offset) = trun.data_offset {
+            position = (base_data_offset as i64 + data_offset as i64) as u64;
+        }
+
+        for sample_size in &trun.sample_sizes {
+            let sample_size = sample_size
+                .or(tfhd.default_sample_size)
+                .unwrap_or(track.default_sample_size) as u64;
+            samples.push(position..(position + sample_size));
+            position += sample_size;
+        }
+    }
+
+    *data_end = Some(position);
+
+    let sample_description_index = tfhd
+        .sample_description_index
+        .unwrap_or(track.default_sample_description_index);
+    let Some(entry) = track.entries.get(&sample_description_index) else {
+        return Ok(());
+    };
+
+    if keys.get(tfhd.track_id, &entry.tenc.kid).is_none() {
+        return Ok(());
+    }
+
+    // Encryption parameters of samples can be overridden by sample groups.
+    let mut params = vec![];
+
+    for i in 0..samples.len() {
+        params.push(
+            match traf
+                .sbgp
+                .as_ref()
+                .map(|x| x.group_description_index(i))
+                .unwrap_or(0)
+            {
+                0 => Some(&entry.tenc),
+                x if x > 0x10000 => traf
+                    .sgpd
+                    .as_ref()
+                    .and_then(|y| y.entries.get((x - 0x10001) as usize)),
+                x => track.groups.get(x as usize - 1),
+            }
+            .ok_or_else(|| Error::new("SEIG sample group description not found"))?,
+        );
+    }
+
+    let aux_info = if let Some(senc) = &traf.senc {
+        let mut reader = crate::Reader::new(&senc.data, false);
+        let mut aux_info = vec![];
+
+        for tenc in params.iter().take(senc.sample_count as usize) {
+            aux_info.push(read_aux_info(
+                &mut reader,
+                senc.iv_size.unwrap_or(tenc.per_sample_iv_size),
+                senc.use_subsamples,
+            )?);
+        }
+
+        aux_info
+    } else if let (
+        Some(saiz),
+        Some(SAIOBox {
+            offset: Some(offset),
+        }),
+    ) = (&traf.saiz, &traf.saio)
+    {
+        // Unlike sample data, auxiliary information offsets are relative to moof box
+        // when base data offset is not explicitly set.
+        let mut position = (tfhd.base_data_offset.unwrap_or(*moof_position) + offset) as usize;
+        let mut aux_info = vec![];
+
+        for (i, tenc) in params.iter().take(saiz.sample_count as usize).enumerate() {
+            let size = saiz.sample_info_size(i) as usize;
+            let info = data
+                .get(position..(position + size))
+                .ok_or_else(|| Error::new_read_err("sample auxiliary information"))?;
+            let mut reader = crate::Reader::new(info, false);
+            aux_info.push(read_aux_info(
+                &mut reader,
+                tenc.per_sample_iv_size,
+                size > tenc.per_sample_iv_size as usize,
+            )?);
+            position += size;
+        }
+
+        aux_info
+    } else {
+        vec![]
+    };
+
+    for (i, sample) in samples.into_iter().enumerate() {
+        let tenc = params[i];
+
+        if !tenc.is_protected {
+            continue;
+        }
+
+        let Some(cipher) = keys.get(tfhd.track_id, &tenc.kid) else {
+            continue;
+        };
+        let (iv, subsamples) = aux_info
+            .get(i)
+            .map(|(x, y)| (x.as_slice(), y.as_slice()))
+            .unwrap_or_default();
+        let iv = if tenc.per_sample_iv_size == 0 {
+            tenc.constant_iv.as_deref().unwrap_or_default()
+        } else {
+            iv
+        };
+        let sample = data
+            .get_mut((sample.start as usize)..(sample.end as usize))
+            .ok_or_else(|| Error::new_read_err(format!("sample data at {:?}", sample)))?;
+
+        SampleCipher {
+            cipher,
+            scheme: entry.scheme,
+            iv,
+            subsamples,
+            crypt_byte_block: tenc.crypt_byte_block,
+            skip_byte_block: tenc.skip_byte_block,
+        }
+        .decrypt(sample)?;
+    }
+
+    for position in traf.positions {
+        rename_box(data, position, b"free")?;
+    }
+
+    Ok(())
+}
+
+/// Read initialization vector and subsamples of a sample from `senc` box or auxiliary information.
+fn read_aux_info(
+    reader: &mut crate::Reader,
+    iv_size: u8,
+    use_subsamples: bool,
+) -> Result<SampleAuxInfo> {
+    let iv = reader
+        .read_bytes_u8(iv_size as usize)
+        .map_err(|_| Error::new_read_err(format!("sample iv ({} bytes)", iv_size)))?;
+    let mut subsamples = vec![];
+
+    if use_subsamples {
+        let subsample_count = reader
+            .read_u16()
+            .map_err(|_| Error::new_read_err("subsample count (u16)"))?;
+
+        for _ in 0..subsample_count {
+            let clear_bytes = reader
+                .read_u16()
+                .map_err(|_| Error::new_read_err("subsample clear bytes (u16)"))?;
+            let encrypted_bytes = reader
+                .read_u32()
+                .map_err(|_| Error::new_read_err("subsample encrypted bytes (u32)"))?;
+            subsamples.push((clear_bytes, encrypted_bytes));
+        }
+    }
+
+    Ok((iv, subsamples))
+}
+
+/// Change type of a box present at given position.
+fn rename_box(data: &mut [u8], position: u64, name: &[u8; 4]) -> Result<()> {
+    let position = position as usize + 4;
+
+    data.get_mut(position..(position + 4))
+        .ok_or_else(|| Error::new_read_err(format!("box type at {}", position)))?
+        .copy_from_slice(name);
+    Ok(())
+}
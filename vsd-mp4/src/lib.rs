@@ -8,6 +8,7 @@
 //! The following are a list of [Cargo features][https://doc.rust-lang.org/stable/cargo/reference/features.html#the-features-section] that can be
 //! enabled or disabled:
 //! 
+//! - **decrypt**: Enables support for decrypting `CENC` protected streams (`cenc`, `cens`, `cbc1` and `cbcs` schemes).
 //! - **pssh**: Enables support for parsing `PSSH` boxes.
 //! - **text-ttml**: Enables support for extracting ttml subtitles. 
 //! - **text-vtt**: Enables support for extracting vtt subtitles.
@@ -15,6 +16,11 @@
 mod error;
 mod parser;
 mod reader;
+mod track;
+
+#[cfg(feature = "decrypt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "decrypt")))]
+pub mod decrypt;
 
 #[cfg(feature = "pssh")]
 #[cfg_attr(docsrs, doc(cfg(feature = "pssh")))]
@@ -27,6 +33,7 @@ pub mod text;
 pub use error::Error;
 pub use parser::*;
 pub use reader::Reader;
+pub use track::{track_types, TrackType};
 
 /// A `Result` alias where the `Err` case is `vsd_mp4::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -286,6 +286,53 @@ pub fn visual_sample_entry(mut _box: ParsedBox) -> HandlerResult {
     Ok(())
 }
 
+/// A callback that tells the Mp4 parser to treat the body of a box as an audio
+/// sample entry. An audio sample entry has some fixed-sized fields describing
+/// the audio codec parameters, followed by an arbitrary number of appended
+/// children. Each child is a box.
+pub fn audio_sample_entry(mut _box: ParsedBox) -> HandlerResult {
+    // The "reader" starts at the payload, so we need to add the header to the
+    // start position.  The header size varies.
+    let header_size = _box.header_size();
+
+    // Skip 6 reserved bytes.
+    // Skip 2-byte data reference index.
+    // Read 2-byte version (quicktime sound sample description).
+    // Skip 6 more reserved bytes.
+    // Skip 2-byte channel count.
+    // Skip 2-byte sample size.
+    // Skip 2-byte pre-defined.
+    // Skip 2-byte reserved.
+    // Skip 4-byte sample rate.
+    // 28 bytes total, followed by 16 more bytes for version 1 and 36 more bytes for version 2.
+    _box.reader
+        .skip(8)
+        .map_err(|_| Error::new_read_err("audio sample entry reserved 8 bytes"))?;
+    let version = _box
+        .reader
+        .read_u16()
+        .map_err(|_| Error::new_read_err("audio sample entry version (u16)"))?;
+    let skip = match version {
+        1 => 34,
+        2 => 54,
+        _ => 18,
+    };
+    _box.reader
+        .skip(skip)
+        .map_err(|_| Error::new_read_err(format!("audio sample entry reserved {} bytes", skip)))?;
+
+    while _box.reader.has_more_data() && !_box.parser.done {
+        _box.parser.parse_next(
+            _box.start + header_size,
+            &mut _box.reader,
+            Some(_box.partial_okay),
+            None,
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Create a callback that tells the Mp4 parser to treat the body of a box as a
 /// binary blob and to parse the body's contents using the provided callback.
 pub fn alldata(callback: Arc<dyn Fn(Vec<u8>) -> HandlerResult>) -> CallbackType {
@@ -50,6 +50,18 @@ pub enum KeyIdSystemType {
     WideVine,
 }
 
+impl KeyIdSystemType {
+    /// Get system id type from a `PSSH` box system id (in hex).
+    pub fn from_system_id(system_id: &str) -> Self {
+        match system_id.replace('-', "").to_lowercase().as_str() {
+            COMMAN_SYSTEM_ID => Self::Comman,
+            PLAYREADY_SYSTEM_ID => Self::PlayReady,
+            WIDEVINE_SYSTEM_ID => Self::WideVine,
+            x => Self::Other(x.to_owned()),
+        }
+    }
+}
+
 impl std::fmt::Display for KeyIdSystemType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -66,6 +78,7 @@ impl std::fmt::Display for KeyIdSystemType {
 }
 
 /// Parse `PSSH` box from mp4 files.
+#[derive(Clone, Default)]
 pub struct Pssh {
     pub key_ids: Vec<KeyId>,
     /// In hex.
@@ -104,6 +117,29 @@ impl Pssh {
         })
     }
 
+    /// Parse key ids from a bare playready object i.e. `mspr:pro` element of DASH manifests.
+    pub fn from_playready_object(data: &[u8]) -> Result<Self> {
+        Ok(Self {
+            key_ids: playready::parse(data)?.into_iter().collect(),
+            system_ids: vec![PLAYREADY_SYSTEM_ID.to_owned()],
+        })
+    }
+
+    /// Merge key ids and system ids parsed from another source.
+    pub fn extend(&mut self, other: Self) {
+        for key_id in other.key_ids {
+            if !self.key_ids.iter().any(|x| x.value == key_id.value) {
+                self.key_ids.push(key_id);
+            }
+        }
+
+        for system_id in other.system_ids {
+            if !self.system_ids.contains(&system_id) {
+                self.system_ids.push(system_id);
+            }
+        }
+    }
+
     fn parse_pssh_box(&mut self, _box: &mut ParsedBox) -> Result<()> {
         if _box.version.is_none() {
             return Err(Error::new(
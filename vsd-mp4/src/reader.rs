@@ -35,6 +35,12 @@ impl Reader {
         self.inner.position()
     }
 
+    pub fn read_u8(&mut self) -> Result<u8> {
+        let mut buf = [0; 1];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
     pub fn read_u16(&mut self) -> Result<u16> {
         let mut buf = [0; 2];
         self.inner.read_exact(&mut buf)?;
@@ -0,0 +1,72 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://developer.apple.com/library/archive/documentation/QuickTime/QTFF/QTFFChap2/qtff2.html#//apple_ref/doc/uid/TP40000939-CH204-25621
+    2. https://mp4ra.org/registered-types/handlers
+
+*/
+
+use crate::{parser, parser::Mp4Parser, Error, Result};
+use std::sync::{Arc, Mutex};
+
+/// Type of a track, read from its handler reference (`hdlr`) box.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrackType {
+    Audio,
+    Subtitles,
+    Video,
+    /// Any other handler type, i.e. `meta` for timed metadata tracks.
+    Other(String),
+}
+
+impl TrackType {
+    fn from_handler_type(handler_type: &str) -> Self {
+        match handler_type {
+            "soun" => Self::Audio,
+            "clcp" | "sbtl" | "subt" | "text" => Self::Subtitles,
+            "vide" => Self::Video,
+            x => Self::Other(x.to_owned()),
+        }
+    }
+}
+
+/// Parse types of all tracks present in `moov` box.
+/// Data can also be partial, i.e. only the beginning of a large file.
+pub fn track_types(data: &[u8]) -> Result<Vec<TrackType>> {
+    let track_types = Arc::new(Mutex::new(vec![]));
+    let track_types_c = track_types.clone();
+
+    Mp4Parser::default()
+        ._box("moov", Arc::new(parser::children))
+        ._box("trak", Arc::new(parser::children))
+        ._box("mdia", Arc::new(parser::children))
+        .full_box(
+            "hdlr",
+            Arc::new(move |mut _box| {
+                _box.reader
+                    .skip(4)
+                    .map_err(|_| Error::new_read_err("HDLR box pre defined (u32)"))?;
+                let handler_type = _box
+                    .reader
+                    .read_u32()
+                    .map_err(|_| Error::new_read_err("HDLR box handler type (u32)"))?;
+                let handler_type = parser::type_to_string(handler_type as usize)
+                    .map_err(|_| Error::new_decode_err("HDLR box handler type (u32) to string"))?;
+                track_types_c
+                    .lock()
+                    .unwrap()
+                    .push(TrackType::from_handler_type(&handler_type));
+                Ok(())
+            }),
+        )
+        .parse(data, Some(true), Some(true))?;
+
+    let track_types = track_types.lock().unwrap().clone();
+
+    if track_types.is_empty() {
+        return Err(Error::new("HDLR box not found"));
+    }
+
+    Ok(track_types)
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "decrypt")]
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+fn sample(name: &str) -> Vec<u8> {
+    fs::read(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../mp4decrypt/examples/sample")
+            .join(name),
+    )
+    .unwrap()
+}
+
+fn keys() -> HashMap<String, String> {
+    HashMap::from([(
+        "eb676abbcb345e96bbcf616630f1a3da".to_owned(),
+        "100b6c20940f779a4589152b57d2dacb".to_owned(),
+    )])
+}
+
+/// 64-bit FNV-1a hash.
+fn fnv(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, x| {
+        (hash ^ *x as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[test]
+fn decrypt_cenc_sample() {
+    let init = sample("init.mp4");
+    let segment = sample("segment_0.m4s");
+    let data = [init.clone(), segment.clone()].concat();
+
+    let decrypted = vsd_mp4::decrypt::mp4decrypt(&data, keys(), None).unwrap();
+    assert_eq!(decrypted.len(), data.len());
+
+    // Hash of mdat payload decrypted using python cryptography package.
+    let mdat = &decrypted[(init.len() + 4705 + 8)..];
+    assert_eq!(fnv(mdat), 0x29a0b8271b7075bb);
+
+    let fragments = vsd_mp4::decrypt::mp4decrypt(&segment, keys(), Some(init.clone())).unwrap();
+    assert_eq!(fragments, decrypted[init.len()..]);
+}
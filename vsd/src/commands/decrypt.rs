@@ -0,0 +1,85 @@
+use super::save::key_parser;
+use anyhow::{bail, Result};
+use clap::Args;
+use std::{collections::HashMap, path::PathBuf};
+
+/// Decrypt CENC encrypted mp4 files.
+#[derive(Debug, Clone, Args)]
+pub struct Decrypt {
+    /// Path of encrypted mp4 file.
+    /// It is read into memory if vsd is compiled without mp4decrypt feature.
+    #[arg(required = true)]
+    input: PathBuf,
+
+    /// Path for decrypted output file.
+    #[arg(short, long, required = true)]
+    output: PathBuf,
+
+    /// Path of init segment (or any file containing moov box) for decrypting an input file which only contains fragments,
+    /// e.g. segments saved by `save` sub-command using --no-decrypt and --no-merge flags.
+    #[arg(long)]
+    init: Option<PathBuf>,
+
+    /// Keys for decrypting input file in format KID:KEY or TRACK_ID:KEY.
+    /// KEY value can be specified in hex, base64 or file format.
+    /// This option can be used multiple times.
+    #[arg(short, long, required = true, value_name = "KID:KEY", value_parser = key_parser)]
+    key: Vec<(Option<String>, String)>,
+}
+
+impl Decrypt {
+    pub fn execute(self) -> Result<()> {
+        let mut keys = HashMap::new();
+
+        for (kid, key) in &self.key {
+            if let Some(kid) = kid {
+                keys.insert(kid.to_owned(), key.to_owned());
+            } else {
+                bail!("key id is required for decrypting files, use KID:KEY format.");
+            }
+        }
+
+        self.decrypt(keys)
+    }
+
+    // File is streamed through bento4 when mp4decrypt is available,
+    // whereas pure rust decryption reads whole file into memory.
+    #[cfg(feature = "mp4decrypt")]
+    fn decrypt(self, keys: HashMap<String, String>) -> Result<()> {
+        use kdam::{tqdm, BarExt};
+
+        let mut pb = tqdm!(unit = " steps".to_owned(), dynamic_ncols = true);
+
+        let result = mp4decrypt::mp4decrypt_file(
+            &self.input,
+            &self.output,
+            &crate::utils::mp4decrypt_keys(keys)?,
+            self.init.as_deref(),
+            Some(&mut |step, total| {
+                pb.total = total as usize;
+                let _ = pb.update_to(step as usize);
+            }),
+        );
+
+        eprintln!();
+        Ok(result?)
+    }
+
+    #[cfg(all(feature = "rust-decrypt", not(feature = "mp4decrypt")))]
+    fn decrypt(self, keys: HashMap<String, String>) -> Result<()> {
+        let data = std::fs::read(&self.input)?;
+        let init = self.init.map(std::fs::read).transpose()?;
+        std::fs::write(
+            &self.output,
+            vsd_mp4::decrypt::mp4decrypt(&data, keys, init)?,
+        )?;
+        Ok(())
+    }
+
+    #[cfg(not(any(feature = "mp4decrypt", feature = "rust-decrypt")))]
+    fn decrypt(self, _keys: HashMap<String, String>) -> Result<()> {
+        bail!(
+            "vsd is compiled without cenc decryption support, enable mp4decrypt or rust-decrypt feature."
+        )
+    }
+}
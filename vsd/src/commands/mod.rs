@@ -1,3 +1,4 @@
+mod decrypt;
 mod extract;
 mod merge;
 mod save;
@@ -5,9 +6,10 @@ mod save;
 #[cfg(feature = "browser")]
 mod capture;
 
+pub use decrypt::Decrypt;
 pub use extract::Extract;
 pub use merge::Merge;
-pub use save::{Quality, Save};
+pub use save::{Quality, QualityFallback, Save};
 
 #[cfg(feature = "browser")]
 pub use capture::Capture;
@@ -22,7 +24,9 @@ use clap::{ColorChoice, Parser, Subcommand};
         env!("CARGO_PKG_VERSION"),
         "\n\nEnabled features:",
         "\n  browser                 : ", cfg!(feature = "browser"),
+        "\n  mp4decrypt              : ", cfg!(feature = "mp4decrypt"),
         "\n  native-tls              : ", cfg!(feature = "native-tls"),
+        "\n  rust-decrypt            : ", cfg!(feature = "rust-decrypt"),
         "\n  rustls-tls-native-roots : ", cfg!(feature = "rustls-tls-native-roots"),
         "\n  rustls-tls-webpki-roots : ", cfg!(feature = "rustls-tls-webpki-roots"),
     ),
@@ -37,10 +41,12 @@ pub struct Args {
     pub color: ColorChoice,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Subcommand)]
 pub enum Commands {
     #[cfg(feature = "browser")]
     Capture(Capture),
+    Decrypt(Decrypt),
     Extract(Extract),
     Merge(Merge),
     Save(Save),
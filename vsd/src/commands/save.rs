@@ -1,9 +1,12 @@
 use crate::{
     cookie::{CookieJar, CookieParam},
+    downloader::DownloadOptions,
+    filter::StreamFilter,
+    playlist::{AudioChannels, AudioCodec, VideoCodec, VideoRange},
     utils,
 };
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use cookie::Cookie;
 use kdam::term::Colorizer;
 use reqwest::{
@@ -36,8 +39,17 @@ pub struct Save {
     #[arg(short, long)]
     pub directory: Option<PathBuf>,
 
+    /// Print streams as json instead of a table when used with --list-streams flag.
+    #[arg(long, requires = "list_streams")]
+    pub json: bool,
+
+    /// List all streams of playlist along with their ids, encryption and estimated size, and exit without downloading.
+    #[arg(long)]
+    pub list_streams: bool,
+
     /// Mux all downloaded streams to a video container (.mp4, .mkv, etc.) using ffmpeg.
     /// Note that existing files will be overwritten and downloaded streams will be deleted.
+    /// If extension is omitted then a single stream is saved in its own container, otherwise .mkv is used.
     #[arg(short, long)]
     pub output: Option<String>,
 
@@ -45,6 +57,65 @@ pub struct Save {
     #[arg(long)]
     pub raw_prompts: bool,
 
+    /// Audio languages to download, best matching stream is selected for each language (eg. en,de,ja).
+    /// Languages are matched same as --prefer-audio-lang and `all` selects every audio stream.
+    /// Missing languages are warned about, use --require-langs to treat them as errors.
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        value_delimiter = ',',
+        value_name = "LANGS",
+        conflicts_with = "select_audio"
+    )]
+    pub audio_lang: Vec<String>,
+
+    /// Preferred channel count of audio streams (eg. 2, 6 or max).
+    /// Streams with exact channel count are ranked first followed by streams with fewer channels,
+    /// and max ranks streams with more channels higher.
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        default_value = "max",
+        value_name = "2|6|max"
+    )]
+    pub audio_channels: AudioChannels,
+
+    /// Roles of audio and subtitles streams which are skipped during automatic selection (eg. description,commentary).
+    /// These streams can still be selected using --select-audio, --select-subs, --select-id or prompts.
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        value_delimiter = ',',
+        value_name = "ROLES"
+    )]
+    pub exclude_roles: Vec<String>,
+
+    /// Maximum combined bitrate of automatically selected video and audio streams (eg. 3M or 800k).
+    /// Lower quality video streams and lower bandwidth audio streams of same language are selected to stay within this limit.
+    #[arg(long, help_heading = "Automation Options", value_name = "BITRATE", value_parser = bitrate_parser)]
+    pub max_bitrate: Option<u64>,
+
+    /// Maximum projected output size of automatically selected video and audio streams (eg. 700MB or 1.5GiB).
+    /// This is applied as a bitrate limit over duration of playlist, same as --max-bitrate.
+    #[arg(long, help_heading = "Automation Options", value_name = "SIZE", value_parser = size_parser)]
+    pub max_size: Option<u64>,
+
+    /// Minimum combined bitrate of automatically selected video and audio streams (eg. 1M).
+    #[arg(long, help_heading = "Automation Options", value_name = "BITRATE", value_parser = bitrate_parser)]
+    pub min_bitrate: Option<u64>,
+
+    /// Preferred audio codecs in order of preference (eg. atmos,eac3,aac).
+    /// Among audio streams of same language, streams with preferred codecs are ranked higher than channel count and bandwidth.
+    /// Atmos matches object based audio of any codec.
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        value_enum,
+        value_delimiter = ',',
+        value_name = "CODECS"
+    )]
+    pub prefer_audio_codec: Vec<AudioCodec>,
+
     /// Preferred language when multiple audio streams with different languages are available.
     /// Must be in RFC 5646 format (eg. fr or en-AU).
     /// If a preference is not specified and multiple audio streams are present,
@@ -52,6 +123,17 @@ pub struct Save {
     #[arg(long, help_heading = "Automation Options")]
     pub prefer_audio_lang: Option<String>,
 
+    /// Preferred video codecs in order of preference (eg. av1,hevc,avc).
+    /// Among video streams of same resolution, streams with preferred codecs are ranked higher than bandwidth.
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        value_enum,
+        value_delimiter = ',',
+        value_name = "CODECS"
+    )]
+    pub prefer_codec: Vec<VideoCodec>,
+
     /// Preferred language when multiple subtitles streams with different languages are available.
     /// Must be in RFC 5646 format (eg. fr or en-AU).
     /// If a preference is not specified and multiple subtitles streams are present,
@@ -65,10 +147,71 @@ pub struct Save {
     #[arg(short, long, help_heading = "Automation Options", default_value = "highest", value_name = "WIDTHxHEIGHT|HEIGHTp", value_parser = quality_parser)]
     pub quality: Quality,
 
+    /// Fallback policy when no stream of --quality resolution is available.
+    /// Nearest picks stream with closest height (lower one on ties),
+    /// lower and higher pick closest height only in that direction and fail doesn't pick any stream.
+    #[arg(long, help_heading = "Automation Options", value_enum, default_value_t = QualityFallback::Nearest)]
+    pub quality_fallback: QualityFallback,
+
+    /// Fail if any of the languages from --audio-lang and --subs-lang are not available.
+    #[arg(long, help_heading = "Automation Options")]
+    pub require_langs: bool,
+
+    /// Select audio streams using a filter expression evaluated against each stream.
+    /// Filter is an optional modifier [best, worst, all, first N] (default: best) followed by an optional expression.
+    /// Expression compares fields [bw, channels, codecs, ext, fps, group, height, label, lang, live, range, role, width]
+    /// using operators [=, !=, <, <=, >, >=, ~ (contains), !~, in (a, b)] joined by &&, || and !.
+    /// Numbers can have k, M or G suffix (eg. "lang in (en, fr) && channels>=6" or "all lang=en").
+    #[arg(long, help_heading = "Automation Options", value_name = "FILTER")]
+    pub select_audio: Option<StreamFilter>,
+
+    /// Select entries of IPTV style (multi-entry) m3u playlists without prompting.
+    /// Comma separated terms are matched against entry titles and attribute values (case insensitive),
+    /// KEY=VALUE terms must match an attribute exactly (eg. group-title=News) and `all` selects every entry.
+    /// Each selected entry is downloaded as its own file named after its title.
+    #[arg(long, help_heading = "Automation Options", value_name = "FILTER")]
+    pub select_entries: Option<String>,
+
+    /// Select streams by their ids without prompting (eg. 1080p-hevc,audio-en).
+    /// Ids are shown in stream selection prompts and by --list-streams flag.
+    /// These are DASH representation ids, HLS uris without query and smooth streaming names with bitrate.
+    #[arg(long, help_heading = "Automation Options", value_delimiter = ',', value_name = "IDS", conflicts_with_all = ["audio_lang", "select_audio", "select_subs", "select_video", "subs_lang"])]
+    pub select_id: Vec<String>,
+
+    /// Select subtitle streams using a filter expression, see --select-audio for syntax (eg. "all" or "lang=en && !role=forced-subtitle").
+    #[arg(long, help_heading = "Automation Options", value_name = "FILTER")]
+    pub select_subs: Option<StreamFilter>,
+
+    /// Select video streams using a filter expression, see --select-audio for syntax (eg. "height<=1080 && codecs~hvc1 && bw<8M").
+    /// This flag takes precedence over --quality flag.
+    #[arg(long, help_heading = "Automation Options", value_name = "FILTER")]
+    pub select_video: Option<StreamFilter>,
+
     /// Skip user input prompts and proceed with defaults.
     #[arg(long, help_heading = "Automation Options")]
     pub skip_prompts: bool,
 
+    /// Subtitle languages to download, see --audio-lang for details (eg. en,fr or all).
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        value_delimiter = ',',
+        value_name = "LANGS",
+        conflicts_with = "select_subs"
+    )]
+    pub subs_lang: Vec<String>,
+
+    /// Preferred dynamic range of video streams in order of preference (eg. dv,hdr10).
+    /// Among video streams of same resolution, this preference is ranked higher than --prefer-codec.
+    #[arg(
+        long,
+        help_heading = "Automation Options",
+        value_enum,
+        value_delimiter = ',',
+        value_name = "RANGES"
+    )]
+    pub video_range: Vec<VideoRange>,
+
     /// Fill request client with some existing cookies value.
     /// Cookies value can be same as document.cookie or in json format same as puppeteer.
     #[arg(long, help_heading = "Client Options", default_value = "[]", hide_default_value = true, value_parser = cookie_parser)]
@@ -82,7 +225,7 @@ pub struct Save {
     /// Skip checking and validation of site certificates.
     #[arg(long, help_heading = "Client Options")]
     pub no_certificate_checks: bool,
-    
+
     /// Set http(s) / socks proxy address for requests.
     #[arg(long, help_heading = "Client Options", value_parser = proxy_address_parser)]
     pub proxy: Option<Proxy>,
@@ -122,7 +265,7 @@ pub struct Save {
     /// Maximum number of retries to download an individual segment.
     #[arg(long, help_heading = "Download Options", default_value_t = 15)]
     pub retry_count: u8,
-    
+
     /// Download streams without merging them.
     /// Note that --output flag is ignored if this flag is used.
     #[arg(long, help_heading = "Download Options")]
@@ -151,6 +294,14 @@ pub enum Quality {
     Youtube8k,
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+pub enum QualityFallback {
+    Fail,
+    Higher,
+    Lower,
+    Nearest,
+}
+
 fn quality_parser(s: &str) -> Result<Quality, String> {
     Ok(match s.to_lowercase().as_str() {
         "lowest" | "min" => Quality::Lowest,
@@ -196,7 +347,48 @@ fn quality_parser(s: &str) -> Result<Quality, String> {
     })
 }
 
-fn key_parser(s: &str) -> Result<(Option<String>, String), String> {
+fn bitrate_parser(s: &str) -> Result<u64, String> {
+    let number = s.trim().trim_end_matches("bps").trim_end_matches("bit/s");
+    let (number, multiplier) = match number.chars().last() {
+        Some('k' | 'K') => (&number[..(number.len() - 1)], 1_000.0),
+        Some('m' | 'M') => (&number[..(number.len() - 1)], 1_000_000.0),
+        Some('g' | 'G') => (&number[..(number.len() - 1)], 1_000_000_000.0),
+        _ => (number, 1.0),
+    };
+
+    number
+        .trim()
+        .parse::<f64>()
+        .map(|x| (x * multiplier) as u64)
+        .map_err(|_| format!("could not parse bitrate {}.", s))
+}
+
+fn size_parser(s: &str) -> Result<u64, String> {
+    let size = s.trim().to_lowercase();
+    let (number, multiplier) = [
+        ("kib", 1024.0),
+        ("mib", 1024.0 * 1024.0),
+        ("gib", 1024.0 * 1024.0 * 1024.0),
+        ("kb", 1_000.0),
+        ("mb", 1_000_000.0),
+        ("gb", 1_000_000_000.0),
+        ("k", 1_000.0),
+        ("m", 1_000_000.0),
+        ("g", 1_000_000_000.0),
+        ("b", 1.0),
+    ]
+    .into_iter()
+    .find_map(|(x, y)| size.strip_suffix(x).map(|z| (z, y)))
+    .unwrap_or((&size, 1.0));
+
+    number
+        .trim()
+        .parse::<f64>()
+        .map(|x| (x * multiplier) as u64)
+        .map_err(|_| format!("could not parse size {}.", s))
+}
+
+pub(super) fn key_parser(s: &str) -> Result<(Option<String>, String), String> {
     let (key_id, mut key) = if let Some((key_id, key)) = s.split_once(':') {
         (Some(key_id.to_lowercase().replace('-', "")), key.to_owned())
     } else {
@@ -293,24 +485,46 @@ impl Save {
         let client = client_builder.cookie_provider(Arc::new(jar)).build()?;
 
         crate::downloader::download(
-            self.all_keys,
-            self.base_url,
-            client,
-            self.directory,
+            client.clone(),
             &self.input,
-            self.key,
-            self.no_decrypt,
-            self.no_merge,
-            self.output,
-            self.prefer_audio_lang,
-            self.prefer_subs_lang,
-            self.quality,
-            self.skip_prompts,
-            self.raw_prompts,
-            self.retry_count,
-            self.threads,
+            DownloadOptions {
+                all_keys: self.all_keys,
+                audio_channels: self.audio_channels,
+                audio_lang: self.audio_lang,
+                base_url: self.base_url,
+                directory: self.directory,
+                exclude_roles: self.exclude_roles,
+                json: self.json,
+                keys: self.key,
+                list_streams: self.list_streams,
+                max_bitrate: self.max_bitrate,
+                max_size: self.max_size,
+                min_bitrate: self.min_bitrate,
+                no_decrypt: self.no_decrypt,
+                no_merge: self.no_merge,
+                output: self.output,
+                prefer_audio_codec: self.prefer_audio_codec,
+                prefer_audio_lang: self.prefer_audio_lang,
+                prefer_codec: self.prefer_codec,
+                prefer_subs_lang: self.prefer_subs_lang,
+                quality: self.quality,
+                quality_fallback: self.quality_fallback,
+                require_langs: self.require_langs,
+                select_audio: self.select_audio,
+                select_entries: self.select_entries,
+                select_id: self.select_id,
+                select_subs: self.select_subs,
+                select_video: self.select_video,
+                skip_prompts: self.skip_prompts,
+                subs_lang: self.subs_lang,
+                raw_prompts: self.raw_prompts,
+                retry_count: self.retry_count,
+                threads: self.threads,
+                video_range: self.video_range,
+            },
         )?;
 
+        crate::update::check_for_new_release(&client);
         Ok(())
     }
 }
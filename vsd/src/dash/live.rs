@@ -0,0 +1,140 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://dashif.org/docs/DASH-IF-IOP-v4.3.pdf
+    2. https://github.com/Dash-Industry-Forum/dash.js/blob/development/src/streaming/controllers/TimeSyncController.js
+
+*/
+
+use super::patch;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use dash_mpd::MPD;
+use kdam::term::Colorizer;
+use reqwest::{blocking::Client, header, Url};
+use serde::Deserialize;
+
+// dash-mpd expects Location nodes as locations, so they are read from the manifest again here.
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct MpdLocation {
+    #[serde(rename = "Location")]
+    locations: Vec<String>,
+}
+
+/// Url of `MPD > Location` node resolved against url of manifest.
+pub(crate) fn location(xml: &str, url: &Url) -> Option<Url> {
+    quick_xml::de::from_str::<MpdLocation>(xml)
+        .ok()?
+        .locations
+        .first()
+        .and_then(|x| url.join(x.trim()).ok())
+}
+
+/// Reload a dynamic manifest which was fetched from `url`. `PatchLocation` is used when it is still valid,
+/// otherwise whole manifest is fetched again from `Location` (falling back to `url`).
+/// Returned url is the one from which reloaded manifest was fetched.
+pub(crate) fn reload(client: &Client, xml: &str, mpd: &MPD, url: &Url) -> Result<(String, Url)> {
+    let publish_time = mpd.publishTime.unwrap_or_else(Utc::now);
+    let mpd_url = location(xml, url).unwrap_or_else(|| url.to_owned());
+
+    for patch_location in &mpd.PatchLocation {
+        if let Some(ttl) = patch_location.ttl {
+            if (Utc::now() - publish_time).num_milliseconds() as f64 > ttl * 1000.0 {
+                continue;
+            }
+        }
+
+        let patch_url = mpd_url.join(patch_location.content.trim())?;
+        let patched = client
+            .get(patch_url.clone())
+            .send()
+            .and_then(|x| x.error_for_status())
+            .and_then(|x| x.text())
+            .map_err(|x| anyhow!(x))
+            .and_then(|x| patch::apply_patch(xml, &x));
+
+        match patched {
+            Ok(xml) => return Ok((xml, url.to_owned())),
+            Err(e) => println!(
+                "    {} couldn't apply patch {} ({}), reloading whole playlist",
+                "Warning".colorize("bold yellow"),
+                patch_url,
+                e
+            ),
+        }
+    }
+
+    let response = client.get(mpd_url).send()?.error_for_status()?;
+    let url = response.url().to_owned();
+    Ok((response.text()?, url))
+}
+
+/// Difference between server and local clock measured using `UTCTiming` schemes.
+/// Schemes are tried in order of their appearance and ntp schemes are not supported.
+pub(crate) fn clock_skew(client: &Client, mpd: &MPD, url: &Url) -> Option<chrono::Duration> {
+    for utc_timing in &mpd.UTCTiming {
+        let (scheme, value) = match (&utc_timing.schemeIdUri, &utc_timing.value) {
+            (Some(scheme), Some(value)) => (scheme, value),
+            _ => continue,
+        };
+        let scheme = scheme
+            .strip_prefix("urn:mpeg:dash:utc:")
+            .and_then(|x| x.split(':').next())
+            .unwrap_or_default();
+
+        for value in value.split_whitespace() {
+            let requested_at = Utc::now();
+            let server_time = match scheme {
+                "direct" => parse_datetime(value),
+                "http-iso" | "http-xsdate" => url.join(value).ok().and_then(|x| {
+                    client
+                        .get(x)
+                        .send()
+                        .and_then(|x| x.error_for_status())
+                        .and_then(|x| x.text())
+                        .ok()
+                        .and_then(|x| parse_datetime(&x))
+                }),
+                "http-head" => url.join(value).ok().and_then(|x| {
+                    client
+                        .head(x)
+                        .send()
+                        .ok()?
+                        .headers()
+                        .get(header::DATE)?
+                        .to_str()
+                        .ok()
+                        .and_then(|x| DateTime::parse_from_rfc2822(x).ok())
+                        .map(|x| x.with_timezone(&Utc))
+                }),
+                _ => None,
+            };
+
+            if let Some(server_time) = server_time {
+                // Assume response was generated halfway through the request.
+                let local_time = requested_at + (Utc::now() - requested_at) / 2;
+                let skew = server_time - local_time;
+                println!(
+                    "     {} clock using {} ({:+.3}s)",
+                    "Synced".colorize("bold green"),
+                    scheme,
+                    skew.num_milliseconds() as f64 / 1000.0
+                );
+                return Some(skew);
+            }
+        }
+    }
+
+    None
+}
+
+fn parse_datetime(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    DateTime::parse_from_rfc3339(s)
+        .map(|x| x.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").map(|x| x.and_utc()))
+        .ok()
+}
@@ -1,8 +1,13 @@
+mod live;
 mod locator;
+mod patch;
 mod playlist;
+mod protection;
 mod template;
 
 use locator::DashUrl;
 use template::Template;
 
-pub(crate) use playlist::{parse_as_master, push_segments};
+pub(crate) use live::{clock_skew, location, reload};
+pub(crate) use playlist::{parse_as_master, push_segments, relocate};
+pub(crate) use protection::MpdProtection;
@@ -0,0 +1,526 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://www.rfc-editor.org/rfc/rfc5261
+    2. https://github.com/Dash-Industry-Forum/dash.js/blob/development/src/dash/models/PatchManifestModel.js
+
+*/
+
+use anyhow::{anyhow, bail, Result};
+use quick_xml::{escape::escape, events::Event, Reader};
+
+// dash-mpd cannot serialize manifests back without losing unknown nodes,
+// so patches are applied on a minimal xml tree and the result is parsed again.
+
+#[derive(Clone)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Clone)]
+struct Element {
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    name: String,
+}
+
+impl Element {
+    fn parse(xml: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(xml);
+        let mut stack: Vec<Element> = vec![];
+        let mut root = None;
+
+        loop {
+            let element = match reader.read_event()? {
+                Event::Start(x) => {
+                    stack.push(Self::from_start(&x)?);
+                    continue;
+                }
+                Event::Empty(x) => Self::from_start(&x)?,
+                Event::End(_) => stack
+                    .pop()
+                    .ok_or_else(|| anyhow!("unexpected closing tag in xml."))?,
+                Event::Text(x) => {
+                    let text = x.unescape()?;
+
+                    if let Some(parent) = stack.last_mut() {
+                        if !text.trim().is_empty() {
+                            parent.children.push(Node::Text(text.into_owned()));
+                        }
+                    }
+
+                    continue;
+                }
+                Event::CData(x) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(Node::Text(
+                            String::from_utf8_lossy(&x.into_inner()).into_owned(),
+                        ));
+                    }
+
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(Node::Element(element));
+            } else {
+                root = Some(element);
+            }
+        }
+
+        root.ok_or_else(|| anyhow!("xml doesn't have a root element."))
+    }
+
+    fn from_start(start: &quick_xml::events::BytesStart) -> Result<Self> {
+        let mut attributes = vec![];
+
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                attribute.unescape_value()?.into_owned(),
+            ));
+        }
+
+        Ok(Self {
+            attributes,
+            children: vec![],
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        })
+    }
+
+    fn write(&self, xml: &mut String) {
+        xml.push('<');
+        xml.push_str(&self.name);
+
+        for (key, value) in &self.attributes {
+            xml.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+        }
+
+        if self.children.is_empty() {
+            xml.push_str("/>");
+            return;
+        }
+
+        xml.push('>');
+
+        for child in &self.children {
+            match child {
+                Node::Element(x) => x.write(xml),
+                Node::Text(x) => xml.push_str(&escape(x)),
+            }
+        }
+
+        xml.push_str(&format!("</{}>", self.name));
+    }
+
+    fn local_name(&self) -> &str {
+        self.name.rsplit(':').next().unwrap()
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(x, _)| x == key)
+            .map(|(_, x)| x.as_str())
+    }
+
+    fn set_attribute(&mut self, key: &str, value: &str) {
+        if let Some((_, x)) = self.attributes.iter_mut().find(|(x, _)| x == key) {
+            *x = value.to_owned();
+        } else {
+            self.attributes.push((key.to_owned(), value.to_owned()));
+        }
+    }
+
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|x| match x {
+                Node::Text(x) => Some(x.as_str()),
+                _ => None,
+            })
+            .collect::<String>()
+            .trim()
+            .to_owned()
+    }
+
+    fn child_mut(&mut self, path: &[usize]) -> Option<&mut Element> {
+        let mut element = self;
+
+        for i in path {
+            element = match element.children.get_mut(*i) {
+                Some(Node::Element(x)) => x,
+                _ => return None,
+            };
+        }
+
+        Some(element)
+    }
+
+    /// Resolve a simple xpath selector (`/MPD/Period[@id='1']/AdaptationSet[2]/@attr`)
+    /// to child indices of target element and an optional target attribute.
+    fn select(&self, selector: &str) -> Result<(Vec<usize>, Option<String>)> {
+        let mut steps = split_steps(selector.trim().trim_start_matches('/'));
+        let mut attribute = None;
+
+        if let Some(x) = steps.last().and_then(|x| x.strip_prefix('@')) {
+            attribute = Some(x.to_owned());
+            steps.pop();
+        }
+
+        let mut steps = steps.into_iter();
+        let root = steps
+            .next()
+            .ok_or_else(|| anyhow!("empty patch selector {:?}.", selector))?;
+        let (name, predicates) = parse_step(root)?;
+
+        if !matches_step(self, &name, &predicates, 1) {
+            bail!("patch selector {:?} doesn't match root element.", selector);
+        }
+
+        let mut path = vec![];
+        let mut element = self;
+
+        for step in steps {
+            let (name, predicates) = parse_step(step)?;
+            let mut position = 0;
+            let mut found = None;
+
+            for (i, child) in element.children.iter().enumerate() {
+                if let Node::Element(child) = child {
+                    if child.local_name() == name || name == "*" {
+                        position += 1;
+
+                        if matches_step(child, &name, &predicates, position) {
+                            found = Some((i, child));
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let (i, child) =
+                found.ok_or_else(|| anyhow!("patch selector {:?} doesn't match.", selector))?;
+            path.push(i);
+            element = child;
+        }
+
+        Ok((path, attribute))
+    }
+}
+
+enum Predicate {
+    Attribute(String, String),
+    Position(usize),
+}
+
+fn split_steps(selector: &str) -> Vec<&str> {
+    let mut steps = vec![];
+    let mut quoted = None;
+    let mut start = 0;
+
+    for (i, x) in selector.char_indices() {
+        match x {
+            '\'' | '"' if quoted.is_none() => quoted = Some(x),
+            '\'' | '"' if quoted == Some(x) => quoted = None,
+            '/' if quoted.is_none() => {
+                steps.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    steps.push(&selector[start..]);
+    steps
+}
+
+fn parse_step(step: &str) -> Result<(String, Vec<Predicate>)> {
+    let (name, mut rest) = step.split_once('[').map_or((step, ""), |(x, y)| (x, y));
+    let name = name.rsplit(':').next().unwrap().to_owned();
+    let mut predicates = vec![];
+
+    while !rest.is_empty() {
+        let (predicate, remaining) = rest
+            .split_once(']')
+            .ok_or_else(|| anyhow!("unclosed predicate in patch selector step {:?}.", step))?;
+        let predicate = predicate.trim();
+
+        if let Some((key, value)) = predicate.strip_prefix('@').and_then(|x| x.split_once('=')) {
+            predicates.push(Predicate::Attribute(
+                key.trim().to_owned(),
+                value
+                    .trim()
+                    .trim_matches(|x| x == '\'' || x == '"')
+                    .to_owned(),
+            ));
+        } else if let Ok(position) = predicate.parse::<usize>() {
+            predicates.push(Predicate::Position(position));
+        } else {
+            bail!("unsupported predicate in patch selector step {:?}.", step);
+        }
+
+        rest = remaining.trim_start_matches('[');
+    }
+
+    Ok((name, predicates))
+}
+
+fn matches_step(element: &Element, name: &str, predicates: &[Predicate], position: usize) -> bool {
+    (element.local_name() == name || name == "*")
+        && predicates.iter().all(|x| match x {
+            Predicate::Attribute(key, value) => element.attribute(key) == Some(value.as_str()),
+            Predicate::Position(x) => *x == position,
+        })
+}
+
+/// Apply a mpd patch document to mpd manifest and return the patched manifest.
+pub(crate) fn apply_patch(mpd: &str, patch: &str) -> Result<String> {
+    let mut mpd = Element::parse(mpd)?;
+    let patch = Element::parse(patch)?;
+
+    if patch.local_name() != "Patch" {
+        bail!("mpd patch document doesn't have a Patch root element.");
+    }
+
+    if patch.attribute("mpdId") != mpd.attribute("id")
+        || patch.attribute("originalPublishTime") != mpd.attribute("publishTime")
+    {
+        bail!("mpd patch is not applicable on current mpd.");
+    }
+
+    for operation in patch.children.iter().filter_map(|x| match x {
+        Node::Element(x) => Some(x),
+        _ => None,
+    }) {
+        let selector = operation
+            .attribute("sel")
+            .ok_or_else(|| anyhow!("missing @sel on mpd patch operation."))?;
+        let (path, attribute) = mpd.select(selector)?;
+
+        match (operation.local_name(), attribute) {
+            ("add", None) => {
+                let nodes = operation.children.clone();
+
+                if let Some(x) = operation
+                    .attribute("type")
+                    .and_then(|x| x.strip_prefix('@'))
+                {
+                    let text = operation.text();
+                    mpd.child_mut(&path).unwrap().set_attribute(x, &text);
+                    continue;
+                }
+
+                match operation.attribute("pos") {
+                    Some(x @ ("before" | "after")) => {
+                        let (index, parent) = path
+                            .split_last()
+                            .ok_or_else(|| anyhow!("cannot add sibling to root element."))?;
+                        let index = if x == "before" { *index } else { index + 1 };
+                        mpd.child_mut(parent)
+                            .unwrap()
+                            .children
+                            .splice(index..index, nodes);
+                    }
+                    Some("prepend") => {
+                        mpd.child_mut(&path).unwrap().children.splice(0..0, nodes);
+                    }
+                    _ => mpd.child_mut(&path).unwrap().children.extend(nodes),
+                }
+            }
+            ("replace", None) => {
+                let replacement = operation
+                    .children
+                    .iter()
+                    .find_map(|x| match x {
+                        Node::Element(x) => Some(x.clone()),
+                        _ => None,
+                    })
+                    .ok_or_else(|| anyhow!("missing replacement element in mpd patch."))?;
+
+                *mpd.child_mut(&path).unwrap() = replacement;
+            }
+            ("remove", None) => {
+                let (index, parent) = path
+                    .split_last()
+                    .ok_or_else(|| anyhow!("cannot remove root element."))?;
+                mpd.child_mut(parent).unwrap().children.remove(*index);
+            }
+            ("add" | "replace", Some(x)) => {
+                let text = operation.text();
+                mpd.child_mut(&path).unwrap().set_attribute(&x, &text);
+            }
+            ("remove", Some(x)) => {
+                mpd.child_mut(&path)
+                    .unwrap()
+                    .attributes
+                    .retain(|(key, _)| *key != x);
+            }
+            (x, _) => bail!("unsupported mpd patch operation {:?}.", x),
+        }
+    }
+
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_owned();
+    mpd.write(&mut xml);
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" id="live" publishTime="2024-01-01T00:00:00Z" type="dynamic" availabilityStartTime="2024-01-01T00:00:00Z" minimumUpdatePeriod="PT2S" profiles="urn:mpeg:dash:profile:isoff-live:2011">
+  <Period id="1" start="PT0S">
+    <AdaptationSet id="1" mimeType="video/mp4">
+      <SegmentTemplate timescale="1" media="v_$Time$.m4s" initialization="v_init.mp4">
+        <SegmentTimeline>
+          <S t="0" d="2"/>
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="v1" bandwidth="1000000" width="1280" height="720"/>
+    </AdaptationSet>
+    <AdaptationSet id="2" mimeType="audio/mp4">
+      <Representation id="a1" bandwidth="128000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    fn patch(operations: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Patch xmlns="urn:mpeg:dash:schema:mpd-patch:2020" mpdId="live" originalPublishTime="2024-01-01T00:00:00Z" publishTime="2024-01-01T00:00:02Z">
+{}
+</Patch>"#,
+            operations
+        )
+    }
+
+    fn representation_ids(mpd: &dash_mpd::MPD) -> Vec<Vec<&str>> {
+        mpd.periods[0]
+            .adaptations
+            .iter()
+            .map(|x| {
+                x.representations
+                    .iter()
+                    .map(|x| x.id.as_deref().unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn attribute(xml: &str, selector: &str) -> Option<String> {
+        let mut mpd = Element::parse(xml).unwrap();
+        let (path, attribute) = mpd.select(selector).unwrap();
+        mpd.child_mut(&path)
+            .unwrap()
+            .attribute(&attribute.unwrap())
+            .map(|x| x.to_owned())
+    }
+
+    #[test]
+    fn add_elements() {
+        let xml = apply_patch(
+            MPD,
+            &patch(
+                r#"<add sel="/MPD/Period[@id='1']/AdaptationSet[@id='1']/Representation[@id='v1']" pos="before"><Representation id="v0" bandwidth="500000"/></add>
+<add sel="/MPD/Period[@id='1']/AdaptationSet[@id='1']/Representation[@id='v1']" pos="after"><Representation id="v2" bandwidth="2000000"/></add>
+<add sel="/MPD/Period[@id='1']/AdaptationSet[@id='1']" pos="prepend"><Representation id="first" bandwidth="100000"/></add>
+<add sel="/MPD/Period[@id='1']/AdaptationSet[@id='1']/SegmentTemplate/SegmentTimeline"><S t="2" d="2"/></add>"#,
+            ),
+        )
+        .unwrap();
+        let mpd = dash_mpd::parse(&xml).unwrap();
+
+        assert_eq!(
+            representation_ids(&mpd),
+            vec![vec!["first", "v0", "v1", "v2"], vec!["a1"]]
+        );
+
+        let timeline = mpd.periods[0].adaptations[0]
+            .SegmentTemplate
+            .as_ref()
+            .and_then(|x| x.SegmentTimeline.as_ref())
+            .unwrap();
+        assert_eq!(
+            timeline.segments.iter().map(|x| x.t).collect::<Vec<_>>(),
+            vec![Some(0), Some(2)]
+        );
+    }
+
+    #[test]
+    fn replace_and_remove_elements() {
+        let xml = apply_patch(
+            MPD,
+            &patch(
+                r#"<replace sel="/MPD/Period/AdaptationSet[@id='1']/Representation[@id='v1']"><Representation id="v1" bandwidth="3000000" width="1920" height="1080"/></replace>
+<remove sel="/MPD/Period/AdaptationSet[2]"/>"#,
+            ),
+        )
+        .unwrap();
+        let mpd = dash_mpd::parse(&xml).unwrap();
+
+        assert_eq!(representation_ids(&mpd), vec![vec!["v1"]]);
+
+        let representation = &mpd.periods[0].adaptations[0].representations[0];
+        assert_eq!(representation.bandwidth, Some(3000000));
+        assert_eq!(representation.height, Some(1080));
+    }
+
+    #[test]
+    fn patch_attributes() {
+        let xml = apply_patch(
+            MPD,
+            &patch(
+                r#"<replace sel="/MPD/@publishTime">2024-01-01T00:00:02Z</replace>
+<add sel="/MPD/Period/AdaptationSet[@id='2']" type="@lang">en</add>
+<add sel="/MPD/Period/AdaptationSet[@id='2']/@codecs">mp4a.40.2</add>
+<remove sel="/MPD/Period/AdaptationSet[@id='1']/Representation[@id='v1']/@width"/>"#,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            attribute(&xml, "/MPD/@publishTime").as_deref(),
+            Some("2024-01-01T00:00:02Z")
+        );
+        assert_eq!(
+            attribute(&xml, "/MPD/Period/AdaptationSet[@id='2']/@lang").as_deref(),
+            Some("en")
+        );
+        assert_eq!(
+            attribute(&xml, "/MPD/Period/AdaptationSet[@id='2']/@codecs").as_deref(),
+            Some("mp4a.40.2")
+        );
+        assert_eq!(
+            attribute(&xml, "/MPD/Period/AdaptationSet[1]/Representation/@width"),
+            None
+        );
+
+        let mpd = dash_mpd::parse(&xml).unwrap();
+        assert_eq!(mpd.periods[0].adaptations[1].lang.as_deref(), Some("en"));
+        assert_eq!(mpd.periods[0].adaptations[0].representations[0].width, None);
+    }
+
+    #[test]
+    fn patch_not_applicable() {
+        for patch in [
+            patch("").replace("mpdId=\"live\"", "mpdId=\"vod\""),
+            patch("").replace(
+                "originalPublishTime=\"2024-01-01T00:00:00Z\"",
+                "originalPublishTime=\"2023-12-31T23:59:58Z\"",
+            ),
+        ] {
+            assert_eq!(
+                apply_patch(MPD, &patch).unwrap_err().to_string(),
+                "mpd patch is not applicable on current mpd."
+            );
+        }
+    }
+}
@@ -6,17 +6,23 @@
     2. https://github.com/streamlink/streamlink/blob/781ef1fc92f215d0f3ec9a272fbe9f2cac122f08/src/streamlink/stream/dash_manifest.py
     2. https://github.com/nilaoda/N_m3u8DL-RE/blob/7bba10aa0d7adf7e79e0feec7327039681cb7bd4/src/N_m3u8DL-RE.Parser/Extractor/DASHExtractor2.cs
 
-    
+
 */
 
-use super::{DashUrl, Template};
-use crate::playlist::{
-    Key, KeyMethod, Map, MasterPlaylist, MediaPlaylist, MediaType, PlaylistType, Range, Segment,
+use super::{DashUrl, MpdProtection, Template};
+use crate::{
+    playlist::{
+        Key, KeyMethod, Map, MasterPlaylist, MediaPlaylist, MediaType, PlaylistType, Range,
+        Segment, Tiles, VideoRange,
+    },
+    utils,
 };
 use anyhow::{anyhow, bail, Result};
-use dash_mpd::MPD;
+use chrono::{DateTime, Utc};
+use dash_mpd::{ContentProtection, EssentialProperty, MPD};
 use reqwest::Url;
 use std::collections::HashMap;
+use vsd_mp4::pssh::Pssh;
 
 pub(crate) fn parse_as_master(mpd: &MPD, uri: &str) -> MasterPlaylist {
     let mut streams = vec![];
@@ -45,6 +51,7 @@ pub(crate) fn parse_as_master(mpd: &MPD, uri: &str) -> MasterPlaylist {
                     match mime_type.as_str() {
                         "application/ttml+xml" | "application/x-sami" => MediaType::Subtitles,
                         x if x.starts_with("audio") => MediaType::Audio,
+                        x if x.starts_with("image") => MediaType::Thumbnails,
                         x if x.starts_with("text") => MediaType::Subtitles,
                         x if x.starts_with("video") => MediaType::Video,
                         _ => MediaType::Undefined,
@@ -63,26 +70,74 @@ pub(crate) fn parse_as_master(mpd: &MPD, uri: &str) -> MasterPlaylist {
                     }
                 }
 
-                // if let Some(role) = &representation.role {
-                //     if let Some(value) = &role.value {
-                //         if value == "subtitle" {
-                //             media_type = MediaType::Subtitles;
-                //         }
-                //     }
-                // }
+                let roles = parse_roles(&adaptation_set.Role);
+
+                if media_type == MediaType::Undefined
+                    && roles
+                        .iter()
+                        .any(|x| x == "subtitle" || x == "caption" || x == "forced-subtitle")
+                {
+                    media_type = MediaType::Subtitles;
+                }
+
+                let tiles = parse_tiles(
+                    representation
+                        .essential_property
+                        .iter()
+                        .chain(adaptation_set.essential_property.iter()),
+                    representation.width.zip(representation.height),
+                );
+
+                if tiles.is_some() {
+                    media_type = MediaType::Thumbnails;
+                }
+
+                let video_range = if media_type == MediaType::Video {
+                    Some(parse_video_range(
+                        &codecs,
+                        representation
+                            .essential_property
+                            .iter()
+                            .chain(adaptation_set.essential_property.iter())
+                            .map(|x| (&x.schemeIdUri, &x.value))
+                            .chain(
+                                representation
+                                    .supplemental_property
+                                    .iter()
+                                    .chain(adaptation_set.supplemental_property.iter())
+                                    .map(|x| (&x.schemeIdUri, &x.value)),
+                            ),
+                    ))
+                } else {
+                    None
+                };
+
+                let object_audio = media_type == MediaType::Audio
+                    && parse_object_audio(
+                        &codecs,
+                        representation
+                            .essential_property
+                            .iter()
+                            .chain(adaptation_set.essential_property.iter())
+                            .map(|x| (&x.schemeIdUri, &x.value))
+                            .chain(
+                                representation
+                                    .supplemental_property
+                                    .iter()
+                                    .chain(adaptation_set.supplemental_property.iter())
+                                    .map(|x| (&x.schemeIdUri, &x.value)),
+                            ),
+                    );
 
                 streams.push(MediaPlaylist {
+                    accessibility: parse_accessibility(&adaptation_set.Accessibility),
                     bandwidth: representation.bandwidth,
-                    channels: representation
-                        .AudioChannelConfiguration
-                        .get(0)
-                        .and_then(|x| x.value.as_ref().map(|y| y.parse::<f32>().ok()))
-                        .flatten()
-                        .or(adaptation_set
+                    channels: parse_channels(
+                        representation
                             .AudioChannelConfiguration
-                            .get(0)
-                            .and_then(|x| x.value.as_ref().map(|y| y.parse::<f32>().ok()))
-                            .flatten()),
+                            .iter()
+                            .chain(adaptation_set.AudioChannelConfiguration.iter()),
+                    ),
                     codecs,
                     extension: mime_type
                         .as_ref()
@@ -94,14 +149,23 @@ pub(crate) fn parse_as_master(mpd: &MPD, uri: &str) -> MasterPlaylist {
                     } else {
                         None
                     },
+                    group_id: None,
+                    groups: Default::default(),
                     i_frame: false, // Cannot be comment here
-                    language: adaptation_set.lang.clone(),
+                    id: representation.id.clone().unwrap_or_else(|| {
+                        DashUrl::new(period_index, adaptation_index, representation_index)
+                            .to_string()
+                    }),
+                    label: parse_label(&representation.Label)
+                        .or(parse_label(&adaptation_set.Label)),
+                    language: representation.lang.clone().or(adaptation_set.lang.clone()),
                     live: if let Some(mpdtype) = &mpd.mpdtype {
                         mpdtype == "dynamic"
                     } else {
                         false
                     },
                     media_type,
+                    object_audio,
                     playlist_type: PlaylistType::Dash,
                     resolution: if let (Some(width), Some(height)) =
                         (representation.width, representation.height)
@@ -110,25 +174,72 @@ pub(crate) fn parse_as_master(mpd: &MPD, uri: &str) -> MasterPlaylist {
                     } else {
                         None
                     },
+                    roles,
                     segments: vec![], // Cannot be comment here
+                    tiles,
                     uri: DashUrl::new(period_index, adaptation_index, representation_index)
                         .to_string(),
+                    video_range,
                 });
             }
         }
     }
 
     MasterPlaylist {
+        duration: mpd
+            .periods
+            .first()
+            .and_then(|x| x.duration)
+            .or(mpd.mediaPresentationDuration)
+            .map(|x| x.as_secs_f32()),
         playlist_type: PlaylistType::Dash,
         uri: uri.to_owned(),
         streams,
     }
 }
 
-pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &str) -> Result<()> {
+/// Locate representation of a selected stream again by its `Representation@id` in a reloaded
+/// manifest, adaptation sets and representations may have been added or removed meanwhile.
+pub(crate) fn relocate(mpd: &MPD, playlist: &mut MediaPlaylist) -> Result<()> {
+    // Streams without Representation@id can only be located by their position.
+    if playlist.id.starts_with("dash://") {
+        return Ok(());
+    }
+
+    let period_index = 0;
+
+    if let Some(period) = mpd.periods.get(period_index) {
+        for (adaptation_index, adaptation_set) in period.adaptations.iter().enumerate() {
+            for (representation_index, representation) in
+                adaptation_set.representations.iter().enumerate()
+            {
+                if representation.id.as_ref() == Some(&playlist.id) {
+                    playlist.uri =
+                        DashUrl::new(period_index, adaptation_index, representation_index)
+                            .to_string();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    bail!(
+        "representation {} is not present in reloaded dash playlist.",
+        playlist.id
+    );
+}
+
+pub(crate) fn push_segments(
+    mpd: &MPD,
+    protection: &MpdProtection,
+    playlist: &mut MediaPlaylist,
+    base_url: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
     let location = playlist.uri.parse::<DashUrl>().map_err(|x| anyhow!(x))?;
+    let live = mpd.mpdtype.as_deref() == Some("dynamic");
 
-    for (_period_index, period) in mpd.periods.iter().enumerate() {
+    for (period_index, period) in mpd.periods.iter().enumerate() {
         for (adaptation_index, adaptation_set) in period.adaptations.iter().enumerate() {
             for (representation_index, representation) in
                 adaptation_set.representations.iter().enumerate()
@@ -146,6 +257,12 @@ pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &
                         period_duration_secs = duration.as_secs_f32();
                     }
 
+                    // Time elapsed since start of period, segments after this are not available yet.
+                    let live_edge_secs = mpd.availabilityStartTime.filter(|_| live).map(|x| {
+                        (now - x).num_milliseconds() as f32 / 1000.0
+                            - period.start.map(|x| x.as_secs_f32()).unwrap_or(0.0)
+                    });
+
                     let mut base_url = base_url.parse::<Url>().unwrap();
 
                     if let Some(mpd_baseurl) = mpd.base_url.get(0).map(|x| x.base.as_ref()) {
@@ -293,10 +410,12 @@ pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &
                             if let Some(media) = &segment_template.media {
                                 let media = template.resolve(media);
                                 let timescale = segment_template.timescale.unwrap_or(1) as f32;
+                                let presentation_time_offset =
+                                    segment_template.presentationTimeOffset.unwrap_or(0) as f32;
                                 let mut segment_time = 0;
                                 let mut number = segment_template.startNumber.unwrap_or(1);
 
-                                for s in &segment_timeline.segments {
+                                for (i, s) in segment_timeline.segments.iter().enumerate() {
                                     if let Some(t) = s.t {
                                         segment_time = t;
                                     }
@@ -320,8 +439,16 @@ pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &
 
                                     if let Some(r) = s.r {
                                         let mut count = 0;
-                                        // FIXME - Perhaps we also need to account for startTime?
-                                        let end_time = period_duration_secs * timescale;
+                                        let end_time = if let Some(t) =
+                                            segment_timeline.segments.get(i + 1).and_then(|x| x.t)
+                                        {
+                                            t as f32
+                                        } else if let Some(live_edge_secs) = live_edge_secs {
+                                            live_edge_secs * timescale + presentation_time_offset
+                                        } else {
+                                            period_duration_secs * timescale
+                                                + presentation_time_offset
+                                        };
 
                                         loop {
                                             count += 1;
@@ -334,7 +461,7 @@ pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &
                                                 if count > r {
                                                     break;
                                                 }
-                                            } else if segment_time as f32 > end_time {
+                                            } else if (segment_time + s.d) as f32 >= end_time {
                                                 break;
                                             }
 
@@ -388,6 +515,20 @@ pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &
                                     total_number -= 1;
                                 }
 
+                                if let Some(live_edge_secs) =
+                                    live_edge_secs.filter(|_| period_duration_secs == 0.0)
+                                {
+                                    // Only completely available segments within time shift buffer are listed.
+                                    let available = (live_edge_secs / duration).floor() as i64;
+                                    let window = mpd
+                                        .timeShiftBufferDepth
+                                        .map(|x| (x.as_secs_f32() / duration).floor() as i64)
+                                        .unwrap_or(available);
+
+                                    number += (available - window).max(0);
+                                    total_number = available.min(window).max(0);
+                                }
+
                                 for _ in 1..=total_number {
                                     template.insert("Number", number.to_string());
 
@@ -489,6 +630,17 @@ pub(crate) fn push_segments(mpd: &MPD, playlist: &mut MediaPlaylist, base_url: &
                                 iv: None,
                                 key_format: None,
                                 method: x,
+                                pssh: parse_pssh(
+                                    representation
+                                        .ContentProtection
+                                        .iter()
+                                        .chain(adaptation_set.ContentProtection.iter()),
+                                    protection.playready_objects(
+                                        period_index,
+                                        adaptation_index,
+                                        representation_index,
+                                    ),
+                                ),
                                 uri: None,
                             }),
                         };
@@ -624,6 +776,179 @@ fn parse_frame_rate(frame_rate: &Option<String>) -> Option<f32> {
     })
 }
 
+fn parse_pssh<'a>(
+    content_protections: impl Iterator<Item = &'a ContentProtection>,
+    playready_objects: Vec<String>,
+) -> Option<Pssh> {
+    let mut pssh = Pssh::default();
+
+    // Invalid pssh data is ignored here, key ids are also read from initialization segments.
+    for content_protection in content_protections {
+        for cenc_pssh in &content_protection.cenc_pssh {
+            if let Some(data) = &cenc_pssh.content {
+                if let Ok(Ok(x)) = utils::decode_base64(data.trim()).map(|x| Pssh::new(&x)) {
+                    pssh.extend(x);
+                }
+            }
+        }
+    }
+
+    for data in playready_objects {
+        if let Ok(Ok(x)) = utils::decode_base64(data).map(|x| Pssh::from_playready_object(&x)) {
+            pssh.extend(x);
+        }
+    }
+
+    if pssh.system_ids.is_empty() {
+        None
+    } else {
+        Some(pssh)
+    }
+}
+
+fn parse_roles(roles: &[dash_mpd::Role]) -> Vec<String> {
+    roles
+        .iter()
+        .filter_map(|x| x.value.as_ref())
+        .map(|x| match x.as_str() {
+            // DASH-IF IOP uses "forced-subtitle", some packagers write "forced_subtitle".
+            "forced_subtitle" | "forced-subtitles" => "forced-subtitle".to_owned(),
+            x => x.to_owned(),
+        })
+        .collect()
+}
+
+fn parse_accessibility(accessibility: &Option<dash_mpd::Accessibility>) -> Vec<String> {
+    accessibility
+        .iter()
+        .filter_map(|x| match (x.schemeIdUri.as_deref(), x.value.as_deref()) {
+            // https://dashif.org/docs/DASH-IF-IOP-v4.3.pdf (table 31)
+            (Some("urn:tva:metadata:cs:AudioPurposeCS:2007"), Some("1")) => {
+                Some("description".to_owned())
+            }
+            (Some("urn:tva:metadata:cs:AudioPurposeCS:2007"), Some("2")) => {
+                Some("caption".to_owned())
+            }
+            (Some("urn:mpeg:dash:role:2011"), Some(x)) => Some(x.to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_label(labels: &[dash_mpd::Label]) -> Option<String> {
+    labels
+        .iter()
+        .map(|x| x.content.trim())
+        .find(|x| !x.is_empty())
+        .map(|x| x.to_owned())
+}
+
+// https://dashif.org/docs/DASH-IF-IOP-v4.3.pdf (6.2.6 Tiles of thumbnails)
+fn parse_tiles<'a>(
+    properties: impl Iterator<Item = &'a EssentialProperty>,
+    resolution: Option<(u64, u64)>,
+) -> Option<Tiles> {
+    for property in properties {
+        if property.schemeIdUri == "http://dashif.org/thumbnail_tile"
+            || property.schemeIdUri == "http://dashif.org/guidelines/thumbnail_tile"
+        {
+            let mut tiles = property
+                .value
+                .as_ref()
+                .and_then(|x| Tiles::from_layout(x))?;
+
+            tiles.resolution = resolution.map(|(w, h)| (w / tiles.columns, h / tiles.rows));
+            return Some(tiles);
+        }
+    }
+
+    None
+}
+
+/// Dolby vision codecs or transfer characteristics from essential (preferred) and supplemental properties.
+/// Backward compatible hlg streams are signalled as sdr in essential and hlg in supplemental property.
+fn parse_video_range<'a>(
+    codecs: &Option<String>,
+    properties: impl Iterator<Item = (&'a String, &'a Option<String>)>,
+) -> VideoRange {
+    if let Some(x) = codecs.as_deref().and_then(VideoRange::from_codecs) {
+        return x;
+    }
+
+    let video_ranges = properties
+        .filter(|(x, _)| *x == "urn:mpeg:mpegB:cicp:TransferCharacteristics")
+        .filter_map(|(_, x)| {
+            x.as_deref()
+                .and_then(VideoRange::from_transfer_characteristics)
+        })
+        .collect::<Vec<_>>();
+
+    if video_ranges.contains(&VideoRange::Hlg) {
+        VideoRange::Hlg
+    } else {
+        video_ranges.first().copied().unwrap_or(VideoRange::Sdr)
+    }
+}
+
+/// Channel count from first audio channel configuration of a known scheme.
+fn parse_channels<'a>(
+    configurations: impl Iterator<Item = &'a dash_mpd::AudioChannelConfiguration>,
+) -> Option<f32> {
+    configurations
+        .filter_map(|x| Some((x.schemeIdUri.as_deref()?, x.value.as_deref()?.trim())))
+        .find_map(|(scheme_id_uri, value)| match scheme_id_uri {
+            // ISO/IEC 23001-8 ChannelConfiguration code points.
+            "urn:mpeg:mpegB:cicp:ChannelConfiguration" => match value {
+                "1" | "2" | "3" | "4" | "5" | "6" => value.parse::<f32>().ok(),
+                "7" | "12" | "14" => Some(8.0),
+                "8" => Some(2.0),
+                "9" => Some(3.0),
+                "10" => Some(4.0),
+                "11" => Some(7.0),
+                "13" => Some(24.0),
+                "15" | "17" | "19" => Some(12.0),
+                "16" => Some(10.0),
+                "18" | "20" => Some(14.0),
+                _ => None,
+            },
+            // Hex bit mask of channels (L, C, R, Ls, Rs, Lc/Rc, Lrs/Rrs, Cs, Ts, Lsd/Rsd, Lw/Rw, Vhl/Vhr, Vhc, Lts/Rts, LFE2, LFE).
+            "tag:dolby.com,2014:dash:audio_channel_configuration:2011"
+            | "urn:dolby:dash:audio_channel_configuration:2011" => {
+                let mask = u16::from_str_radix(value, 16).ok()?;
+                Some(
+                    (0..16)
+                        .filter(|x| mask & (0x8000 >> x) != 0)
+                        .map(|x| {
+                            if matches!(x, 5 | 6 | 9 | 10 | 11 | 13) {
+                                2
+                            } else {
+                                1
+                            }
+                        })
+                        .sum::<u16>() as f32,
+                )
+            }
+            _ => value.parse::<f32>().ok(),
+        })
+}
+
+/// Dolby atmos (E-AC-3 JOC) is signalled using `ec+3` codec or extension type properties.
+fn parse_object_audio<'a>(
+    codecs: &Option<String>,
+    mut properties: impl Iterator<Item = (&'a String, &'a Option<String>)>,
+) -> bool {
+    codecs
+        .as_deref()
+        .is_some_and(|x| x.split(',').any(|y| y.trim() == "ec+3"))
+        || properties.any(|(x, y)| {
+            matches!(
+                x.as_str(),
+                "tag:dolby.com,2018:dash:EC3_ExtensionType:2018"
+                    | "tag:dolby.com,2014:dash:DolbyDigitalPlusExtensionType:2014"
+            ) && matches!(y.as_deref(), Some("JOC" | "ec+3"))
+        })
+}
+
 fn parse_range(range: &Option<String>) -> Option<Range> {
     range.as_ref().map(|range| {
         if let Some((Some(start), Some(end))) = range
@@ -637,28 +962,84 @@ fn parse_range(range: &Option<String>) -> Option<Range> {
     })
 }
 
-// // Sec-Fetch-Mode: navigate
-// // Upgrade-Insecure-Requests: 1
-// /*
-// A manifest may use a data URL (RFC 2397) to embed media content such as the
-// initialization segment directly in the manifest (recommended by YouTube for live
-// streaming, but uncommon in practice).
-//  */
-// if url.scheme() == "data" {
-//     let us = &url.to_string();
-//     let du = DataUrl::process(us)
-//         .map_err(|_| DashMpdError::Parsing(String::from("parsing data URL")))?;
-//     if du.mime_type().type_ != "audio" {
-//         return Err(DashMpdError::UnhandledMediaStream(
-//             String::from("expecting audio content in data URL")));
-//     }
-//     let (body, _fragment) = du.decode_to_vec()
-//         .map_err(|_| DashMpdError::Parsing(String::from("decoding data URL")))?;
-//     if downloader.verbosity > 2 {
-//         println!("Audio segment data URL -> {} octets", body.len());
-//     }
-//     if let Err(e) = tmpfile_audio.write_all(&body) {
-//         log::error!("Unable to write DASH audio data: {e:?}");
-//         return Err(DashMpdError::Io(e, String::from("writing DASH audio data")));
-//     }
-//     have_audio = true;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" mediaPresentationDuration="PT10S" profiles="urn:mpeg:dash:profile:isoff-on-demand:2011">
+  <Period>
+    <AdaptationSet mimeType="audio/mp4" lang="en">
+      <Accessibility schemeIdUri="urn:tva:metadata:cs:AudioPurposeCS:2007" value="1"/>
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="alternate"/>
+      <Label> </Label>
+      <Label>English (Audio Description)</Label>
+      <Representation id="audio" bandwidth="128000">
+        <BaseURL>audio.mp4</BaseURL>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet mimeType="text/vtt" lang="en">
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="forced_subtitle"/>
+      <Label>Forced</Label>
+      <Representation id="forced" bandwidth="256">
+        <Label>English (Forced)</Label>
+        <BaseURL>forced.vtt</BaseURL>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet mimeType="text/vtt" lang="en">
+      <Accessibility schemeIdUri="urn:mpeg:dash:role:2011" value="caption"/>
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="subtitle"/>
+      <Representation id="sdh" bandwidth="256">
+        <BaseURL>sdh.vtt</BaseURL>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    #[test]
+    fn roles_accessibility_and_labels() {
+        let mpd = dash_mpd::parse(MPD).unwrap();
+        let streams = parse_as_master(&mpd, "https://example.com/manifest.mpd").streams;
+
+        assert_eq!(streams[0].roles, vec!["alternate"]);
+        assert_eq!(streams[0].accessibility, vec!["description"]);
+        assert_eq!(
+            streams[0].label.as_deref(),
+            Some("English (Audio Description)")
+        );
+        assert!(streams[0].has_role("description"));
+        assert_eq!(streams[0].dispositions(), vec!["visual_impaired"]);
+
+        assert_eq!(streams[1].roles, vec!["forced-subtitle"]);
+        assert!(streams[1].accessibility.is_empty());
+        assert_eq!(streams[1].label.as_deref(), Some("English (Forced)"));
+        assert!(!streams[1].has_role("caption"));
+        assert_eq!(streams[1].dispositions(), vec!["forced"]);
+
+        assert_eq!(streams[2].roles, vec!["subtitle"]);
+        assert_eq!(streams[2].accessibility, vec!["caption"]);
+        assert_eq!(streams[2].label, None);
+        assert!(streams[2].has_role("caption"));
+        assert_eq!(streams[2].dispositions(), vec!["hearing_impaired"]);
+    }
+
+    #[test]
+    fn relocate_after_reload() {
+        let mpd = dash_mpd::parse(MPD).unwrap();
+        let mut streams = parse_as_master(&mpd, "https://example.com/manifest.mpd").streams;
+
+        // Audio description adaptation set is removed in reloaded manifest.
+        let start = MPD
+            .find("    <AdaptationSet mimeType=\"audio/mp4\"")
+            .unwrap();
+        let end = start + MPD[start..].find("</AdaptationSet>").unwrap() + 17;
+        let reloaded = dash_mpd::parse(&format!("{}{}", &MPD[..start], &MPD[end..])).unwrap();
+
+        relocate(&reloaded, &mut streams[2]).unwrap();
+        assert_eq!(
+            streams[2].uri,
+            "dash://period.0.adaptation-set.1.representation.0"
+        );
+        assert!(relocate(&reloaded, &mut streams[0]).is_err());
+    }
+}
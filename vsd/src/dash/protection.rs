@@ -0,0 +1,204 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://learn.microsoft.com/en-us/playready/specifications/mpeg-dash-playready
+
+*/
+
+use kdam::term::Colorizer;
+use quick_xml::{events::Event, Reader};
+
+// dash-mpd doesn't deserialize mspr:pro elements inside ContentProtection nodes,
+// so only those are read from the manifest again here. A streaming reader is used
+// instead of serde, which can't collect non-contiguous sibling elements.
+
+#[derive(Default)]
+pub(crate) struct MpdProtection {
+    periods: Vec<Period>,
+}
+
+#[derive(Default)]
+struct Period {
+    adaptation_sets: Vec<AdaptationSet>,
+}
+
+#[derive(Default)]
+struct AdaptationSet {
+    playready_objects: Vec<String>,
+    representations: Vec<Representation>,
+}
+
+#[derive(Default)]
+struct Representation {
+    playready_objects: Vec<String>,
+}
+
+impl MpdProtection {
+    pub(crate) fn parse(xml: &str) -> Self {
+        match Self::read(xml) {
+            Ok(protection) => protection,
+            Err(e) => {
+                println!(
+                    "    {} couldn't parse playready objects (mspr:pro) from manifest ({}), their kids and pssh data are skipped",
+                    "Warning".colorize("bold yellow"),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    fn read(xml: &str) -> Result<Self, quick_xml::Error> {
+        let mut reader = Reader::from_str(xml);
+        let mut protection = Self::default();
+        let mut in_representation = false;
+        let mut in_content_protection = false;
+        let mut pro = None::<String>;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) => match e.local_name().as_ref() {
+                    b"Representation" => {
+                        in_representation = true;
+                        protection.push(e.local_name().as_ref());
+                    }
+                    b"ContentProtection" => in_content_protection = true,
+                    b"pro" if in_content_protection => pro = Some(String::new()),
+                    x => protection.push(x),
+                },
+                Event::Empty(e) => protection.push(e.local_name().as_ref()),
+                Event::Text(e) => {
+                    if let Some(pro) = &mut pro {
+                        pro.push_str(&e.unescape()?);
+                    }
+                }
+                Event::CData(e) => {
+                    if let Some(pro) = &mut pro {
+                        pro.push_str(&String::from_utf8_lossy(&e));
+                    }
+                }
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"Representation" => in_representation = false,
+                    b"ContentProtection" => in_content_protection = false,
+                    b"pro" => {
+                        if let Some(pro) = pro.take() {
+                            let adaptation_set = protection
+                                .periods
+                                .last_mut()
+                                .and_then(|x| x.adaptation_sets.last_mut());
+
+                            if in_representation {
+                                if let Some(representation) =
+                                    adaptation_set.and_then(|x| x.representations.last_mut())
+                                {
+                                    representation.playready_objects.push(pro);
+                                }
+                            } else if let Some(adaptation_set) = adaptation_set {
+                                adaptation_set.playready_objects.push(pro);
+                            }
+                        }
+                    }
+                    _ => (),
+                },
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+
+        Ok(protection)
+    }
+
+    fn push(&mut self, element: &[u8]) {
+        match element {
+            b"Period" => self.periods.push(Period::default()),
+            b"AdaptationSet" => {
+                if let Some(period) = self.periods.last_mut() {
+                    period.adaptation_sets.push(AdaptationSet::default());
+                }
+            }
+            b"Representation" => {
+                if let Some(adaptation_set) = self
+                    .periods
+                    .last_mut()
+                    .and_then(|x| x.adaptation_sets.last_mut())
+                {
+                    adaptation_set
+                        .representations
+                        .push(Representation::default());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Base64 encoded playready objects of a representation (falling back to its adaptation set).
+    pub(super) fn playready_objects(
+        &self,
+        period: usize,
+        adaptation_set: usize,
+        representation: usize,
+    ) -> Vec<String> {
+        let adaptation_set = match self
+            .periods
+            .get(period)
+            .and_then(|x| x.adaptation_sets.get(adaptation_set))
+        {
+            Some(x) => x,
+            None => return vec![],
+        };
+
+        adaptation_set
+            .representations
+            .get(representation)
+            .map(|x| x.playready_objects.iter())
+            .into_iter()
+            .flatten()
+            .chain(adaptation_set.playready_objects.iter())
+            .map(|x| x.trim().to_owned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleaved_siblings() {
+        let protection = MpdProtection::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" xmlns:mspr="urn:microsoft:playready">
+  <Period>
+    <AdaptationSet mimeType="video/mp4">
+      <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" value="cenc"/>
+      <Representation id="video-1" bandwidth="1000000"/>
+      <ContentProtection schemeIdUri="urn:uuid:9a04f079-9840-4286-ab92-e65be0885f95">
+        <mspr:pro>video</mspr:pro>
+      </ContentProtection>
+      <Representation id="video-2" bandwidth="2000000">
+        <ContentProtection schemeIdUri="urn:uuid:9a04f079-9840-4286-ab92-e65be0885f95">
+          <mspr:pro> video-2 </mspr:pro>
+        </ContentProtection>
+      </Representation>
+    </AdaptationSet>
+    <EventStream schemeIdUri="urn:example"/>
+    <AdaptationSet mimeType="audio/mp4">
+      <Representation id="audio" bandwidth="128000"/>
+      <ContentProtection schemeIdUri="urn:uuid:9a04f079-9840-4286-ab92-e65be0885f95">
+        <pro>audio</pro>
+      </ContentProtection>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        );
+
+        assert_eq!(protection.playready_objects(0, 0, 0), vec!["video"]);
+        assert_eq!(
+            protection.playready_objects(0, 0, 1),
+            vec!["video-2", "video"]
+        );
+        assert_eq!(protection.playready_objects(0, 1, 0), vec!["audio"]);
+        assert!(protection.playready_objects(0, 2, 0).is_empty());
+    }
+}
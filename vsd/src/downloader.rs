@@ -1,48 +1,106 @@
 use crate::{
-    commands::Quality,
+    commands::{Quality, QualityFallback},
+    filter::StreamFilter,
     merger::Merger,
-    playlist::{KeyMethod, MediaType, PlaylistType, Range, Segment},
-    update, utils,
+    playlist::{
+        AudioChannels, AudioCodec, Key, KeyMethod, MasterPlaylist, MediaPlaylist, MediaType,
+        PlaylistType, Range, Segment, VideoCodec, VideoRange,
+    },
+    utils,
 };
 use anyhow::{anyhow, bail, Result};
 use kdam::{term::Colorizer, tqdm, BarExt, Column, RichProgress};
-use reqwest::{
-    blocking::{Client, RequestBuilder},
-    header, StatusCode, Url,
-};
+use reqwest::{blocking::Client, header, StatusCode, Url};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     fs::File,
-    io::Write,
+    io::{Read, Write},
     path::{Path, PathBuf},
     process::{Command, Stdio},
     sync::{Arc, Mutex},
     time::Instant,
 };
 use vsd_mp4::{
-    pssh::Pssh,
+    pssh::{KeyIdSystemType, Pssh},
     text::{ttml_text_parser, Mp4TtmlParser, Mp4VttParser},
+    TrackType,
 };
 
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn download(
-    all_keys: bool,
-    base_url: Option<Url>,
-    client: Client,
-    directory: Option<PathBuf>,
-    input: &str,
-    keys: Vec<(Option<String>, String)>,
-    no_decrypt: bool,
-    no_merge: bool,
-    output: Option<String>,
-    prefer_audio_lang: Option<String>,
-    prefer_subs_lang: Option<String>,
-    quality: Quality,
-    skip_prompts: bool,
-    raw_prompts: bool,
-    retry_count: u8,
-    threads: u8,
-) -> Result<()> {
+/// Options of `save` sub-command used while downloading a playlist.
+#[derive(Clone)]
+pub(crate) struct DownloadOptions {
+    pub(crate) all_keys: bool,
+    pub(crate) audio_channels: AudioChannels,
+    pub(crate) audio_lang: Vec<String>,
+    pub(crate) base_url: Option<Url>,
+    pub(crate) directory: Option<PathBuf>,
+    pub(crate) exclude_roles: Vec<String>,
+    pub(crate) json: bool,
+    pub(crate) keys: Vec<(Option<String>, String)>,
+    pub(crate) list_streams: bool,
+    pub(crate) max_bitrate: Option<u64>,
+    pub(crate) max_size: Option<u64>,
+    pub(crate) min_bitrate: Option<u64>,
+    pub(crate) no_decrypt: bool,
+    pub(crate) no_merge: bool,
+    pub(crate) output: Option<String>,
+    pub(crate) prefer_audio_codec: Vec<AudioCodec>,
+    pub(crate) prefer_audio_lang: Option<String>,
+    pub(crate) prefer_codec: Vec<VideoCodec>,
+    pub(crate) prefer_subs_lang: Option<String>,
+    pub(crate) quality: Quality,
+    pub(crate) quality_fallback: QualityFallback,
+    pub(crate) require_langs: bool,
+    pub(crate) select_audio: Option<StreamFilter>,
+    pub(crate) select_entries: Option<String>,
+    pub(crate) select_id: Vec<String>,
+    pub(crate) select_subs: Option<StreamFilter>,
+    pub(crate) select_video: Option<StreamFilter>,
+    pub(crate) skip_prompts: bool,
+    pub(crate) subs_lang: Vec<String>,
+    pub(crate) raw_prompts: bool,
+    pub(crate) retry_count: u8,
+    pub(crate) threads: u8,
+    pub(crate) video_range: Vec<VideoRange>,
+}
+
+pub(crate) fn download(client: Client, input: &str, options: DownloadOptions) -> Result<()> {
+    let DownloadOptions {
+        all_keys,
+        audio_channels,
+        audio_lang,
+        base_url,
+        directory,
+        exclude_roles,
+        json,
+        keys,
+        list_streams,
+        max_bitrate,
+        max_size,
+        min_bitrate,
+        no_decrypt,
+        no_merge,
+        output,
+        prefer_audio_codec,
+        prefer_audio_lang,
+        prefer_codec,
+        prefer_subs_lang,
+        quality,
+        quality_fallback,
+        require_langs,
+        select_audio,
+        select_entries,
+        select_id,
+        select_subs,
+        select_video,
+        skip_prompts,
+        subs_lang,
+        raw_prompts,
+        retry_count,
+        threads,
+        video_range,
+    } = options.clone();
+
     let mut playlist_url = base_url
         .clone()
         .unwrap_or_else(|| "https://example.com".parse::<Url>().unwrap());
@@ -52,10 +110,11 @@ pub(crate) fn download(
     // -----------------------------------------------------------------------------------------
 
     let mut playlist_type = None;
+    let mut progressive = None;
     let path = Path::new(input);
 
     let playlist = if path.exists() {
-        if base_url.is_none() {
+        if base_url.is_none() && !list_streams {
             println!(
                 "    {} base url is not set",
                 "Warning".colorize("bold yellow")
@@ -68,6 +127,8 @@ pub(crate) fn download(
                 playlist_type = Some(PlaylistType::Dash);
             } else if ext == "m3u" || ext == "m3u8" {
                 playlist_type = Some(PlaylistType::Hls);
+            } else if ext == "ism" || ext == "ismc" {
+                playlist_type = Some(PlaylistType::Smooth);
             }
         }
 
@@ -78,6 +139,8 @@ pub(crate) fn download(
                 playlist_type = Some(PlaylistType::Dash);
             } else if text.contains("#EXTM3U") {
                 playlist_type = Some(PlaylistType::Hls);
+            } else if text.contains("<SmoothStreamingMedia") {
+                playlist_type = Some(PlaylistType::Smooth);
             }
         }
 
@@ -94,21 +157,33 @@ pub(crate) fn download(
                 b"application/x-mpegurl" | b"application/vnd.apple.mpegurl" => {
                     playlist_type = Some(PlaylistType::Hls)
                 }
+                b"application/vnd.ms-sstr+xml" => playlist_type = Some(PlaylistType::Smooth),
                 _ => (),
             }
         }
 
-        let text = response.text()?;
+        if playlist_type.is_none() {
+            progressive = progressive_media(&playlist_url, response.headers());
+        }
+
+        // Response body of progressive media is downloaded later using range requests.
+        let text = if progressive.is_some() {
+            String::new()
+        } else {
+            response.text()?
+        };
 
         if playlist_type.is_none() {
             if text.contains("<MPD") {
                 playlist_type = Some(PlaylistType::Dash);
             } else if text.contains("#EXTM3U") {
                 playlist_type = Some(PlaylistType::Hls);
+            } else if text.contains("<SmoothStreamingMedia") {
+                playlist_type = Some(PlaylistType::Smooth);
             }
         }
 
-        if playlist_type.is_none() {
+        if playlist_type.is_none() && progressive.is_none() {
             println!(
                 "   {} website for DASH and HLS playlists",
                 "Scraping".colorize("bold cyan")
@@ -182,6 +257,7 @@ pub(crate) fn download(
                     b"application/x-mpegurl" | b"application/vnd.apple.mpegurl" => {
                         playlist_type = Some(PlaylistType::Hls)
                     }
+                    b"application/vnd.ms-sstr+xml" => playlist_type = Some(PlaylistType::Smooth),
                     _ => (),
                 }
             }
@@ -193,6 +269,8 @@ pub(crate) fn download(
                     playlist_type = Some(PlaylistType::Dash);
                 } else if text.contains("#EXTM3U") {
                     playlist_type = Some(PlaylistType::Hls);
+                } else if text.contains("<SmoothStreamingMedia") {
+                    playlist_type = Some(PlaylistType::Smooth);
                 }
             }
 
@@ -202,11 +280,160 @@ pub(crate) fn download(
         }
     };
 
+    // -----------------------------------------------------------------------------------------
+    // Download IPTV Playlist Entries
+    // -----------------------------------------------------------------------------------------
+
+    let iptv_entries = if matches!(playlist_type, Some(PlaylistType::Hls)) {
+        crate::hls::parse_iptv_playlist(&playlist)
+    } else {
+        None
+    };
+
+    if let Some(entries) = iptv_entries {
+        if list_streams {
+            bail!("streams of IPTV playlist entries cannot be listed, list them one by one.");
+        }
+
+        let entries = crate::hls::select_entries(
+            entries,
+            select_entries.as_deref(),
+            skip_prompts,
+            raw_prompts,
+        )?;
+        let entries_count = entries.len();
+        let mut file_stems = HashSet::new();
+        let mut failed_entries = vec![];
+
+        for (i, entry) in entries.into_iter().enumerate() {
+            let mut file_stem = entry.file_stem();
+
+            if file_stem.is_empty() {
+                file_stem = format!("entry_{}", i + 1);
+            }
+
+            if file_stems.contains(&file_stem) {
+                for j in 1.. {
+                    let x = format!("{}_({})", file_stem, j);
+
+                    if !file_stems.contains(&x) {
+                        file_stem = x;
+                        break;
+                    }
+                }
+            }
+
+            file_stems.insert(file_stem.clone());
+
+            // Each entry is saved as its own file named after its title.
+            let entry_output = match output.as_ref().and_then(|x| Path::new(x).extension()) {
+                Some(ext) => format!("{}.{}", file_stem, ext.to_string_lossy()),
+                None => file_stem,
+            };
+
+            println!(
+                "\n   {} entry {}/{} {}",
+                "Starting".colorize("bold cyan"),
+                i + 1,
+                entries_count,
+                entry.title.colorize("cyan")
+            );
+
+            if let Err(e) = download(
+                client.clone(),
+                base_url
+                    .as_ref()
+                    .unwrap_or(&playlist_url)
+                    .join(&entry.uri)?
+                    .as_str(),
+                DownloadOptions {
+                    output: Some(entry_output),
+                    select_entries: None,
+                    ..options.clone()
+                },
+            ) {
+                println!("{}: {}", "error".colorize("bold red"), e);
+                failed_entries.push(entry.title);
+            }
+        }
+
+        if !failed_entries.is_empty() {
+            bail!(
+                "failed to download {} of {} entries ({}).",
+                failed_entries.len(),
+                entries_count,
+                failed_entries.join(", ")
+            );
+        }
+
+        return Ok(());
+    }
+
     // -----------------------------------------------------------------------------------------
     // Parse Playlist & Select Streams & Push Segments
     // -----------------------------------------------------------------------------------------
 
-    let (mut video_audio_streams, subtitle_streams) = match playlist_type {
+    // Languages from --audio-lang and --subs-lang are also used for sorting streams.
+    let prefer_audio_langs = if audio_lang.is_empty() {
+        prefer_audio_lang.into_iter().collect::<Vec<_>>()
+    } else {
+        audio_lang.clone()
+    };
+    let prefer_subs_langs = if subs_lang.is_empty() {
+        prefer_subs_lang.into_iter().collect::<Vec<_>>()
+    } else {
+        subs_lang.clone()
+    };
+
+    // All streams are kept while listing them.
+    let select_streams = |master: MasterPlaylist| {
+        // Limit of --max-size is applied as a bitrate limit over whole duration.
+        let max_bitrate = match (max_size, master.duration) {
+            (Some(size), Some(duration)) if duration > 0.0 => {
+                let bitrate = (size as f32 * 8.0 / duration) as u64;
+                Some(max_bitrate.map_or(bitrate, |x| x.min(bitrate)))
+            }
+            (Some(_), _) => {
+                println!(
+                    "    {} duration of playlist is unknown, --max-size is ignored",
+                    "Warning".colorize("bold yellow")
+                );
+                max_bitrate
+            }
+            _ => max_bitrate,
+        };
+        let master = master.sort_streams(
+            &audio_channels,
+            &prefer_audio_codec,
+            &prefer_audio_langs,
+            &prefer_codec,
+            &prefer_subs_langs,
+            &video_range,
+        );
+
+        if list_streams {
+            Ok(master.all_streams())
+        } else {
+            master.select_streams(
+                quality,
+                quality_fallback,
+                max_bitrate,
+                min_bitrate,
+                &audio_lang,
+                &subs_lang,
+                &exclude_roles,
+                require_langs,
+                select_audio.as_ref(),
+                &select_id,
+                select_subs.as_ref(),
+                select_video.as_ref(),
+                skip_prompts,
+                raw_prompts,
+            )
+        }
+    };
+
+    let (mut video_audio_streams, subtitle_streams, thumbnail_streams) = match playlist_type {
         Some(PlaylistType::Dash) => {
             let mpd = dash_mpd::parse(&playlist).map_err(|x| {
                 anyhow!(
@@ -215,75 +442,236 @@ pub(crate) fn download(
                     playlist
                 )
             })?;
-            let (mut video_audio_streams, mut subtitle_streams) =
-                crate::dash::parse_as_master(&mpd, playlist_url.as_str())
-                    .sort_streams(prefer_audio_lang, prefer_subs_lang)
-                    .select_streams(quality, skip_prompts, raw_prompts)?;
+            let mut protection = crate::dash::MpdProtection::parse(&playlist);
+            let (mut video_audio_streams, mut subtitle_streams, mut thumbnail_streams) =
+                select_streams(crate::dash::parse_as_master(&mpd, playlist_url.as_str()))?;
+
+            // MPD > Location is used for further requests of manifest and resolving base urls.
+            let mut mpd_url = crate::dash::location(&playlist, &playlist_url)
+                .unwrap_or_else(|| playlist_url.clone());
+            let mut mpd = mpd;
+            let mut now = chrono::Utc::now();
+
+            if mpd.mpdtype.as_deref() == Some("dynamic") && !list_streams {
+                // Live window may have moved while selecting streams.
+                println!(
+                    " {} dynamic playlist {}",
+                    "Refreshing".colorize("bold cyan"),
+                    mpd_url
+                );
+                let (xml, url) = crate::dash::reload(&client, &playlist, &mpd, &playlist_url)?;
+                mpd = dash_mpd::parse(&xml).map_err(|x| {
+                    anyhow!(
+                        "couldn't parse response as dash playlist (failed with {}).\n\n{}",
+                        x,
+                        xml
+                    )
+                })?;
+                protection = crate::dash::MpdProtection::parse(&xml);
+                mpd_url = crate::dash::location(&xml, &url).unwrap_or(url);
+                now += crate::dash::clock_skew(&client, &mpd, &mpd_url)
+                    .unwrap_or_else(chrono::Duration::zero);
+
+                for stream in video_audio_streams
+                    .iter_mut()
+                    .chain(subtitle_streams.iter_mut())
+                    .chain(thumbnail_streams.iter_mut())
+                {
+                    crate::dash::relocate(&mpd, stream)?;
+                }
+            }
 
             for stream in video_audio_streams
                 .iter_mut()
                 .chain(subtitle_streams.iter_mut())
+                .chain(thumbnail_streams.iter_mut())
             {
                 crate::dash::push_segments(
                     &mpd,
+                    &protection,
                     stream,
-                    base_url.as_ref().unwrap_or(&playlist_url).as_str(),
+                    base_url.as_ref().unwrap_or(&mpd_url).as_str(),
+                    now,
                 )?;
-                stream.uri = playlist_url.as_str().to_owned();
+                stream.uri = mpd_url.as_str().to_owned();
             }
 
-            (video_audio_streams, subtitle_streams)
+            (video_audio_streams, subtitle_streams, thumbnail_streams)
         }
-        Some(PlaylistType::Hls) => match m3u8_rs::parse_playlist_res(playlist.as_bytes()) {
-            Ok(m3u8_rs::Playlist::MasterPlaylist(m3u8)) => {
-                let (mut video_audio_streams, mut subtitle_streams) =
-                    crate::hls::parse_as_master(&m3u8, playlist_url.as_str())
-                        .sort_streams(prefer_audio_lang, prefer_subs_lang)
-                        .select_streams(quality, skip_prompts, raw_prompts)?;
+        Some(PlaylistType::Hls) => {
+            let (playlist, variables) =
+                crate::hls::substitute_variables(&playlist, &playlist_url, &HashMap::new())?;
+
+            match m3u8_rs::parse_playlist_res(playlist.as_bytes()) {
+                Ok(m3u8_rs::Playlist::MasterPlaylist(m3u8)) => {
+                    let session_key = crate::hls::parse_session_key(&playlist);
+                    let mut master = crate::hls::parse_as_master(&m3u8, playlist_url.as_str());
+
+                    // Duration of HLS playlists is only known from media playlists.
+                    if let Some(stream) = master
+                        .streams
+                        .iter()
+                        .find(|x| x.media_type == MediaType::Video)
+                        .filter(|_| max_size.is_some())
+                    {
+                        let response = client
+                            .get(base_url.as_ref().unwrap_or(&playlist_url).join(&stream.uri)?)
+                            .send()?;
+                        let media_url = response.url().to_owned();
+                        let (text, _) = crate::hls::substitute_variables(
+                            &response.text()?,
+                            &media_url,
+                            &variables,
+                        )?;
+                        master.duration = m3u8_rs::parse_media_playlist_res(text.as_bytes())
+                            .ok()
+                            .map(|x| x.segments.iter().map(|y| y.duration).sum());
+                    }
 
-                for stream in video_audio_streams
-                    .iter_mut()
-                    .chain(subtitle_streams.iter_mut())
-                {
-                    stream.uri = base_url
-                        .as_ref()
-                        .unwrap_or(&playlist_url)
-                        .join(&stream.uri)?
-                        .to_string();
-                    let response = client.get(&stream.uri).send()?;
-                    let text = response.text()?;
-                    let media_playlist = m3u8_rs::parse_media_playlist_res(text.as_bytes())
-                        .map_err(|x| {
-                            anyhow!(
-                                "couldn't parse response as hls playlist (failed with {}).\n\n{}\n\n{}",
-                                x,
-                                stream.uri,
-                                text
-                            )
-                        })?;
-                    crate::hls::push_segments(&media_playlist, stream);
+                    let (mut video_audio_streams, mut subtitle_streams, mut thumbnail_streams) =
+                        select_streams(master)?;
+
+                    for stream in video_audio_streams
+                        .iter_mut()
+                        .chain(subtitle_streams.iter_mut())
+                        .chain(thumbnail_streams.iter_mut())
+                    {
+                        stream.uri = base_url
+                            .as_ref()
+                            .unwrap_or(&playlist_url)
+                            .join(&stream.uri)?
+                            .to_string();
+                        let response = client.get(&stream.uri).send()?;
+                        let media_url = response.url().to_owned();
+                        let (text, _) = crate::hls::substitute_variables(
+                            &response.text()?,
+                            &media_url,
+                            &variables,
+                        )?;
+                        let media_playlist = m3u8_rs::parse_media_playlist_res(text.as_bytes())
+                            .map_err(|x| {
+                                anyhow!(
+                                    "couldn't parse response as hls playlist (failed with {}).\n\n{}\n\n{}",
+                                    x,
+                                    stream.uri,
+                                    text
+                                )
+                            })?;
+                        crate::hls::push_segments(
+                            &media_playlist,
+                            &text,
+                            stream,
+                            session_key.as_ref(),
+                        )?;
+                    }
+
+                    (video_audio_streams, subtitle_streams, thumbnail_streams)
                 }
+                Ok(m3u8_rs::Playlist::MediaPlaylist(m3u8)) => {
+                    let mut media_playlist = crate::playlist::MediaPlaylist {
+                        id: file_name(&playlist_url),
+                        uri: playlist_url.to_string(),
+                        ..Default::default()
+                    };
+                    crate::hls::push_segments(&m3u8, &playlist, &mut media_playlist, None)?;
 
-                (video_audio_streams, subtitle_streams)
+                    if media_playlist.media_type == MediaType::Thumbnails {
+                        (vec![], vec![], vec![media_playlist])
+                    } else {
+                        (vec![media_playlist], vec![], vec![])
+                    }
+                }
+                Err(x) => bail!(
+                    "couldn't parse response as hls playlist (failed with {}).\n\n{}\n\n{}",
+                    x,
+                    playlist_url,
+                    playlist
+                ),
+            }
+        }
+        Some(PlaylistType::Smooth) => {
+            let manifest = crate::smooth::SmoothStreamingMedia::parse(&playlist)?;
+            let (mut video_audio_streams, mut subtitle_streams, mut thumbnail_streams) =
+                select_streams(crate::smooth::parse_as_master(&manifest, playlist_url.as_str()))?;
+
+            for stream in video_audio_streams
+                .iter_mut()
+                .chain(subtitle_streams.iter_mut())
+                .chain(thumbnail_streams.iter_mut())
+            {
+                crate::smooth::push_segments(
+                    &manifest,
+                    stream,
+                    base_url.as_ref().unwrap_or(&playlist_url).as_str(),
+                )?;
+                stream.uri = playlist_url.as_str().to_owned();
+            }
+
+            (video_audio_streams, subtitle_streams, thumbnail_streams)
+        }
+        None if progressive.is_some() => {
+            let (media_type, extension) = progressive.clone().unwrap();
+
+            if !list_streams {
+                println!(
+                    "   {} progressive {} media ({})",
+                    "Detected".colorize("bold cyan"),
+                    media_type,
+                    extension
+                );
             }
-            Ok(m3u8_rs::Playlist::MediaPlaylist(m3u8)) => {
-                let mut media_playlist = crate::playlist::MediaPlaylist {
+
+            let stream = MediaPlaylist {
+                extension: Some(extension),
+                id: file_name(&playlist_url),
+                media_type,
+                segments: vec![Segment {
                     uri: playlist_url.to_string(),
                     ..Default::default()
-                };
-                crate::hls::push_segments(&m3u8, &mut media_playlist);
-                (vec![media_playlist], vec![])
-            }
-            Err(x) => bail!(
-                "couldn't parse response as hls playlist (failed with {}).\n\n{}\n\n{}",
-                x,
-                playlist_url,
-                playlist
-            ),
-        },
-        _ => bail!("couldn't determine playlist type, only DASH and HLS playlists are supported."),
+                }],
+                uri: playlist_url.to_string(),
+                ..Default::default()
+            };
+
+            (vec![stream], vec![], vec![])
+        }
+        _ => bail!("couldn't determine playlist type, only DASH, HLS and Smooth Streaming playlists are supported."),
     };
 
+    if list_streams {
+        return crate::list::print_streams(
+            video_audio_streams
+                .iter()
+                .chain(subtitle_streams.iter())
+                .chain(thumbnail_streams.iter()),
+            json,
+        );
+    }
+
+    if max_bitrate.is_some() || max_size.is_some() || min_bitrate.is_some() {
+        let bitrate = crate::playlist::combined_bandwidth(
+            video_audio_streams
+                .iter()
+                .find(|x| x.media_type == MediaType::Video),
+            &video_audio_streams
+                .iter()
+                .filter(|x| x.media_type == MediaType::Audio)
+                .collect::<Vec<_>>(),
+        );
+        let duration = video_audio_streams
+            .iter()
+            .map(|x| x.segments.iter().map(|y| y.duration).sum::<f32>())
+            .fold(0.0, f32::max);
+
+        println!(
+            "  {} output size {} ({} kbit/s for {})",
+            "Projected".colorize("bold cyan"),
+            utils::format_bytes((bitrate as f32 * duration / 8.0) as usize, 2).2,
+            bitrate / 1000,
+            kdam::format::interval(duration as usize, false)
+        );
+    }
+
     // -----------------------------------------------------------------------------------------
     // Parse Key Ids
     // -----------------------------------------------------------------------------------------
@@ -320,32 +708,53 @@ pub(crate) fn download(
             .unwrap_or(stream.uri.parse::<Url>().unwrap());
 
         if let Some(segment) = stream.segments.get(0) {
+            let mut pssh = segment
+                .key
+                .as_ref()
+                .and_then(|x| x.pssh.clone())
+                .unwrap_or_default();
+
             if let Some(map) = &segment.map {
                 let url = stream_base_url.join(&map.uri)?;
-                let mut request = client.get(url);
 
-                if let Some(range) = &map.range {
-                    request = request.header(header::RANGE, range.as_header_value());
+                match fetch(&client, &stream_base_url, &map.uri, map.range.as_ref()) {
+                    Ok(bytes) => pssh.extend(Pssh::new(&bytes).map_err(|x| anyhow!(x))?),
+                    Err(_) if !pssh.system_ids.is_empty() => println!(
+                        "    {} couldn't fetch {} to parse key ids, using manifest key ids only",
+                        "Warning".colorize("bold yellow"),
+                        url
+                    ),
+                    Err(e) => return Err(e),
                 }
+            }
 
-                let response = request.send()?;
-                let pssh = Pssh::new(&response.bytes()?).map_err(|x| anyhow!(x))?;
-
-                for key_id in pssh.key_ids {
-                    if !kids.contains(&key_id.value) {
-                        kids.insert(key_id.value.clone());
-                        println!(
-                            "      {} {} {} ({})",
-                            "KeyId".colorize("bold green"),
-                            if default_kids.contains(&key_id.value) {
-                                "*"
-                            } else {
-                                " "
-                            },
-                            key_id.uuid(),
-                            key_id.system_type,
-                        );
-                    }
+            if !pssh.system_ids.is_empty() {
+                println!(
+                    "   {} {} ({} stream)",
+                    "SystemId".colorize("bold green"),
+                    pssh.system_ids
+                        .iter()
+                        .map(|x| KeyIdSystemType::from_system_id(x).to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    stream.media_type,
+                );
+            }
+
+            for key_id in pssh.key_ids {
+                if !kids.contains(&key_id.value) {
+                    kids.insert(key_id.value.clone());
+                    println!(
+                        "      {} {} {} ({})",
+                        "KeyId".colorize("bold green"),
+                        if default_kids.contains(&key_id.value) {
+                            "*"
+                        } else {
+                            " "
+                        },
+                        key_id.uuid(),
+                        key_id.system_type,
+                    );
                 }
             }
         }
@@ -413,13 +822,10 @@ pub(crate) fn download(
             );
         }
 
-        if video_streams_count == 0
-            && (audio_streams_count > 1
-                || subtitle_streams.len() > 1
-                || (audio_streams_count != 0 && !subtitle_streams.is_empty()))
-        {
+        // Audio streams are muxed together (along with subtitles) even without a video stream.
+        if video_streams_count == 0 && audio_streams_count == 0 && subtitle_streams.len() > 1 {
             println!(
-                "    {} --output is ignored when no video streams is selected but multiple audio/subtitle streams are selected",
+                "    {} --output is ignored when only multiple subtitle streams are selected",
                 "Warning".colorize("bold yellow")
             );
         }
@@ -446,6 +852,20 @@ pub(crate) fn download(
         );
     }
 
+    // Images of i-frame thumbnail streams are extracted using ffmpeg.
+    if thumbnail_streams.iter().any(|x| x.i_frame)
+        && Command::new("ffmpeg")
+            .arg("-version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_err()
+    {
+        bail!(
+            "could'nt locate ffmpeg binary in PATH (https://www.ffmpeg.org/download.html), it is required for extracting images from i-frame thumbnail streams."
+        );
+    }
+
     let mut temp_files = vec![];
     let one_stream = (video_audio_streams.len() == 1) && subtitle_streams.is_empty();
 
@@ -504,27 +924,20 @@ pub(crate) fn download(
 
         for segment in &stream.segments {
             if let Some(map) = &segment.map {
-                let url = stream_base_url.join(&map.uri)?;
-                let mut request = client.get(url);
-
-                if let Some(range) = &map.range {
-                    request = request.header(header::RANGE, range.as_header_value());
-                }
-
-                let response = request.send()?;
-                let bytes = response.bytes()?;
-                subtitles_data.extend_from_slice(&bytes);
+                subtitles_data.extend_from_slice(&fetch(
+                    &client,
+                    &stream_base_url,
+                    &map.uri,
+                    map.range.as_ref(),
+                )?);
             }
 
-            let url = stream_base_url.join(&segment.uri)?;
-            let mut request = client.get(url);
-
-            if let Some(range) = &segment.range {
-                request = request.header(header::RANGE, range.as_header_value());
-            }
-
-            let response = request.send()?;
-            let bytes = response.bytes()?;
+            let bytes = fetch(
+                &client,
+                &stream_base_url,
+                &segment.uri,
+                segment.range.as_ref(),
+            )?;
             subtitles_data.extend_from_slice(&bytes);
 
             if first_run {
@@ -549,7 +962,9 @@ pub(crate) fn download(
                     .to_string_lossy()
                     .to_string();
                 temp_files.push(Stream {
+                    dispositions: stream.dispositions(),
                     file_path: temp_file.clone(),
+                    label: stream.label.clone(),
                     language: stream.language.clone(),
                     media_type: stream.media_type.clone(),
                 });
@@ -619,6 +1034,212 @@ pub(crate) fn download(
         pb.reset(Some(0));
     }
 
+    // -----------------------------------------------------------------------------------------
+    // Download Thumbnail Streams
+    // -----------------------------------------------------------------------------------------
+
+    for stream in thumbnail_streams {
+        pb.write(format!(
+            " {} {} stream {}",
+            "Processing".colorize("bold green"),
+            stream.media_type,
+            stream.display_stream().colorize("cyan"),
+        ))?;
+
+        let length = stream.segments.len();
+
+        if length == 0 {
+            pb.write(format!(
+                "    {} skipping stream (no segments)",
+                "Warning".colorize("bold yellow"),
+            ))?;
+            continue;
+        }
+
+        pb.pb.total = length;
+
+        // Images are stored inside a directory alongside a webvtt track referencing them.
+        let vtt_file = stream.file_path(&directory, "vtt");
+        let images_directory = vtt_file.with_extension("");
+        let images_directory_name = images_directory
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        std::fs::create_dir_all(&images_directory)?;
+
+        pb.write(format!(
+            "{} stream to {}",
+            "Downloading".colorize("bold green"),
+            images_directory.to_string_lossy().colorize("cyan")
+        ))?;
+
+        let ext = stream.extension();
+        let width = length.to_string().len();
+        let mut downloaded_bytes = 0;
+        let mut previous_map = None;
+        let mut time = 0.0;
+        let mut vtt = "WEBVTT\n\n".to_owned();
+
+        let stream_base_url = base_url
+            .clone()
+            .unwrap_or(stream.uri.parse::<Url>().unwrap());
+
+        let mut previous_key = None;
+        let mut fragments_info = None;
+
+        for (i, segment) in stream.segments.iter().enumerate() {
+            if let Some(map) = &segment.map {
+                previous_map = Some(fetch_with_retries(
+                    &client,
+                    &stream_base_url,
+                    &map.uri,
+                    map.range.as_ref(),
+                    retry_count,
+                    |x| Ok(pb.write(x)?),
+                )?);
+                fragments_info = previous_map.clone();
+            }
+
+            if !no_decrypt {
+                if let Some(key) = &segment.key {
+                    previous_key = segment_keys(
+                        &client,
+                        &stream_base_url,
+                        &stream,
+                        key,
+                        &keys,
+                        all_keys,
+                        |x| Ok(pb.write(x)?),
+                    )?;
+                }
+            }
+
+            let cenc = matches!(
+                &previous_key,
+                Some(Keys {
+                    method: KeyMethod::Cenc,
+                    ..
+                })
+            );
+
+            let data = fetch_with_retries(
+                &client,
+                &stream_base_url,
+                &segment.uri,
+                segment.range.as_ref(),
+                retry_count,
+                |x| Ok(pb.write(x)?),
+            )?;
+            downloaded_bytes += data.len();
+
+            let bytes = decrypt_segment(
+                data,
+                previous_map.clone(),
+                previous_key.as_ref(),
+                if cenc { fragments_info.clone() } else { None },
+            )?;
+
+            let image = if stream.i_frame {
+                // I-frame segments are ts / fmp4 chunks, their first frame is extracted as an image.
+                let image = format!("{:0width$}.jpg", i, width = width);
+                let segment_file =
+                    images_directory.join(format!("{:0width$}.{}", i, ext, width = width));
+                File::create(&segment_file)?.write_all(&bytes)?;
+
+                let code = Command::new("ffmpeg")
+                    .arg("-i")
+                    .arg(&segment_file)
+                    .args(["-frames:v", "1", "-y"])
+                    .arg(images_directory.join(&image))
+                    .stderr(Stdio::null())
+                    .spawn()?
+                    .wait()?;
+                std::fs::remove_file(&segment_file)?;
+
+                if !code.success() {
+                    bail!(
+                        "ffmpeg exited with code {} while extracting image from {}",
+                        code.code().unwrap_or(1),
+                        segment.uri
+                    )
+                }
+
+                image
+            } else {
+                let image = format!("{:0width$}.{}", i, ext, width = width);
+                File::create(images_directory.join(&image))?.write_all(&bytes)?;
+                image
+            };
+
+            let image = format!("{}/{}", images_directory_name, image);
+
+            match &stream.tiles {
+                Some(tiles) => {
+                    let count = tiles.columns * tiles.rows;
+                    let duration = tiles.duration.unwrap_or(segment.duration / count as f32);
+
+                    for j in 0..count {
+                        let start = time + (j as f32 * duration);
+
+                        if j != 0 && start >= time + segment.duration {
+                            break;
+                        }
+
+                        let fragment = if let Some((w, h)) = tiles.resolution {
+                            format!(
+                                "#xywh={},{},{},{}",
+                                (j % tiles.columns) * w,
+                                (j / tiles.columns) * h,
+                                w,
+                                h
+                            )
+                        } else {
+                            String::new()
+                        };
+
+                        vtt += &format!(
+                            "{} --> {}\n{}{}\n\n",
+                            utils::format_vtt_timestamp(start),
+                            utils::format_vtt_timestamp(start + duration),
+                            image,
+                            fragment
+                        );
+                    }
+                }
+                None => {
+                    vtt += &format!(
+                        "{} --> {}\n{}\n\n",
+                        utils::format_vtt_timestamp(time),
+                        utils::format_vtt_timestamp(time + segment.duration),
+                        image
+                    );
+                }
+            }
+
+            time += segment.duration;
+
+            pb.replace(
+                0,
+                Column::Text(format!(
+                    "[bold blue]{}",
+                    utils::format_bytes(downloaded_bytes, 2).2
+                )),
+            );
+            pb.update(1)?;
+        }
+
+        File::create(&vtt_file)?.write_all(vtt.as_bytes())?;
+
+        pb.write(format!(
+            " {} stream successfully (track {})",
+            "Downloaded".colorize("bold green"),
+            vtt_file.to_string_lossy().colorize("cyan")
+        ))?;
+        eprintln!();
+        pb.reset(Some(0));
+    }
+
     // -----------------------------------------------------------------------------------------
     // Estimation
     // -----------------------------------------------------------------------------------------
@@ -636,29 +1257,60 @@ pub(crate) fn download(
         let mut ranges = None;
 
         if let Some(segment) = stream.segments.get(0) {
+            if segment.uri.starts_with("data:") {
+                let content_length = fetch(
+                    &client,
+                    &stream_base_url,
+                    &segment.uri,
+                    segment.range.as_ref(),
+                )?
+                .len();
+                relative_sizes.push_back(total_segments * content_length);
+                continue;
+            }
+
             let url = stream_base_url.join(&segment.uri)?;
             let mut request = client.head(url.clone());
 
             if total_segments == 1 {
-                let response = request.send()?;
-                let content_length = response
-                    .headers()
-                    .get(header::CONTENT_LENGTH)
-                    .map(|x| x.to_str().unwrap().parse::<usize>().unwrap())
-                    .unwrap_or(0);
-
-                if content_length == 0 {
-                    bail!(
-                        "cannot download a single segment ({}) of unknown content length.",
-                        url
-                    );
-                } else {
+                // Only download the sub-range of resource if segment is a byte range.
+                if let Some(range) = &segment.range {
                     ranges = Some(PartialRangeIter {
-                        start: 0,
-                        end: content_length as u64 - 1,
+                        start: range.start,
+                        end: range.end,
                         buffer_size,
                     });
-                    relative_sizes.push_back(content_length);
+                    relative_sizes.push_back((range.end - range.start + 1) as usize);
+                } else {
+                    let response = request.send()?;
+                    let content_length = response
+                        .headers()
+                        .get(header::CONTENT_LENGTH)
+                        .map(|x| x.to_str().unwrap().parse::<usize>().unwrap())
+                        .unwrap_or(0);
+
+                    let accept_ranges = response
+                        .headers()
+                        .get(header::ACCEPT_RANGES)
+                        .map(|x| x.as_bytes() == b"bytes")
+                        .unwrap_or(false);
+
+                    if content_length == 0 {
+                        bail!(
+                            "cannot download a single segment ({}) of unknown content length.",
+                            url
+                        );
+                    } else if progressive.is_some() && !accept_ranges {
+                        // Server doesn't support range requests, download whole resource at once.
+                        relative_sizes.push_back(content_length);
+                    } else {
+                        ranges = Some(PartialRangeIter {
+                            start: 0,
+                            end: content_length as u64 - 1,
+                            buffer_size,
+                        });
+                        relative_sizes.push_back(content_length);
+                    }
                 }
             } else {
                 if let Some(range) = &segment.range {
@@ -717,7 +1369,7 @@ pub(crate) fn download(
     let pool = threadpool::ThreadPool::new(threads as usize);
     let mut should_mux = !no_decrypt && !no_merge;
 
-    for stream in video_audio_streams {
+    for mut stream in video_audio_streams {
         pb.lock().unwrap().write(format!(
             " {} {} stream {}",
             "Processing".colorize("bold green"),
@@ -739,16 +1391,28 @@ pub(crate) fn download(
             .file_path(&directory, &stream.extension())
             .to_string_lossy()
             .to_string();
+        let mut part_file = stream
+            .part_file_path(&directory, &stream.extension())
+            .to_string_lossy()
+            .to_string();
 
         if let Some(output) = &output {
             if one_stream && output.ends_with(&format!(".{}", stream.extension())) {
                 temp_file = output.to_owned();
+                part_file = format!("{}.part", output);
+                should_mux = false;
+            } else if one_stream && Path::new(output).extension().is_none() {
+                // Keep container of stream when output doesn't have an extension.
+                temp_file = format!("{}.{}", output, stream.extension());
+                part_file = format!("{}.part", temp_file);
                 should_mux = false;
             }
         }
 
         temp_files.push(Stream {
+            dispositions: stream.dispositions(),
             file_path: temp_file.clone(),
+            label: stream.label.clone(),
             language: stream.language.clone(),
             media_type: stream.media_type.clone(),
         });
@@ -758,7 +1422,40 @@ pub(crate) fn download(
             temp_file.colorize("cyan"),
         ))?;
 
-        let merger = Arc::new(Mutex::new(if no_merge {
+        // Progressive media is downloaded to a part file which is resumed on next run.
+        let mut resumed_bytes = 0;
+
+        if progressive.is_some() && Path::new(&part_file).exists() {
+            resumed_bytes =
+                resume_segments(&mut stream.segments, std::fs::metadata(&part_file)?.len())
+                    as usize;
+
+            if resumed_bytes != 0 {
+                let mut pb = pb.lock().unwrap();
+                pb.write(format!(
+                    "   {} from {}",
+                    "Resuming".colorize("bold cyan"),
+                    utils::format_bytes(resumed_bytes, 2).2
+                ))?;
+                pb.update(length - stream.segments.len())?;
+            }
+        }
+
+        if progressive.is_some() && stream.segments.is_empty() {
+            std::fs::rename(&part_file, &temp_file)?;
+            downloaded_bytes += resumed_bytes;
+            pb.lock().unwrap().write(format!(
+                " {} stream successfully",
+                "Downloaded".colorize("bold green"),
+            ))?;
+            continue;
+        }
+
+        let merger = Arc::new(Mutex::new(if resumed_bytes != 0 {
+            Merger::resume(stream.segments.len(), &part_file)?
+        } else if progressive.is_some() {
+            Merger::new(stream.segments.len(), &part_file)?
+        } else if no_merge {
             Merger::with_directory(stream.segments.len(), &temp_file)?
         } else {
             Merger::new(stream.segments.len(), &temp_file)?
@@ -769,6 +1466,7 @@ pub(crate) fn download(
         let relative_size = relative_sizes.iter().sum();
         let mut previous_map = None;
         let mut previous_key = None;
+        let mut fragments_info = None;
 
         let stream_base_url = base_url
             .clone()
@@ -776,116 +1474,58 @@ pub(crate) fn download(
 
         for (i, segment) in stream.segments.iter().enumerate() {
             if let Some(map) = &segment.map {
-                let url = stream_base_url.join(&map.uri)?;
-                let mut request = client.get(url);
-
-                if let Some(range) = &map.range {
-                    request = request.header(header::RANGE, range.as_header_value());
-                }
-
-                let response = request.send()?;
-                let bytes = response.bytes()?;
-                previous_map = Some(bytes.to_vec())
+                previous_map = Some(fetch(
+                    &client,
+                    &stream_base_url,
+                    &map.uri,
+                    map.range.as_ref(),
+                )?);
+                fragments_info = previous_map.clone();
             }
 
             if !no_decrypt {
                 if let Some(key) = &segment.key {
-                    match key.method {
-                        KeyMethod::Aes128 => {
-                            if !keys.is_empty() {
-                                bail!("custom keys with AES-128 encryption is not supported");
-                            }
-
-                            if let Some(uri) = &key.uri {
-                                previous_key = Some(Keys {
-                                    bytes: if key.key_format.is_none() {
-                                        let url = stream_base_url.join(uri)?;
-                                        let request = client.get(url);
-                                        let response = request.send()?;
-                                        response.bytes()?.to_vec()
-                                    } else {
-                                        vec![]
-                                    },
-                                    iv: key.iv.clone(),
-                                    method: key.method.clone(),
-                                });
-                            } else {
-                                bail!("uri cannot be none when key method is AES-128");
-                            }
-                        }
-                        KeyMethod::Cenc => {
-                            let default_kid = stream.default_kid();
-                            let mut decryption_keys = HashMap::new();
-
-                            if all_keys {
-                                for key in &keys {
-                                    if let Some(kid) = &key.0 {
-                                        decryption_keys.insert(kid.to_owned(), key.1.to_owned());
-                                    } else if let Some(default_kid) = &default_kid {
-                                        decryption_keys
-                                            .insert(default_kid.to_owned(), key.1.to_owned());
-                                    }
-                                }
-                            } else {
-                                for key in &keys {
-                                    if let Some(default_kid) = &default_kid {
-                                        if let Some(kid) = &key.0 {
-                                            if default_kid == kid {
-                                                decryption_keys
-                                                    .insert(kid.to_owned(), key.1.to_owned());
-                                            }
-                                        } else {
-                                            decryption_keys
-                                                .insert(default_kid.to_owned(), key.1.to_owned());
-                                        }
-                                    }
-                                }
-                            }
-
-                            if decryption_keys.is_empty() {
-                                bail!(
-                                    "cannot determine keys to use, bypass this error using {} flag.",
-                                    "--all-keys".colorize("bold green")
-                                );
-                            }
-
-                            for key in &decryption_keys {
-                                pb.lock().unwrap().write(format!(
-                                    "        {} {}:{}",
-                                    "Key".colorize("bold green"),
-                                    key.0,
-                                    key.1
-                                ))?;
-                            }
-
-                            previous_key = Some(Keys::from_hex_keys(decryption_keys));
-                        }
-                        _ => previous_key = None,
-                    }
+                    previous_key = segment_keys(
+                        &client,
+                        &stream_base_url,
+                        &stream,
+                        key,
+                        &keys,
+                        all_keys,
+                        |x| Ok(pb.lock().unwrap().write(x)?),
+                    )?;
                 }
             }
 
-            let url = stream_base_url.join(&segment.uri)?;
-            let mut request = client.get(url);
-
-            if let Some(range) = &segment.range {
-                request = request.header(header::RANGE, range.as_header_value());
-            }
+            let cenc = matches!(
+                &previous_key,
+                Some(Keys {
+                    method: KeyMethod::Cenc,
+                    ..
+                })
+            );
 
             let thread_data = ThreadData {
-                downloaded_bytes,
+                base_url: stream_base_url.clone(),
+                client: client.clone(),
+                downloaded_bytes: downloaded_bytes + resumed_bytes,
+                fragments_info: if cenc { fragments_info.clone() } else { None },
                 index: i,
                 keys: previous_key.clone(),
                 map: previous_map.clone(),
                 merger: merger.clone(),
+                patch_track_id: matches!(stream.playlist_type, PlaylistType::Smooth),
                 pb: pb.clone(),
+                range: segment.range.clone(),
                 relative_size,
-                request,
                 timer: timer.clone(),
                 total_retries: retry_count,
+                uri: segment.uri.clone(),
             };
 
-            if previous_key.is_none() {
+            // Init segment is written only once, cenc fragments are decrypted using it as fragments info.
+            // Every segment file still starts with decrypted init segment when --no-merge is used.
+            if previous_key.is_none() || (cenc && !no_merge) {
                 previous_map = None;
             }
 
@@ -910,7 +1550,11 @@ pub(crate) fn download(
             );
         }
 
-        downloaded_bytes += merger.stored();
+        downloaded_bytes += merger.stored() + resumed_bytes;
+
+        if progressive.is_some() {
+            std::fs::rename(&part_file, &temp_file)?;
+        }
 
         pb.lock().unwrap().write(format!(
             " {} stream successfully",
@@ -924,9 +1568,32 @@ pub(crate) fn download(
     // Mux Downloaded Streams
     // -----------------------------------------------------------------------------------------
 
+    // Streams of undefined type are muxed as per tracks found in their downloaded data.
+    for temp_file in temp_files
+        .iter_mut()
+        .filter(|x| x.media_type == MediaType::Undefined)
+    {
+        temp_file.media_type = sniff_media_type(&temp_file.file_path);
+
+        if temp_file.media_type == MediaType::Undefined {
+            println!(
+                "    {} {} is a data stream, it won't be muxed",
+                "Warning".colorize("bold yellow"),
+                temp_file.file_path
+            );
+        } else {
+            println!(
+                "   {} {} stream in {}",
+                "Detected".colorize("bold green"),
+                temp_file.media_type,
+                temp_file.file_path
+            );
+        }
+    }
+
     let video_temp_files = temp_files
         .iter()
-        .filter(|x| (x.media_type == MediaType::Video) || (x.media_type == MediaType::Undefined))
+        .filter(|x| x.media_type == MediaType::Video)
         .collect::<Vec<_>>();
     let video_streams_count = video_temp_files.len();
     let audio_streams_count = temp_files
@@ -939,14 +1606,19 @@ pub(crate) fn download(
         .count();
 
     if should_mux
-        && (video_streams_count == 1 || audio_streams_count == 1 || subtitle_streams_count == 1)
+        && video_streams_count <= 1
+        && (video_streams_count == 1 || audio_streams_count >= 1 || subtitle_streams_count == 1)
     {
         if let Some(output) = &output {
+            let output = if Path::new(output).extension().is_none() {
+                format!("{}.mkv", output)
+            } else {
+                output.to_owned()
+            };
+            let output = &output;
             let all_temp_files = temp_files
                 .iter()
-                .filter(|x| {
-                    (x.media_type == MediaType::Video) || (x.media_type == MediaType::Undefined)
-                })
+                .filter(|x| x.media_type == MediaType::Video)
                 .chain(
                     temp_files
                         .iter()
@@ -965,15 +1637,11 @@ pub(crate) fn download(
                 args.extend_from_slice(&["-i".to_owned(), temp_file.file_path.clone()]);
             }
 
-            if (video_streams_count == 1)
-                || (audio_streams_count == 1)
-                || (subtitle_streams_count == 1)
-            {
-                // TODO - Re-consider this copy
-                args.extend_from_slice(&["-c".to_owned(), "copy".to_owned()]);
-            } else {
-                args.extend_from_slice(&["-c".to_owned(), "copy".to_owned()]);
+            args.extend_from_slice(&["-c".to_owned(), "copy".to_owned()]);
 
+            // Single input is copied as it is, streams are mapped explicitly (along with their
+            // metadata and dispositions) only while muxing multiple inputs.
+            if all_temp_files.len() > 1 {
                 if subtitle_streams_count > 0 && output.ends_with(".mp4") {
                     args.extend_from_slice(&["-c:s".to_owned(), "mov_text".to_owned()]);
                 }
@@ -986,33 +1654,44 @@ pub(crate) fn download(
                 let mut subtitle_index = 0;
 
                 for temp_file in &all_temp_files {
-                    match temp_file.media_type {
+                    let (specifier, index) = match temp_file.media_type {
                         MediaType::Audio => {
-                            if let Some(language) = &temp_file.language {
-                                args.extend_from_slice(&[
-                                    format!("-metadata:s:a:{}", audio_index),
-                                    format!("language={}", language),
-                                ]);
-                            }
-
                             audio_index += 1;
+                            ("a", audio_index - 1)
                         }
                         MediaType::Subtitles => {
-                            if let Some(language) = &temp_file.language {
-                                args.extend_from_slice(&[
-                                    format!("-metadata:s:s:{}", subtitle_index),
-                                    format!("language={}", language),
-                                ]);
-                            }
-
                             subtitle_index += 1;
+                            ("s", subtitle_index - 1)
                         }
-                        _ => (),
+                        _ => continue,
+                    };
+
+                    if let Some(language) = &temp_file.language {
+                        args.extend_from_slice(&[
+                            format!("-metadata:s:{}:{}", specifier, index),
+                            format!("language={}", language),
+                        ]);
+                    }
+
+                    if let Some(label) = &temp_file.label {
+                        args.extend_from_slice(&[
+                            format!("-metadata:s:{}:{}", specifier, index),
+                            format!("title={}", label),
+                        ]);
+                    }
+
+                    let mut dispositions = temp_file.dispositions.clone();
+
+                    if specifier == "s" && index == 0 {
+                        dispositions.insert(0, "default");
                     }
-                }
 
-                if subtitle_streams_count > 0 {
-                    args.extend_from_slice(&["-disposition:s:0".to_owned(), "default".to_owned()]);
+                    if !dispositions.is_empty() {
+                        args.extend_from_slice(&[
+                            format!("-disposition:{}:{}", specifier, index),
+                            dispositions.join("+"),
+                        ]);
+                    }
                 }
             }
 
@@ -1068,10 +1747,36 @@ pub(crate) fn download(
         }
     }
 
-    update::check_for_new_release(&client);
     Ok(())
 }
 
+/// Media type of an undefined stream, decided by sniffing the beginning of its downloaded data.
+/// Undefined is returned for data streams (i.e. timed metadata) and unknown formats.
+fn sniff_media_type(path: &str) -> MediaType {
+    let mut data = vec![];
+
+    if File::open(path)
+        .and_then(|x| x.take(1024 * 1024).read_to_end(&mut data))
+        .is_err()
+    {
+        return MediaType::Undefined;
+    }
+
+    // Mpeg-ts and webm streams usually contain muxed audio and video.
+    if (data.first() == Some(&0x47) && data.get(188).map(|x| *x == 0x47).unwrap_or(true))
+        || data.starts_with(&[0x1a, 0x45, 0xdf, 0xa3])
+    {
+        return MediaType::Video;
+    }
+
+    match vsd_mp4::track_types(&data) {
+        Ok(x) if x.contains(&TrackType::Video) => MediaType::Video,
+        Ok(x) if x.contains(&TrackType::Audio) => MediaType::Audio,
+        Ok(x) if x.contains(&TrackType::Subtitles) => MediaType::Subtitles,
+        _ => MediaType::Undefined,
+    }
+}
+
 enum SubtitleType {
     Mp4Vtt,
     Mp4Ttml,
@@ -1080,7 +1785,9 @@ enum SubtitleType {
     VttText,
 }
 struct Stream {
+    dispositions: Vec<&'static str>,
     file_path: String,
+    label: Option<String>,
     language: Option<String>,
     media_type: MediaType,
 }
@@ -1107,6 +1814,7 @@ impl Keys {
         }
     }
 
+    #[cfg(any(feature = "mp4decrypt", feature = "rust-decrypt"))]
     fn as_hex_keys(&self) -> HashMap<String, String> {
         String::from_utf8(self.bytes.clone())
             .unwrap()
@@ -1119,7 +1827,11 @@ impl Keys {
             .collect()
     }
 
-    fn decrypt(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+    #[cfg_attr(
+        not(any(feature = "mp4decrypt", feature = "rust-decrypt")),
+        allow(unused_variables)
+    )]
+    fn decrypt(&self, mut data: Vec<u8>, fragments_info: Option<Vec<u8>>) -> Result<Vec<u8>> {
         Ok(match self.method {
             KeyMethod::Aes128 => {
                 let iv = if let Some(iv) = &self.iv {
@@ -1130,14 +1842,48 @@ impl Keys {
 
                 utils::decrypt_aes_128_cbc(&mut data, &self.bytes, iv.as_ref())?
             }
+            // Pure rust decryption is preferred when both features are enabled.
+            #[cfg(feature = "rust-decrypt")]
             KeyMethod::Cenc => {
-                mp4decrypt::mp4decrypt(&data, self.as_hex_keys(), None).map_err(|x| anyhow!(x))?
+                vsd_mp4::decrypt::mp4decrypt(&data, self.as_hex_keys(), fragments_info)?
             }
+            #[cfg(all(feature = "mp4decrypt", not(feature = "rust-decrypt")))]
+            KeyMethod::Cenc => mp4decrypt::mp4decrypt(
+                &data,
+                &utils::mp4decrypt_keys(self.as_hex_keys())?,
+                fragments_info,
+            )?,
+            #[cfg(not(any(feature = "mp4decrypt", feature = "rust-decrypt")))]
+            KeyMethod::Cenc => bail!(
+                "vsd is compiled without cenc decryption support, enable mp4decrypt or rust-decrypt feature."
+            ),
             _ => data,
         })
     }
 }
 
+/// Skip byte ranges of progressive media segments which are already present in a `.part` file,
+/// returns number of resumed bytes. Nothing is resumed when segments don't have byte ranges
+/// (server doesn't accept range requests) or part file is larger than the resource.
+fn resume_segments(segments: &mut Vec<Segment>, part_size: u64) -> u64 {
+    let total_size = segments
+        .last()
+        .and_then(|x| x.range.as_ref())
+        .map(|x| x.end + 1);
+
+    if !total_size.map(|x| part_size <= x).unwrap_or(false) {
+        return 0;
+    }
+
+    segments.retain(|x| x.range.as_ref().map(|x| x.end >= part_size).unwrap_or(true));
+
+    if let Some(range) = segments.get_mut(0).and_then(|x| x.range.as_mut()) {
+        range.start = range.start.max(part_size);
+    }
+
+    part_size
+}
+
 // https://rust-lang-nursery.github.io/rust-cookbook/web/clients/download.html#make-a-partial-download-with-http-range-headers
 struct PartialRangeIter {
     start: u64,
@@ -1162,27 +1908,38 @@ impl Iterator for PartialRangeIter {
     }
 }
 struct ThreadData {
+    base_url: Url,
+    client: Client,
     downloaded_bytes: usize,
+    fragments_info: Option<Vec<u8>>,
     index: usize,
     keys: Option<Keys>,
     map: Option<Vec<u8>>,
     merger: Arc<Mutex<Merger>>,
+    patch_track_id: bool,
     pb: Arc<Mutex<RichProgress>>,
+    range: Option<Range>,
     relative_size: usize,
-    request: RequestBuilder,
     timer: Arc<Instant>,
     total_retries: u8,
+    uri: String,
 }
 
 impl ThreadData {
     fn execute(&self) -> Result<()> {
-        let mut segment = self.map.clone().unwrap_or(vec![]);
-        segment.append(&mut self.download_segment()?);
+        let mut data = self.download_segment()?;
 
-        if let Some(keys) = &self.keys {
-            segment = keys.decrypt(segment)?;
+        if self.patch_track_id {
+            crate::smooth::patch_track_id(&mut data);
         }
 
+        let segment = decrypt_segment(
+            data,
+            self.map.clone(),
+            self.keys.as_ref(),
+            self.fragments_info.clone(),
+        )?;
+
         let mut merger = self.merger.lock().unwrap();
         merger.write(self.index, &segment)?;
         merger.flush()?;
@@ -1192,42 +1949,28 @@ impl ThreadData {
     }
 
     fn download_segment(&self) -> Result<Vec<u8>> {
-        for _ in 0..self.total_retries {
-            let response = match self.request.try_clone().unwrap().send() {
-                Ok(response) => response,
-                Err(error) => {
-                    self.pb
-                        .lock()
-                        .unwrap()
-                        .write(check_reqwest_error(&error)?)?;
-                    continue;
-                }
-            };
-
-            let status = response.status();
-
-            if status.is_client_error() || status.is_server_error() {
-                bail!("failed to fetch segments");
-            }
-
-            let data = response.bytes()?.to_vec();
-            let elapsed_time = self.timer.elapsed().as_secs() as usize;
-
-            if elapsed_time != 0 {
-                let stored = self.merger.lock().unwrap().stored() + data.len();
-                self.pb.lock().unwrap().replace(
-                    12,
-                    Column::Text(format!(
-                        "[yellow]{}/s",
-                        utils::format_bytes(stored / elapsed_time, 2).2
-                    )),
-                );
-            }
-
-            return Ok(data);
+        let data = fetch_with_retries(
+            &self.client,
+            &self.base_url,
+            &self.uri,
+            self.range.as_ref(),
+            self.total_retries,
+            |x| Ok(self.pb.lock().unwrap().write(x)?),
+        )?;
+        let elapsed_time = self.timer.elapsed().as_secs() as usize;
+
+        if elapsed_time != 0 {
+            let stored = self.merger.lock().unwrap().stored() + data.len();
+            self.pb.lock().unwrap().replace(
+                12,
+                Column::Text(format!(
+                    "[yellow]{}/s",
+                    utils::format_bytes(stored / elapsed_time, 2).2
+                )),
+            );
         }
 
-        bail!("reached maximum number of retries to download a segment");
+        Ok(data)
     }
 
     fn notify(&self, stored: usize, estimate: usize) -> Result<()> {
@@ -1247,6 +1990,262 @@ impl ThreadData {
     }
 }
 
+/// Keys for decrypting segments following `key`, these are left as they are for `None`.
+#[allow(clippy::too_many_arguments)]
+fn segment_keys(
+    client: &Client,
+    base_url: &Url,
+    stream: &MediaPlaylist,
+    key: &Key,
+    keys: &[(Option<String>, String)],
+    all_keys: bool,
+    mut write: impl FnMut(String) -> Result<()>,
+) -> Result<Option<Keys>> {
+    match key.method {
+        KeyMethod::Aes128 => {
+            if !keys.is_empty() {
+                bail!("custom keys with AES-128 encryption is not supported");
+            }
+
+            if let Some(uri) = &key.uri {
+                Ok(Some(Keys {
+                    bytes: if key.key_format.is_none() {
+                        fetch(client, base_url, uri, None)?
+                    } else {
+                        vec![]
+                    },
+                    iv: key.iv.clone(),
+                    method: key.method.clone(),
+                }))
+            } else {
+                bail!("uri cannot be none when key method is AES-128");
+            }
+        }
+        KeyMethod::Cenc => {
+            let default_kid = stream.default_kid();
+            let mut decryption_keys = HashMap::new();
+
+            if all_keys {
+                for key in keys {
+                    if let Some(kid) = &key.0 {
+                        decryption_keys.insert(kid.to_owned(), key.1.to_owned());
+                    } else if let Some(default_kid) = &default_kid {
+                        decryption_keys.insert(default_kid.to_owned(), key.1.to_owned());
+                    }
+                }
+            } else {
+                for key in keys {
+                    if let Some(default_kid) = &default_kid {
+                        if let Some(kid) = &key.0 {
+                            if default_kid == kid {
+                                decryption_keys.insert(kid.to_owned(), key.1.to_owned());
+                            }
+                        } else {
+                            decryption_keys.insert(default_kid.to_owned(), key.1.to_owned());
+                        }
+                    }
+                }
+            }
+
+            if decryption_keys.is_empty() {
+                bail!(
+                    "cannot determine keys to use, bypass this error using {} flag.",
+                    "--all-keys".colorize("bold green")
+                );
+            }
+
+            for key in &decryption_keys {
+                write(format!(
+                    "        {} {}:{}",
+                    "Key".colorize("bold green"),
+                    key.0,
+                    key.1
+                ))?;
+            }
+
+            Ok(Some(Keys::from_hex_keys(decryption_keys)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Prepend init segment to data and decrypt it, cenc fragments are decrypted using `fragments_info`.
+fn decrypt_segment(
+    mut data: Vec<u8>,
+    map: Option<Vec<u8>>,
+    keys: Option<&Keys>,
+    fragments_info: Option<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    Ok(match (keys, &fragments_info) {
+        (Some(keys), Some(_)) => {
+            let mut segment = match map {
+                Some(map) => keys.decrypt(map, None)?,
+                None => vec![],
+            };
+            segment.append(&mut keys.decrypt(data, fragments_info)?);
+            segment
+        }
+        (keys, _) => {
+            let mut segment = map.unwrap_or_default();
+            segment.append(&mut data);
+
+            match keys {
+                Some(keys) => keys.decrypt(segment, None)?,
+                None => segment,
+            }
+        }
+    })
+}
+
+/// Fetch a segment, failed requests are retried `total_retries` times and reported using `write`.
+fn fetch_with_retries(
+    client: &Client,
+    base_url: &Url,
+    uri: &str,
+    range: Option<&Range>,
+    total_retries: u8,
+    mut write: impl FnMut(String) -> Result<()>,
+) -> Result<Vec<u8>> {
+    if uri.starts_with("data:") {
+        return fetch(client, base_url, uri, range);
+    }
+
+    let mut request = client.get(base_url.join(uri)?);
+
+    if let Some(range) = range {
+        request = request.header(header::RANGE, range.as_header_value());
+    }
+
+    for _ in 0..total_retries {
+        let response = match request.try_clone().unwrap().send() {
+            Ok(response) => response,
+            Err(error) => {
+                write(check_reqwest_error(&error)?)?;
+                continue;
+            }
+        };
+
+        let status = response.status();
+
+        if status.is_client_error() || status.is_server_error() {
+            bail!("failed to fetch segments");
+        }
+
+        return Ok(response.bytes()?.to_vec());
+    }
+
+    bail!("reached maximum number of retries to download a segment");
+}
+
+/// Fetch a resource relative to base url, RFC 2397 `data:` uris are decoded locally.
+fn fetch(client: &Client, base_url: &Url, uri: &str, range: Option<&Range>) -> Result<Vec<u8>> {
+    if uri.starts_with("data:") {
+        let data = utils::decode_data_uri(uri)?;
+
+        return if let Some(range) = range {
+            data.get((range.start as usize)..=(range.end as usize))
+                .map(|x| x.to_vec())
+                .ok_or_else(|| anyhow!("byte range is out of bounds of data uri."))
+        } else {
+            Ok(data)
+        };
+    }
+
+    let mut request = client.get(base_url.join(uri)?);
+
+    if let Some(range) = range {
+        request = request.header(header::RANGE, range.as_header_value());
+    }
+
+    Ok(request.send()?.error_for_status()?.bytes()?.to_vec())
+}
+
+/// Last path segment of url, used as id of lone streams.
+fn file_name(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut x| x.next_back())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// Detect plain audio / video files (progressive media) using content type and extension of url.
+fn progressive_media(url: &Url, headers: &header::HeaderMap) -> Option<(MediaType, String)> {
+    let extension = url
+        .path()
+        .rsplit('/')
+        .next()
+        .and_then(|x| x.rsplit_once('.'))
+        .map(|x| x.1.to_lowercase())
+        .filter(|x| {
+            matches!(
+                x.as_str(),
+                "aac"
+                    | "avi"
+                    | "flac"
+                    | "flv"
+                    | "m4a"
+                    | "m4v"
+                    | "mkv"
+                    | "mov"
+                    | "mp3"
+                    | "mp4"
+                    | "oga"
+                    | "ogg"
+                    | "ogv"
+                    | "opus"
+                    | "ts"
+                    | "wav"
+                    | "webm"
+            )
+        });
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.split(';').next())
+        .map(|x| x.trim().to_lowercase())
+        .unwrap_or_else(|| "application/octet-stream".to_owned());
+
+    if content_type.contains("mpegurl") || content_type.contains("dash") {
+        return None;
+    }
+
+    if let Some((r#type, subtype)) = content_type.split_once('/') {
+        let media_type = match r#type {
+            "audio" => MediaType::Audio,
+            "video" => MediaType::Video,
+            "application" if matches!(subtype, "octet-stream" | "binary") => {
+                match extension.as_deref() {
+                    Some("aac" | "flac" | "m4a" | "mp3" | "oga" | "ogg" | "opus" | "wav") => {
+                        MediaType::Audio
+                    }
+                    Some(_) => MediaType::Video,
+                    None => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        let extension = extension.unwrap_or_else(|| {
+            match subtype {
+                "mp2t" => "ts",
+                "mp4" if media_type == MediaType::Audio => "m4a",
+                "mpeg" if media_type == MediaType::Audio => "mp3",
+                "quicktime" => "mov",
+                "x-flv" => "flv",
+                "x-matroska" => "mkv",
+                "x-msvideo" => "avi",
+                "x-wav" => "wav",
+                x => x,
+            }
+            .to_owned()
+        });
+
+        Some((media_type, extension))
+    } else {
+        None
+    }
+}
+
 fn check_reqwest_error(error: &reqwest::Error) -> Result<String> {
     let request = "Request".colorize("bold yellow");
     let url = error.url().unwrap();
@@ -1273,3 +2272,61 @@ fn check_reqwest_error(error: &reqwest::Error) -> Result<String> {
         bail!("download failed {}", url)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progressive_segments(end: u64, buffer_size: u32) -> Vec<Segment> {
+        PartialRangeIter {
+            start: 0,
+            end,
+            buffer_size,
+        }
+        .map(|x| Segment {
+            range: Some(x),
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    fn ranges(segments: &[Segment]) -> Vec<(u64, u64)> {
+        segments
+            .iter()
+            .map(|x| x.range.as_ref().map(|x| (x.start, x.end)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn resume_partially_downloaded_segments() {
+        let mut segments = progressive_segments(249, 100);
+        assert_eq!(ranges(&segments), vec![(0, 99), (100, 199), (200, 249)]);
+        assert_eq!(resume_segments(&mut segments, 150), 150);
+        assert_eq!(ranges(&segments), vec![(150, 199), (200, 249)]);
+
+        let mut segments = progressive_segments(249, 100);
+        assert_eq!(resume_segments(&mut segments, 200), 200);
+        assert_eq!(ranges(&segments), vec![(200, 249)]);
+
+        let mut segments = progressive_segments(249, 100);
+        assert_eq!(resume_segments(&mut segments, 250), 250);
+        assert!(segments.is_empty());
+    }
+
+    #[test]
+    fn restart_download() {
+        // Part file is larger than the resource.
+        let mut segments = progressive_segments(249, 100);
+        assert_eq!(resume_segments(&mut segments, 300), 0);
+        assert_eq!(ranges(&segments), vec![(0, 99), (100, 199), (200, 249)]);
+
+        // Resource is downloaded at once when server doesn't accept range requests.
+        let mut segments = vec![Segment {
+            uri: "video.mp4".to_owned(),
+            ..Default::default()
+        }];
+        assert_eq!(resume_segments(&mut segments, 150), 0);
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].range.is_none());
+    }
+}
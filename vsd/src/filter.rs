@@ -0,0 +1,655 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://github.com/nilaoda/N_m3u8DL-RE#selection-options
+    2. https://github.com/yt-dlp/yt-dlp#filtering-formats
+
+*/
+
+use crate::playlist::MediaPlaylist;
+use std::str::FromStr;
+
+/// Stream selection filter i.e. an optional modifier followed by an optional expression.
+///
+/// ```text
+/// best | worst | all | first N
+/// height<=1080 && codecs~hvc1 && bw<8M
+/// all lang in (en, fr) && channels>=6
+/// ```
+#[derive(Clone, Debug)]
+pub(crate) struct StreamFilter {
+    expression: Option<Expression>,
+    modifier: Modifier,
+}
+
+#[derive(Clone, Debug)]
+enum Modifier {
+    All,
+    Best,
+    First(usize),
+    Worst,
+}
+
+#[derive(Clone, Debug)]
+enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Compare(Field, Operator, Value),
+    In(Field, Vec<Value>),
+    Not(Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Truthy(Field),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Bandwidth,
+    Channels,
+    Codecs,
+    Extension,
+    FrameRate,
+    Group,
+    Height,
+    Label,
+    Language,
+    Live,
+    Range,
+    Role,
+    Width,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Contains,
+    Eq,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+    Ne,
+    NotContains,
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    And,
+    Comma,
+    LeftParen,
+    Not,
+    Operator(Operator),
+    Or,
+    RightParen,
+    Word(String),
+}
+
+impl Field {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_lowercase().as_str() {
+            "bandwidth" | "bitrate" | "bw" => Self::Bandwidth,
+            "channels" | "ch" => Self::Channels,
+            "codec" | "codecs" => Self::Codecs,
+            "ext" | "extension" => Self::Extension,
+            "fps" | "frame_rate" | "framerate" => Self::FrameRate,
+            "group" | "group_id" => Self::Group,
+            "height" | "h" => Self::Height,
+            "label" | "name" => Self::Label,
+            "lang" | "language" => Self::Language,
+            "live" => Self::Live,
+            "range" | "video_range" => Self::Range,
+            "role" | "roles" => Self::Role,
+            "width" | "w" => Self::Width,
+            _ => return None,
+        })
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Self::Bandwidth | Self::Channels | Self::FrameRate | Self::Height | Self::Width
+        )
+    }
+
+    fn number(&self, stream: &MediaPlaylist) -> Option<f64> {
+        match self {
+            Self::Bandwidth => stream.bandwidth.map(|x| x as f64),
+            Self::Channels => stream.channels.map(|x| x as f64),
+            Self::FrameRate => stream.frame_rate.map(|x| x as f64),
+            Self::Height => stream.resolution.map(|(_, h)| h as f64),
+            Self::Width => stream.resolution.map(|(w, _)| w as f64),
+            _ => None,
+        }
+    }
+
+    fn texts(&self, stream: &MediaPlaylist) -> Vec<String> {
+        match self {
+            Self::Codecs => stream
+                .codecs
+                .as_ref()
+                .map(|x| vec![x.to_owned()])
+                .unwrap_or_default(),
+            Self::Extension => vec![stream.extension()],
+            Self::Group => stream.group_id.iter().cloned().collect(),
+            Self::Label => stream.label.iter().cloned().collect(),
+            Self::Language => stream.language.iter().cloned().collect(),
+            Self::Live => vec![stream.live.to_string()],
+            Self::Range => stream
+                .video_range
+                .map(|x| vec![x.to_string()])
+                .unwrap_or_default(),
+            Self::Role => stream
+                .roles
+                .iter()
+                .chain(stream.accessibility.iter())
+                .cloned()
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl Value {
+    fn parse(s: &str) -> Self {
+        let (number, multiplier) = match s.chars().last() {
+            Some('k' | 'K') => (&s[..(s.len() - 1)], 1_000.0),
+            Some('m' | 'M') => (&s[..(s.len() - 1)], 1_000_000.0),
+            Some('g' | 'G') => (&s[..(s.len() - 1)], 1_000_000_000.0),
+            _ => (s, 1.0),
+        };
+
+        match number.parse::<f64>() {
+            Ok(x) => Self::Number(x * multiplier),
+            Err(_) => Self::Text(s.to_owned()),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Self::Number(x) => x.to_string(),
+            Self::Text(x) => x.to_owned(),
+        }
+    }
+}
+
+impl Expression {
+    fn matches(&self, stream: &MediaPlaylist) -> bool {
+        match self {
+            Self::And(x, y) => x.matches(stream) && y.matches(stream),
+            Self::Compare(field, operator, value) => compare(field, operator, value, stream),
+            Self::In(field, values) => values
+                .iter()
+                .any(|x| compare(field, &Operator::Eq, x, stream)),
+            Self::Not(x) => !x.matches(stream),
+            Self::Or(x, y) => x.matches(stream) || y.matches(stream),
+            Self::Truthy(field) => {
+                if field.is_numeric() {
+                    field.number(stream).map(|x| x != 0.0).unwrap_or(false)
+                } else {
+                    field
+                        .texts(stream)
+                        .iter()
+                        .any(|x| !x.is_empty() && x != "false")
+                }
+            }
+        }
+    }
+}
+
+fn compare(field: &Field, operator: &Operator, value: &Value, stream: &MediaPlaylist) -> bool {
+    if let (true, Value::Number(value)) = (field.is_numeric(), value) {
+        let number = match field.number(stream) {
+            Some(x) => x,
+            None => return *operator == Operator::Ne,
+        };
+
+        return match operator {
+            Operator::Eq | Operator::Contains => number == *value,
+            Operator::Ge => number >= *value,
+            Operator::Gt => number > *value,
+            Operator::Le => number <= *value,
+            Operator::Lt => number < *value,
+            Operator::Ne | Operator::NotContains => number != *value,
+        };
+    }
+
+    let value = value.text().to_lowercase();
+    let texts = field
+        .texts(stream)
+        .into_iter()
+        .map(|x| x.to_lowercase())
+        .collect::<Vec<_>>();
+    let equals = |x: &String| {
+        // Language tags also match their primary subtag i.e. en matches en-US.
+        *x == value
+            || (*field == Field::Language && x.split(['-', '_']).next() == Some(value.as_str()))
+    };
+
+    match operator {
+        Operator::Contains => texts.iter().any(|x| x.contains(&value)),
+        Operator::Eq => texts.iter().any(equals),
+        Operator::Ne => !texts.iter().any(equals),
+        Operator::NotContains => !texts.iter().any(|x| x.contains(&value)),
+        _ => false,
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+
+    while let Some(x) = chars.next() {
+        let token = match x {
+            x if x.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '~' => Token::Operator(Operator::Contains),
+            '&' | '|' => {
+                if chars.peek() == Some(&x) {
+                    chars.next();
+                }
+
+                if x == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '!' | '=' | '<' | '>' => {
+                let next = chars.peek().copied();
+                let operator = match (x, next) {
+                    ('!', Some('=')) => Some(Operator::Ne),
+                    ('!', Some('~')) => Some(Operator::NotContains),
+                    ('=', Some('=')) => Some(Operator::Eq),
+                    ('<', Some('=')) => Some(Operator::Le),
+                    ('>', Some('=')) => Some(Operator::Ge),
+                    _ => None,
+                };
+
+                if let Some(operator) = operator {
+                    chars.next();
+                    Token::Operator(operator)
+                } else {
+                    match x {
+                        '!' => Token::Not,
+                        '=' => Token::Operator(Operator::Eq),
+                        '<' => Token::Operator(Operator::Lt),
+                        _ => Token::Operator(Operator::Gt),
+                    }
+                }
+            }
+            '"' | '\'' => {
+                let mut word = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some(y) if y == x => break,
+                        Some(y) => word.push(y),
+                        None => return Err(format!("unclosed {} quote in filter.", x)),
+                    }
+                }
+
+                Token::Word(word)
+            }
+            _ => {
+                let mut word = x.to_string();
+
+                while let Some(y) = chars.peek() {
+                    if y.is_whitespace() || "()!=<>~&|,\"'".contains(*y) {
+                        break;
+                    }
+
+                    word.push(*y);
+                    chars.next();
+                }
+
+                Token::Word(word)
+            }
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    position: usize,
+    tokens: Vec<Token>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(x) if x == token => Ok(()),
+            Some(x) => Err(format!("expected {:?} but found {:?} in filter.", token, x)),
+            None => Err(format!("expected {:?} at end of filter.", token)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expression, String> {
+        let mut expression = self.and()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut expression = self.unary()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.unary()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.unary()?))),
+            Some(Token::LeftParen) => {
+                let expression = self.or()?;
+                self.expect(Token::RightParen)?;
+                Ok(expression)
+            }
+            Some(Token::Word(x)) => self.comparison(&x),
+            Some(x) => Err(format!("unexpected {:?} in filter.", x)),
+            None => Err("unexpected end of filter.".to_owned()),
+        }
+    }
+
+    fn comparison(&mut self, field: &str) -> Result<Expression, String> {
+        let field = Field::parse(field).ok_or_else(|| {
+            format!(
+                "unknown field {:?} in filter. possible values: [bw, channels, codecs, ext, fps, \
+                group, height, label, lang, live, range, role, width]",
+                field
+            )
+        })?;
+
+        match self.peek().cloned() {
+            Some(Token::Operator(operator)) => {
+                self.next();
+                let value = match self.next() {
+                    Some(Token::Word(x)) => Value::parse(&x),
+                    _ => return Err(format!("missing value for {:?} in filter.", field)),
+                };
+
+                if field.is_numeric() {
+                    if let Value::Text(x) = &value {
+                        return Err(format!("{:?} is not a number in filter.", x));
+                    }
+                } else if matches!(
+                    operator,
+                    Operator::Ge | Operator::Gt | Operator::Le | Operator::Lt
+                ) {
+                    return Err(format!("{:?} cannot be compared numerically.", field));
+                }
+
+                Ok(Expression::Compare(field, operator, value))
+            }
+            Some(Token::Word(x)) if x.eq_ignore_ascii_case("in") => {
+                self.next();
+                self.expect(Token::LeftParen)?;
+                let mut values = vec![];
+
+                loop {
+                    match self.next() {
+                        Some(Token::Word(x)) => values.push(Value::parse(&x)),
+                        Some(Token::RightParen) if values.is_empty() => break,
+                        _ => return Err("expected a value inside in (...) list.".to_owned()),
+                    }
+
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::RightParen) => break,
+                        _ => return Err("expected , or ) inside in (...) list.".to_owned()),
+                    }
+                }
+
+                Ok(Expression::In(field, values))
+            }
+            _ => Ok(Expression::Truthy(field)),
+        }
+    }
+}
+
+impl FromStr for StreamFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenize(s)?;
+        let mut modifier = Modifier::Best;
+
+        if let Some(Token::Word(x)) = tokens.first() {
+            let mut consumed = 1;
+
+            modifier = match x.to_lowercase().as_str() {
+                "all" => Modifier::All,
+                "best" => Modifier::Best,
+                "worst" => Modifier::Worst,
+                "first" => {
+                    let n = match tokens.get(1) {
+                        Some(Token::Word(x)) => x.parse::<usize>().ok(),
+                        _ => None,
+                    }
+                    .ok_or("first modifier must be followed by number of streams.")?;
+                    consumed = 2;
+                    Modifier::First(n)
+                }
+                _ => {
+                    consumed = 0;
+                    Modifier::Best
+                }
+            };
+
+            tokens.drain(..consumed);
+        }
+
+        if tokens.is_empty() {
+            return Ok(Self {
+                expression: None,
+                modifier,
+            });
+        }
+
+        let mut parser = Parser {
+            position: 0,
+            tokens,
+        };
+        let expression = parser.or()?;
+
+        if let Some(x) = parser.peek() {
+            return Err(format!("unexpected {:?} in filter.", x));
+        }
+
+        Ok(Self {
+            expression: Some(expression),
+            modifier,
+        })
+    }
+}
+
+impl StreamFilter {
+    /// Indices of streams matching filter. Streams should be already sorted from best to worst.
+    pub(crate) fn select(&self, streams: &[MediaPlaylist]) -> Vec<usize> {
+        let mut matched = streams
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| {
+                self.expression
+                    .as_ref()
+                    .map(|y| y.matches(x))
+                    .unwrap_or(true)
+            })
+            .map(|(i, _)| i);
+
+        match self.modifier {
+            Modifier::All => matched.collect(),
+            Modifier::Best => matched.take(1).collect(),
+            Modifier::First(x) => matched.take(x).collect(),
+            Modifier::Worst => matched.next_back().into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playlist::MediaType;
+
+    fn video(height: u64, codecs: &str, bandwidth: u64) -> MediaPlaylist {
+        MediaPlaylist {
+            bandwidth: Some(bandwidth),
+            codecs: Some(codecs.to_owned()),
+            media_type: MediaType::Video,
+            resolution: Some((height * 16 / 9, height)),
+            ..Default::default()
+        }
+    }
+
+    fn audio(language: &str, channels: f32) -> MediaPlaylist {
+        MediaPlaylist {
+            channels: Some(channels),
+            language: Some(language.to_owned()),
+            media_type: MediaType::Audio,
+            ..Default::default()
+        }
+    }
+
+    fn video_streams() -> Vec<MediaPlaylist> {
+        vec![
+            video(2160, "hvc1.2.4.L153.B0", 16_000_000),
+            video(1080, "hvc1.2.4.L123.B0", 8_000_000),
+            video(1080, "avc1.640028", 6_000_000),
+            video(720, "avc1.64001f", 3_000_000),
+        ]
+    }
+
+    fn select(filter: &str, streams: &[MediaPlaylist]) -> Vec<usize> {
+        filter.parse::<StreamFilter>().unwrap().select(streams)
+    }
+
+    fn error(filter: &str) -> String {
+        filter.parse::<StreamFilter>().unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        let streams = video_streams();
+
+        // && binds tighter than ||.
+        assert_eq!(
+            select("all height=720 || height=1080 && codecs~hvc1", &streams),
+            vec![1, 3]
+        );
+        assert_eq!(
+            select("all (height=720 || height=1080) && codecs~hvc1", &streams),
+            vec![1]
+        );
+        assert_eq!(
+            select("all (height=720 || height=1080) && codecs~avc1", &streams),
+            vec![2, 3]
+        );
+        assert_eq!(select("all !codecs~hvc1", &streams), vec![2, 3]);
+        assert_eq!(select("all codecs!~hvc1", &streams), vec![2, 3]);
+        assert_eq!(
+            select("all !(height<1080 || codecs~avc1)", &streams),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn in_list() {
+        let streams = vec![audio("en-US", 6.0), audio("fr", 2.0), audio("de", 2.0)];
+
+        assert_eq!(select("all lang in (en, fr)", &streams), vec![0, 1]);
+        assert_eq!(select("all lang in ('de')", &streams), vec![2]);
+        assert_eq!(select("all ch in (6)", &streams), vec![0]);
+        assert_eq!(
+            select("all height in (720, 2160)", &video_streams()),
+            vec![0, 3]
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let streams = video_streams();
+
+        assert_eq!(select("all codecs~HVC1", &streams), vec![0, 1]);
+        assert_eq!(select("all codecs~\"640028\"", &streams), vec![2]);
+        assert_eq!(select("all codecs~av01", &streams), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn unit_suffixes() {
+        let streams = video_streams();
+
+        assert_eq!(select("all bw<8M", &streams), vec![2, 3]);
+        assert_eq!(select("all bw>=8m", &streams), vec![0, 1]);
+        assert_eq!(select("all bw>=6000k && bw<=8000K", &streams), vec![1, 2]);
+        assert_eq!(select("all bw>0.01G", &streams), vec![0]);
+    }
+
+    #[test]
+    fn modifiers() {
+        let streams = video_streams();
+
+        assert_eq!(select("", &streams), vec![0]);
+        assert_eq!(select("best", &streams), vec![0]);
+        assert_eq!(select("codecs~avc1", &streams), vec![2]);
+        assert_eq!(select("worst", &streams), vec![3]);
+        assert_eq!(select("worst height>=1080", &streams), vec![2]);
+        assert_eq!(select("all", &streams), vec![0, 1, 2, 3]);
+        assert_eq!(select("first 2", &streams), vec![0, 1]);
+        assert_eq!(select("FIRST 2 codecs~avc1", &streams), vec![2, 3]);
+        assert_eq!(select("first 10 height=1080", &streams), vec![1, 2]);
+    }
+
+    #[test]
+    fn malformed_filters() {
+        assert_eq!(error("height>=abc"), "\"abc\" is not a number in filter.");
+        assert_eq!(error("codecs>5"), "Codecs cannot be compared numerically.");
+        assert_eq!(error("height>="), "missing value for Height in filter.");
+        assert!(error("size>5").starts_with("unknown field \"size\" in filter."));
+        assert_eq!(
+            error("(height=1080"),
+            "expected RightParen at end of filter."
+        );
+        assert_eq!(error("height=1080)"), "unexpected RightParen in filter.");
+        assert_eq!(error("height=1080 &&"), "unexpected end of filter.");
+        assert_eq!(
+            error("lang in (en fr)"),
+            "expected , or ) inside in (...) list."
+        );
+        assert_eq!(
+            error("lang in (,)"),
+            "expected a value inside in (...) list."
+        );
+        assert_eq!(error("label='main"), "unclosed ' quote in filter.");
+        assert_eq!(
+            error("first best"),
+            "first modifier must be followed by number of streams."
+        );
+    }
+}
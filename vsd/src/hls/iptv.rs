@@ -0,0 +1,281 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://en.wikipedia.org/wiki/M3U#Extended_M3U
+    2. https://github.com/iptv-org/iptv/blob/master/PLAYLISTS.md
+
+*/
+
+use anyhow::{bail, Result};
+use kdam::term::Colorizer;
+use regex::Regex;
+use requestty::prompt::style::Stylize;
+use std::io::Write;
+
+/// Single channel of an IPTV style (multi-entry) m3u playlist.
+pub(crate) struct IptvEntry {
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) title: String,
+    pub(crate) uri: String,
+}
+
+impl IptvEntry {
+    pub(crate) fn display(&self) -> String {
+        if self.attributes.is_empty() {
+            self.title.to_owned()
+        } else {
+            format!(
+                "{} ({})",
+                self.title,
+                self.attributes
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    }
+
+    /// Comma separated terms, each one of them is either a case insensitive substring of
+    /// title / attribute values or an exact KEY=VALUE attribute match.
+    fn matches(&self, filter: &str) -> bool {
+        filter
+            .split(',')
+            .map(|x| x.trim().to_lowercase())
+            .any(|term| {
+                if term == "all" {
+                    true
+                } else if let Some((key, value)) = term.split_once('=') {
+                    self.attributes.iter().any(|(x, y)| {
+                        x.eq_ignore_ascii_case(key.trim()) && y.eq_ignore_ascii_case(value.trim())
+                    })
+                } else {
+                    self.title.to_lowercase().contains(&term)
+                        || self
+                            .attributes
+                            .iter()
+                            .any(|(_, x)| x.to_lowercase().contains(&term))
+                }
+            })
+    }
+
+    /// Title of entry which can be safely used as a file name.
+    pub(crate) fn file_stem(&self) -> String {
+        self.title
+            .chars()
+            .map(|x| match x {
+                '/' | '\\' | '?' | '%' | '*' | ':' | '|' | '"' | '<' | '>' | '.' => '_',
+                _ => x,
+            })
+            .collect::<String>()
+            .trim()
+            .to_owned()
+    }
+}
+
+/// Parse IPTV style m3u playlists i.e. channel lists having `#EXTINF:-1 tvg-id="..." group-title="...",Channel Name`
+/// entries. `None` is returned for regular hls playlists.
+pub(crate) fn parse_iptv_playlist(text: &str) -> Option<Vec<IptvEntry>> {
+    if !text.trim_start().starts_with("#EXTM3U")
+        || text.contains("#EXT-X-TARGETDURATION")
+        || text.contains("#EXT-X-STREAM-INF")
+    {
+        return None;
+    }
+
+    let re = Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap();
+    let mut entries = vec![];
+    let mut iptv = false;
+    let mut extinf = None;
+    let mut group = None;
+
+    for line in text.lines().map(|x| x.trim()) {
+        if let Some(value) = line.strip_prefix("#EXTINF:") {
+            // Title is after first comma which is not inside attribute value.
+            let mut quoted = false;
+            let position = value.char_indices().find_map(|(i, x)| {
+                match x {
+                    '"' => quoted = !quoted,
+                    ',' if !quoted => return Some(i),
+                    _ => (),
+                }
+                None
+            });
+            let (head, title) = match position {
+                Some(i) => (&value[..i], value[(i + 1)..].trim()),
+                None => (value, ""),
+            };
+
+            let duration = head
+                .split_whitespace()
+                .next()
+                .and_then(|x| x.parse::<f32>().ok())
+                .unwrap_or(-1.0);
+            let attributes = re
+                .captures_iter(head)
+                .map(|x| (x[1].to_owned(), x[2].to_owned()))
+                .collect::<Vec<_>>();
+
+            if duration <= 0.0 || !attributes.is_empty() {
+                iptv = true;
+            }
+
+            extinf = Some((title.to_owned(), attributes));
+        } else if let Some(value) = line.strip_prefix("#EXTGRP:") {
+            group = Some(value.trim().to_owned());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            let (title, mut attributes) = extinf.take().unwrap_or_default();
+
+            if let Some(group) = group.take() {
+                if !attributes.iter().any(|(x, _)| x == "group-title") {
+                    attributes.push(("group-title".to_owned(), group));
+                }
+            }
+
+            entries.push(IptvEntry {
+                attributes,
+                title: if title.is_empty() {
+                    line.split('?')
+                        .next()
+                        .unwrap()
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or("undefined")
+                        .to_owned()
+                } else {
+                    title
+                },
+                uri: line.to_owned(),
+            });
+        }
+    }
+
+    if iptv && !entries.is_empty() {
+        Some(entries)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn select_entries(
+    entries: Vec<IptvEntry>,
+    filter: Option<&str>,
+    skip_prompts: bool,
+    raw_prompts: bool,
+) -> Result<Vec<IptvEntry>> {
+    if let Some(filter) = filter {
+        let selected_entries = entries
+            .into_iter()
+            .filter(|x| x.matches(filter))
+            .collect::<Vec<_>>();
+
+        for entry in &selected_entries {
+            println!(
+                "   {} {}",
+                "Selected".colorize("bold green"),
+                entry.display()
+            );
+        }
+
+        return Ok(selected_entries);
+    }
+
+    if skip_prompts || raw_prompts {
+        println!("Select entries to download:");
+
+        for (i, entry) in entries.iter().enumerate() {
+            println!(
+                "{:2}) [{}] {}",
+                i + 1,
+                if i == 0 { 'x' } else { ' ' },
+                entry.display()
+            );
+        }
+
+        println!("------------------------------");
+
+        let mut selected_entries_index = vec![1];
+
+        if raw_prompts && !skip_prompts {
+            print!(
+                "Press enter to proceed with defaults.\n\
+                Or select entries to download (1, 2, etc.): "
+            );
+            std::io::stdout().flush()?;
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+
+            println!("------------------------------");
+
+            let input = input.trim();
+
+            if !input.is_empty() {
+                selected_entries_index = input
+                    .split(',')
+                    .filter_map(|x| x.trim().parse::<usize>().ok())
+                    .collect::<Vec<usize>>();
+            }
+        }
+
+        if selected_entries_index
+            .iter()
+            .any(|x| *x == 0 || *x > entries.len())
+        {
+            bail!("selected entry is out of index bounds.");
+        }
+
+        let selected_entries = entries
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| selected_entries_index.contains(&(i + 1)))
+            .map(|(_, x)| x)
+            .collect::<Vec<_>>();
+
+        for entry in &selected_entries {
+            println!(
+                "   {} {}",
+                "Selected".colorize("bold green"),
+                entry.display()
+            );
+        }
+
+        Ok(selected_entries)
+    } else {
+        let question = requestty::Question::multi_select("entries")
+            .should_loop(false)
+            .message("Select entries to download")
+            .choices_with_default(
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| (x.display(), i == 0)),
+            )
+            .transform(|choices, _, backend| {
+                backend.write_styled(
+                    &choices
+                        .iter()
+                        .map(|x| x.text.to_owned())
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                        .cyan(),
+                )
+            })
+            .build();
+
+        let answer = requestty::prompt_one(question)?;
+        let selected_entries_index = answer
+            .as_list_items()
+            .unwrap()
+            .iter()
+            .map(|x| x.index)
+            .collect::<Vec<_>>();
+
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| selected_entries_index.contains(i))
+            .map(|(_, x)| x)
+            .collect())
+    }
+}
@@ -1,3 +1,7 @@
+mod iptv;
 mod playlist;
+mod variables;
 
-pub(crate) use playlist::{parse_as_master, push_segments};
+pub(crate) use iptv::{parse_iptv_playlist, select_entries};
+pub(crate) use playlist::{parse_as_master, parse_session_key, push_segments};
+pub(crate) use variables::substitute_variables;
@@ -1,4 +1,11 @@
-use crate::playlist;
+use crate::{playlist, utils};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use vsd_mp4::pssh::Pssh;
+
+const WIDEVINE_KEY_FORMAT: &str = "urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed";
+const FAIRPLAY_KEY_FORMAT: &str = "com.apple.streamingkeydelivery";
+const PLAYREADY_KEY_FORMAT: &str = "com.microsoft.playready";
 
 pub(crate) fn parse_as_master(
     m3u8: &m3u8_rs::MasterPlaylist,
@@ -8,15 +15,25 @@ pub(crate) fn parse_as_master(
 
     for video_stream in &m3u8.variants {
         streams.push(playlist::MediaPlaylist {
+            accessibility: vec![],
             bandwidth: Some(video_stream.bandwidth),
             channels: None,
             codecs: video_stream.codecs.to_owned(),
             extension: Some("ts".to_owned()), // Cannot be comment here
             frame_rate: video_stream.frame_rate.map(|x| x as f32),
+            group_id: None,
+            groups: parse_groups(video_stream),
             i_frame: video_stream.is_i_frame,
+            id: stream_id(&video_stream.uri),
+            label: None,
             language: None,
             live: false, // Cannot be comment here
-            media_type: playlist::MediaType::Video,
+            media_type: if video_stream.is_i_frame {
+                playlist::MediaType::Thumbnails
+            } else {
+                playlist::MediaType::Video
+            },
+            object_audio: false,
             playlist_type: playlist::PlaylistType::Hls,
             resolution: if let Some(m3u8_rs::Resolution { width, height }) = video_stream.resolution
             {
@@ -24,147 +41,421 @@ pub(crate) fn parse_as_master(
             } else {
                 None
             },
+            roles: vec![],
             segments: vec![], // Cannot be comment here
+            tiles: None,
             uri: video_stream.uri.to_owned(),
+            video_range: Some(parse_video_range(video_stream)),
         });
     }
 
     for alternative_stream in &m3u8.alternatives {
         if let Some(uri) = &alternative_stream.uri {
+            let media_type = match alternative_stream.media_type {
+                m3u8_rs::AlternativeMediaType::Audio => playlist::MediaType::Audio,
+                m3u8_rs::AlternativeMediaType::ClosedCaptions
+                | m3u8_rs::AlternativeMediaType::Subtitles => playlist::MediaType::Subtitles,
+                m3u8_rs::AlternativeMediaType::Video => playlist::MediaType::Video,
+                m3u8_rs::AlternativeMediaType::Other(_) => playlist::MediaType::Undefined,
+            };
+            let variants = m3u8
+                .variants
+                .iter()
+                .filter(|x| {
+                    !x.is_i_frame
+                        && parse_groups(x).contains(&media_type, &alternative_stream.group_id)
+                })
+                .collect::<Vec<_>>();
+            let group_id = Some(alternative_stream.group_id.to_owned());
+            let (channels, object_audio) = parse_channels(alternative_stream.channels.as_deref());
+
             match alternative_stream.media_type {
                 m3u8_rs::AlternativeMediaType::Video => streams.push(playlist::MediaPlaylist {
+                    accessibility: vec![],
                     bandwidth: None, // Cannot be comment here
                     channels: None,
-                    codecs: None,                     // Cannot be comment here
+                    codecs: rendition_codecs(&variants, &media_type),
                     extension: Some("ts".to_owned()), // Cannot be comment here
                     frame_rate: None,                 // Cannot be comment here
-                    i_frame: false,                   // Cannot be comment here
+                    group_id,
+                    groups: playlist::Groups::default(),
+                    i_frame: false, // Cannot be comment here
+                    id: stream_id(uri),
+                    label: None,
                     language: None,
                     live: false, // Cannot be comment here
                     media_type: playlist::MediaType::Video,
+                    object_audio: false,
                     playlist_type: playlist::PlaylistType::Hls,
                     resolution: None, // Cannot be comment here
+                    roles: vec![],
                     segments: vec![], // Cannot be comment here
+                    tiles: None,
                     uri: uri.to_owned(),
+                    video_range: None,
                 }),
 
                 m3u8_rs::AlternativeMediaType::Audio => streams.push(playlist::MediaPlaylist {
-                    bandwidth: None, // Cannot be comment here
-                    channels: alternative_stream
-                        .channels
-                        .as_ref()
-                        .map(|x| x.parse::<f32>().unwrap()),
-                    codecs: None,                     // Cannot be comment here
+                    accessibility: vec![],
+                    bandwidth: rendition_bandwidth(&variants, &media_type),
+                    channels,
+                    codecs: rendition_codecs(&variants, &media_type),
                     extension: Some("ts".to_owned()), // Cannot be comment here
                     frame_rate: None,
+                    group_id,
+                    groups: playlist::Groups::default(),
                     i_frame: false,
+                    id: stream_id(uri),
+                    label: None,
                     language: alternative_stream
                         .language
                         .to_owned()
                         .or(alternative_stream.assoc_language.to_owned()),
                     live: false, // Cannot be comment here
                     media_type: playlist::MediaType::Audio,
+                    object_audio,
                     playlist_type: playlist::PlaylistType::Hls,
                     resolution: None,
+                    roles: vec![],
                     segments: vec![], // Cannot be comment here
+                    tiles: None,
                     uri: uri.to_owned(),
+                    video_range: None,
                 }),
 
                 m3u8_rs::AlternativeMediaType::ClosedCaptions
                 | m3u8_rs::AlternativeMediaType::Subtitles => {
                     streams.push(playlist::MediaPlaylist {
+                        accessibility: vec![],
                         bandwidth: None,
                         channels: None,
-                        codecs: None,                      // Cannot be comment here
+                        codecs: rendition_codecs(&variants, &media_type),
                         extension: Some("vtt".to_owned()), // Cannot be comment here
                         frame_rate: None,
+                        group_id,
+                        groups: playlist::Groups::default(),
                         i_frame: false,
+                        id: stream_id(uri),
+                        label: None,
                         language: alternative_stream
                             .language
                             .to_owned()
                             .or(alternative_stream.assoc_language.to_owned()),
                         live: false, // Cannot be comment here
                         media_type: playlist::MediaType::Subtitles,
+                        object_audio: false,
                         playlist_type: playlist::PlaylistType::Hls,
                         resolution: None,
+                        roles: vec![],
                         segments: vec![], // Cannot be comment here
+                        tiles: None,
                         uri: uri.to_owned(),
+                        video_range: None,
                     })
                 }
 
                 m3u8_rs::AlternativeMediaType::Other(_) => streams.push(playlist::MediaPlaylist {
+                    accessibility: vec![],
                     bandwidth: None,
-                    channels: alternative_stream
-                        .channels
-                        .as_ref()
-                        .map(|x| x.parse::<f32>().unwrap()),
+                    channels,
                     codecs: None,     // Cannot be comment here
                     extension: None,  // Cannot be comment here
                     frame_rate: None, // Cannot be comment here
-                    i_frame: false,   // Cannot be comment here
+                    group_id,
+                    groups: playlist::Groups::default(),
+                    i_frame: false, // Cannot be comment here
+                    id: stream_id(uri),
+                    label: None,
                     language: alternative_stream
                         .language
                         .to_owned()
                         .or(alternative_stream.assoc_language.to_owned()),
                     live: false, // Cannot be comment here
                     media_type: playlist::MediaType::Undefined,
+                    object_audio,
                     playlist_type: playlist::PlaylistType::Hls,
                     resolution: None, // Cannot be comment here
+                    roles: vec![],
                     segments: vec![], // Cannot be comment here
+                    tiles: None,
                     uri: uri.to_owned(),
+                    video_range: None,
                 }),
             }
         }
     }
 
+    // Image media playlists are advertised using #EXT-X-IMAGE-STREAM-INF tags.
+    for tag in &m3u8.unknown_tags {
+        if tag.tag != "X-IMAGE-STREAM-INF" {
+            continue;
+        }
+
+        let attributes = parse_attributes(tag.rest.as_deref().unwrap_or(""));
+
+        if let Some(uri) = attributes.get("URI") {
+            streams.push(playlist::MediaPlaylist {
+                accessibility: vec![],
+                bandwidth: attributes.get("BANDWIDTH").and_then(|x| x.parse().ok()),
+                channels: None,
+                codecs: attributes.get("CODECS").cloned(),
+                extension: Some("jpg".to_owned()), // Cannot be comment here
+                frame_rate: None,
+                group_id: None,
+                groups: playlist::Groups::default(),
+                i_frame: false,
+                id: stream_id(uri),
+                label: None,
+                language: None,
+                live: false, // Cannot be comment here
+                media_type: playlist::MediaType::Thumbnails,
+                object_audio: false,
+                playlist_type: playlist::PlaylistType::Hls,
+                resolution: attributes
+                    .get("RESOLUTION")
+                    .and_then(|x| x.split_once('x'))
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?))),
+                roles: vec![],
+                segments: vec![], // Cannot be comment here
+                tiles: None,      // Cannot be comment here
+                uri: uri.to_owned(),
+                video_range: None,
+            });
+        }
+    }
+
+    // Variants sharing a media playlist with different rendition groups are numbered.
+    let mut ids = HashMap::new();
+
+    for stream in &mut streams {
+        let count = ids.entry(stream.id.clone()).or_insert(0);
+        *count += 1;
+
+        if *count > 1 {
+            stream.id = format!("{}:{}", stream.id, count);
+        }
+    }
+
     playlist::MasterPlaylist {
+        duration: None, // Cannot be comment here
         playlist_type: playlist::PlaylistType::Hls,
         uri: uri.to_owned(),
         streams,
     }
 }
 
-pub(crate) fn push_segments(m3u8: &m3u8_rs::MediaPlaylist, playlist: &mut playlist::MediaPlaylist) {
+fn parse_groups(variant: &m3u8_rs::VariantStream) -> playlist::Groups {
+    playlist::Groups {
+        audio: variant.audio.to_owned(),
+        closed_captions: match &variant.closed_captions {
+            Some(m3u8_rs::ClosedCaptionGroupId::GroupId(x)) => Some(x.to_owned()),
+            _ => None,
+        },
+        subtitles: variant.subtitles.to_owned(),
+        video: variant.video.to_owned(),
+    }
+}
+
+/// `CHANNELS` attribute i.e. count of channels followed by `/` separated parameters (eg. `16/JOC`).
+/// Second parameter lists audio coding identifiers, `JOC` is used for object based (dolby atmos) audio.
+fn parse_channels(channels: Option<&str>) -> (Option<f32>, bool) {
+    let mut parameters = channels.unwrap_or_default().split('/');
+    let count = parameters.next().and_then(|x| x.trim().parse::<f32>().ok());
+    let object_audio = parameters
+        .next()
+        .map(|x| x.split(',').any(|y| y.trim().eq_ignore_ascii_case("JOC")))
+        .unwrap_or(false);
+
+    (count, object_audio)
+}
+
+/// Uri without query and fragment, these are usually session tokens.
+fn stream_id(uri: &str) -> String {
+    uri.split(['?', '#']).next().unwrap_or(uri).to_owned()
+}
+
+/// `VIDEO-RANGE` attribute refined with dolby vision `CODECS` and `SUPPLEMENTAL-CODECS`.
+fn parse_video_range(variant: &m3u8_rs::VariantStream) -> playlist::VideoRange {
+    let attribute = |key| {
+        variant
+            .other_attributes
+            .as_ref()
+            .and_then(|x| x.get(key))
+            .map(|x| x.as_str())
+    };
+
+    // SUPPLEMENTAL-CODECS="dvh1.08.07/db4h"
+    let supplemental_codecs = attribute("SUPPLEMENTAL-CODECS")
+        .map(|x| {
+            x.split(',')
+                .map(|y| y.split('/').next().unwrap())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default();
+
+    if let Some(x) = variant
+        .codecs
+        .as_deref()
+        .and_then(playlist::VideoRange::from_codecs)
+        .or(playlist::VideoRange::from_codecs(&supplemental_codecs))
+    {
+        return x;
+    }
+
+    match attribute("VIDEO-RANGE") {
+        Some("PQ") => playlist::VideoRange::Hdr10,
+        Some("HLG") => playlist::VideoRange::Hlg,
+        _ => playlist::VideoRange::Sdr,
+    }
+}
+
+/// Media type of a single codec from `CODECS` attribute.
+fn codec_media_type(codec: &str) -> playlist::MediaType {
+    match codec.split('.').next().unwrap_or(codec).trim() {
+        "ac-3" | "ac-4" | "alac" | "dtsc" | "dtse" | "dtsh" | "dtsl" | "ec-3" | "flac" | "mha1"
+        | "mhm1" | "mp3" | "mp4a" | "opus" | "vorbis" => playlist::MediaType::Audio,
+        "stpp" | "wvtt" => playlist::MediaType::Subtitles,
+        "av01" | "avc1" | "avc3" | "dva1" | "dvav" | "dvh1" | "dvhe" | "hev1" | "hvc1" | "mp4v"
+        | "vp08" | "vp09" => playlist::MediaType::Video,
+        _ => playlist::MediaType::Undefined,
+    }
+}
+
+/// Codecs of an alternative rendition, derived from `CODECS` attribute of variants referencing its group.
+fn rendition_codecs(
+    variants: &[&m3u8_rs::VariantStream],
+    media_type: &playlist::MediaType,
+) -> Option<String> {
+    let mut codecs = vec![];
+
+    for codec in variants
+        .iter()
+        .filter_map(|x| x.codecs.as_ref())
+        .flat_map(|x| x.split(','))
+        .map(|x| x.trim())
+    {
+        if codec_media_type(codec) == *media_type && !codecs.contains(&codec) {
+            codecs.push(codec);
+        }
+    }
+
+    if codecs.is_empty() {
+        None
+    } else {
+        Some(codecs.join(","))
+    }
+}
+
+/// Bandwidth of an alternative rendition, derived from variants referencing its group
+/// which only contains codecs of the same media type, i.e. audio only variants.
+fn rendition_bandwidth(
+    variants: &[&m3u8_rs::VariantStream],
+    media_type: &playlist::MediaType,
+) -> Option<u64> {
+    variants
+        .iter()
+        .filter(|x| {
+            x.codecs
+                .as_ref()
+                .map(|y| y.split(',').all(|z| codec_media_type(z) == *media_type))
+                .unwrap_or(false)
+        })
+        .map(|x| x.average_bandwidth.unwrap_or(x.bandwidth))
+        .min()
+}
+
+pub(crate) fn parse_session_key(text: &str) -> Option<playlist::Key> {
+    let tags = text
+        .lines()
+        .filter_map(|x| x.trim().strip_prefix("#EXT-X-SESSION-KEY:"))
+        .map(parse_attributes)
+        .collect::<Vec<_>>();
+
+    let tag = tags.first()?;
+    let method = match tag.get("METHOD").map(|x| x.as_str()) {
+        Some("AES-128") => playlist::KeyMethod::Aes128,
+        Some("SAMPLE-AES") => playlist::KeyMethod::SampleAes,
+        Some("SAMPLE-AES-CTR") | Some("SAMPLE-AES-CENC") => playlist::KeyMethod::Cenc,
+        Some("NONE") | None => return None,
+        Some(x) => playlist::KeyMethod::Other(x.to_owned()),
+    };
+
+    let mut key = playlist::Key {
+        default_kid: None,
+        iv: tag.get("IV").cloned(),
+        key_format: tag.get("KEYFORMAT").cloned(),
+        method,
+        pssh: None,
+        uri: tag.get("URI").cloned(),
+    };
+
+    update_key(&mut key, &tags);
+    Some(key)
+}
+
+pub(crate) fn push_segments(
+    m3u8: &m3u8_rs::MediaPlaylist,
+    text: &str,
+    playlist: &mut playlist::MediaPlaylist,
+    session_key: Option<&playlist::Key>,
+) -> Result<()> {
     playlist.i_frame = m3u8.i_frames_only;
     playlist.live = !m3u8.end_list;
 
-    let mut previous_byterange_end = 0;
+    if m3u8.i_frames_only || text.contains("#EXT-X-IMAGES-ONLY") {
+        playlist.media_type = playlist::MediaType::Thumbnails;
+    }
 
-    for segment in &m3u8.segments {
-        let map = segment.map.as_ref().map(|x| playlist::Map {
-            uri: x.uri.to_owned(),
-            range: x.byte_range.as_ref().map(|x| {
-                let offset = x.offset.unwrap_or(0);
+    // Offset of the next byte after previous sub-range of a resource, i.e. segment uri.
+    let mut next_offsets: HashMap<&str, u64> = HashMap::new();
+    let key_tags = parse_key_tags(text);
 
-                let (start, end) = if offset == 0 {
-                    (
-                        previous_byterange_end,
-                        (previous_byterange_end + x.length) - 1,
-                    )
-                } else {
-                    (x.length, (x.length + offset) - 1)
-                };
-
-                previous_byterange_end = end;
-                playlist::Range { start, end }
-            }),
-        });
+    for (i, segment) in m3u8.segments.iter().enumerate() {
+        if playlist.tiles.is_none() {
+            if let Some(tag) = segment.unknown_tags.iter().find(|x| x.tag == "X-TILES") {
+                let attributes = parse_attributes(tag.rest.as_deref().unwrap_or(""));
 
-        let range = segment.byte_range.as_ref().map(|x| {
-            let offset = x.offset.unwrap_or(0);
+                if let Some(mut tiles) = attributes
+                    .get("LAYOUT")
+                    .and_then(|x| playlist::Tiles::from_layout(x))
+                {
+                    tiles.duration = attributes.get("DURATION").and_then(|x| x.parse().ok());
+                    tiles.resolution = attributes
+                        .get("RESOLUTION")
+                        .and_then(|x| x.split_once('x'))
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                    playlist.tiles = Some(tiles);
+                }
+            }
+        }
 
-            let (start, end) = if offset == 0 {
-                (
-                    previous_byterange_end,
-                    (previous_byterange_end + x.length) - 1,
-                )
-            } else {
-                (x.length, (x.length + offset) - 1)
-            };
+        // If offset is not present in EXT-X-MAP BYTERANGE then range starts at byte 0.
+        let map = match &segment.map {
+            Some(x) => Some(playlist::Map {
+                uri: x.uri.to_owned(),
+                range: x
+                    .byte_range
+                    .as_ref()
+                    .map(|x| byte_range(x, x.offset.unwrap_or(0)))
+                    .transpose()?,
+            }),
+            None => None,
+        };
 
-            previous_byterange_end = end;
-            playlist::Range { start, end }
-        });
+        // If offset is not present in EXT-X-BYTERANGE then range begins at the next byte
+        // following the sub-range of the previous segment of same resource.
+        let range = segment
+            .byte_range
+            .as_ref()
+            .map(|x| {
+                let start = x
+                    .offset
+                    .unwrap_or_else(|| *next_offsets.get(segment.uri.as_str()).unwrap_or(&0));
+                next_offsets.insert(&segment.uri, start + x.length);
+                byte_range(x, start)
+            })
+            .transpose()?;
 
         playlist.segments.push(playlist::Segment {
             duration: segment.duration,
@@ -191,19 +482,43 @@ pub(crate) fn push_segments(m3u8: &m3u8_rs::MediaPlaylist, playlist: &mut playli
 
                 if let Some(keyformat) = keyformat {
                     method = match keyformat.as_str() {
-                        "urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed"
-                        | "com.apple.streamingkeydelivery"
-                        | "com.microsoft.playready" => playlist::KeyMethod::Cenc, // cbcs (pattern-based) | cbc1
+                        WIDEVINE_KEY_FORMAT | FAIRPLAY_KEY_FORMAT | PLAYREADY_KEY_FORMAT => {
+                            playlist::KeyMethod::Cenc // cbcs (pattern-based) | cbc1
+                        }
                         _ => method,
                     };
                 }
 
-                Some(playlist::Key {
+                let mut key = playlist::Key {
                     default_kid: None,
                     iv: iv.clone(),
                     key_format: keyformat.clone(),
                     method,
+                    pssh: None,
                     uri: uri.clone(),
+                };
+
+                if let Some(tags) = key_tags.get(&i) {
+                    update_key(&mut key, tags);
+                }
+
+                Some(key)
+            } else if key_tags
+                .get(&i)
+                .and_then(|x| x.last())
+                .and_then(|x| x.get("METHOD"))
+                .map(|x| x == "NONE")
+                .unwrap_or(false)
+            {
+                // m3u8-rs drops METHOD=NONE key tags without an IV attribute, which would
+                // otherwise keep decrypting the following segments with the previous key.
+                Some(playlist::Key {
+                    default_kid: None,
+                    iv: None,
+                    key_format: None,
+                    method: playlist::KeyMethod::None,
+                    pssh: None,
+                    uri: None,
                 })
             } else {
                 None
@@ -214,6 +529,20 @@ pub(crate) fn push_segments(m3u8: &m3u8_rs::MediaPlaylist, playlist: &mut playli
         });
     }
 
+    if let (Some(session_key), Some(playlist::Segment { key: Some(key), .. })) =
+        (session_key, playlist.segments.get_mut(0))
+    {
+        if key.default_kid.is_none() {
+            key.default_kid = session_key.default_kid.clone();
+        }
+
+        if let Some(session_pssh) = &session_key.pssh {
+            key.pssh
+                .get_or_insert_with(Pssh::default)
+                .extend(session_pssh.clone());
+        }
+    }
+
     if let Some(segment) = playlist.segments.get(0) {
         if let Some(init) = &segment.map {
             if init.uri.split('?').next().unwrap().ends_with(".mp4") {
@@ -239,4 +568,357 @@ pub(crate) fn push_segments(m3u8: &m3u8_rs::MediaPlaylist, playlist: &mut playli
             playlist.extension = Some(extension.to_owned());
         }
     }
+
+    Ok(())
+}
+
+fn byte_range(byte_range: &m3u8_rs::ByteRange, start: u64) -> Result<playlist::Range> {
+    if byte_range.length == 0 {
+        bail!(
+            "hls playlist contains a zero length byte range at offset {}.",
+            start
+        );
+    }
+
+    Ok(playlist::Range {
+        start,
+        end: start + byte_range.length - 1,
+    })
+}
+
+/// Attributes of all `#EXT-X-KEY` tags preceding a segment, mapped by segment index.
+/// m3u8-rs only keeps the last key tag of a segment and drops the KEYID attribute.
+fn parse_key_tags(text: &str) -> HashMap<usize, Vec<HashMap<String, String>>> {
+    let mut key_tags = HashMap::new();
+    let mut tags = vec![];
+    let mut index = 0;
+
+    for line in text.lines().map(|x| x.trim()) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            tags.push(parse_attributes(attributes));
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if !tags.is_empty() {
+                key_tags.insert(index, std::mem::take(&mut tags));
+            }
+
+            index += 1;
+        }
+    }
+
+    key_tags
+}
+
+pub(super) fn parse_attributes(attributes: &str) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    let mut rest = attributes.trim();
+
+    while let Some((name, value)) = rest.split_once('=') {
+        let (value, remaining) = if let Some(value) = value.strip_prefix('"') {
+            value.split_once('"').unwrap_or((value, ""))
+        } else {
+            value.split_once(',').unwrap_or((value, ""))
+        };
+
+        map.insert(name.trim().to_owned(), value.to_owned());
+        rest = remaining.trim_start_matches(',').trim();
+    }
+
+    map
+}
+
+fn update_key(key: &mut playlist::Key, tags: &[HashMap<String, String>]) {
+    for tag in tags {
+        if key.default_kid.is_none() {
+            if let Some(kid) = tag.get("KEYID") {
+                key.default_kid = Some(
+                    kid.trim_start_matches("0x")
+                        .trim_start_matches("0X")
+                        .to_lowercase(),
+                );
+            }
+        }
+
+        let key_format = tag.get("KEYFORMAT").map(|x| x.as_str());
+
+        if let Some(WIDEVINE_KEY_FORMAT | FAIRPLAY_KEY_FORMAT | PLAYREADY_KEY_FORMAT) = key_format {
+            key.method = playlist::KeyMethod::Cenc;
+        }
+
+        if let Some(data) = tag
+            .get("URI")
+            .filter(|x| x.starts_with("data:"))
+            .and_then(|x| utils::decode_data_uri(x).ok())
+        {
+            let pssh = match key_format {
+                Some(WIDEVINE_KEY_FORMAT) => Pssh::new(&data).ok(),
+                Some(PLAYREADY_KEY_FORMAT) => Pssh::from_playready_object(&data).ok(),
+                _ => None,
+            };
+
+            if let Some(pssh) = pssh {
+                key.pssh.get_or_insert_with(Pssh::default).extend(pssh);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PSSH_DATA_URI: &str = "data:text/plain;base64,AAAANHBzc2gBAAAA7e+LqXnWSs6jyCfc1R0h7QAAAAEAESIzRFVmd4iZqrvM3e7/AAAAAA==";
+
+    fn parse(text: &str) -> Result<playlist::MediaPlaylist> {
+        parse_with_session_key(text, None)
+    }
+
+    fn parse_with_session_key(
+        text: &str,
+        session_key: Option<&playlist::Key>,
+    ) -> Result<playlist::MediaPlaylist> {
+        let m3u8 = m3u8_rs::parse_media_playlist_res(text.as_bytes()).unwrap();
+        let mut playlist = playlist::MediaPlaylist::default();
+        push_segments(&m3u8, text, &mut playlist, session_key)?;
+        Ok(playlist)
+    }
+
+    fn ranges(playlist: &playlist::MediaPlaylist) -> Vec<Option<(u64, u64)>> {
+        playlist
+            .segments
+            .iter()
+            .map(|x| x.range.as_ref().map(|x| (x.start, x.end)))
+            .collect()
+    }
+
+    #[test]
+    fn implicit_offsets_per_uri() {
+        let playlist = parse(
+            "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXTINF:10,
+#EXT-X-BYTERANGE:100@0
+a.ts
+#EXTINF:10,
+#EXT-X-BYTERANGE:200@0
+b.ts
+#EXTINF:10,
+#EXT-X-BYTERANGE:100
+a.ts
+#EXTINF:10,
+#EXT-X-BYTERANGE:200
+b.ts
+#EXT-X-ENDLIST
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            ranges(&playlist),
+            vec![
+                Some((0, 99)),
+                Some((0, 199)),
+                Some((100, 199)),
+                Some((200, 399))
+            ]
+        );
+    }
+
+    #[test]
+    fn implicit_offsets_after_explicit_offset() {
+        let playlist = parse(
+            "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXTINF:10,
+#EXT-X-BYTERANGE:500@1000
+a.ts
+#EXTINF:10,
+#EXT-X-BYTERANGE:300
+a.ts
+#EXTINF:10,
+#EXT-X-BYTERANGE:200
+a.ts
+#EXT-X-ENDLIST
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            ranges(&playlist),
+            vec![Some((1000, 1499)), Some((1500, 1799)), Some((1800, 1999))]
+        );
+    }
+
+    #[test]
+    fn map_byte_range() {
+        let playlist = parse(
+            "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXT-X-MAP:URI=\"a.mp4\",BYTERANGE=\"720\"
+#EXTINF:10,
+#EXT-X-BYTERANGE:1000@720
+a.mp4
+#EXT-X-MAP:URI=\"b.mp4\",BYTERANGE=\"600@50\"
+#EXTINF:10,
+#EXT-X-BYTERANGE:1000@650
+b.mp4
+#EXT-X-ENDLIST
+",
+        )
+        .unwrap();
+
+        let maps = playlist
+            .segments
+            .iter()
+            .map(|x| {
+                let map = x.map.as_ref().unwrap();
+                let range = map.range.as_ref().unwrap();
+                (map.uri.as_str(), range.start, range.end)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(maps, vec![("a.mp4", 0, 719), ("b.mp4", 50, 649)]);
+        assert_eq!(
+            ranges(&playlist),
+            vec![Some((720, 1719)), Some((650, 1649))]
+        );
+    }
+
+    #[test]
+    fn zero_length_byte_range() {
+        assert!(parse(
+            "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXTINF:10,
+#EXT-X-BYTERANGE:0@100
+a.ts
+#EXT-X-ENDLIST
+",
+        )
+        .is_err());
+        assert!(parse(
+            "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXT-X-MAP:URI=\"a.mp4\",BYTERANGE=\"0\"
+#EXTINF:10,
+a.mp4
+#EXT-X-ENDLIST
+",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn attributes_with_quoted_commas() {
+        let attributes = parse_attributes(
+            "METHOD=SAMPLE-AES,URI=\"skd://key,1\",KEYFORMAT=\"com.apple.streamingkeydelivery\",IV=0x0A0B",
+        );
+
+        assert_eq!(attributes.len(), 4);
+        assert_eq!(attributes["METHOD"], "SAMPLE-AES");
+        assert_eq!(attributes["URI"], "skd://key,1");
+        assert_eq!(attributes["KEYFORMAT"], FAIRPLAY_KEY_FORMAT);
+        assert_eq!(attributes["IV"], "0x0A0B");
+    }
+
+    #[test]
+    fn key_tags_per_segment() {
+        let key_tags = parse_key_tags(&format!(
+            "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"skd://key\",KEYFORMAT=\"{}\"
+#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"{}\",KEYFORMAT=\"{}\"
+#EXTINF:10,
+a.mp4
+#EXTINF:10,
+b.mp4
+#EXT-X-KEY:METHOD=NONE
+#EXTINF:10,
+c.mp4
+#EXT-X-ENDLIST
+",
+            FAIRPLAY_KEY_FORMAT, PSSH_DATA_URI, WIDEVINE_KEY_FORMAT
+        ));
+
+        assert_eq!(key_tags.len(), 2);
+        assert_eq!(key_tags[&0].len(), 2);
+        assert_eq!(key_tags[&0][1]["URI"], PSSH_DATA_URI);
+        assert!(!key_tags.contains_key(&1));
+        assert_eq!(key_tags[&2][0]["METHOD"], "NONE");
+    }
+
+    #[test]
+    fn session_key() {
+        let key = parse_session_key(&format!(
+            "#EXTM3U
+#EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"{}\",KEYID=0x00112233445566778899AABBCCDDEEFF,KEYFORMAT=\"{}\"
+#EXT-X-STREAM-INF:BANDWIDTH=1000000
+video.m3u8
+",
+            PSSH_DATA_URI, WIDEVINE_KEY_FORMAT
+        ))
+        .unwrap();
+
+        assert!(matches!(key.method, playlist::KeyMethod::Cenc));
+        assert_eq!(
+            key.default_kid.as_deref(),
+            Some("00112233445566778899aabbccddeeff")
+        );
+
+        let pssh = key.pssh.unwrap();
+        assert_eq!(pssh.system_ids, vec!["edef8ba979d64acea3c827dcd51d21ed"]);
+        assert_eq!(pssh.key_ids[0].value, "00112233445566778899aabbccddeeff");
+
+        assert!(parse_session_key("#EXTM3U\n#EXT-X-SESSION-KEY:METHOD=NONE\n").is_none());
+    }
+
+    #[test]
+    fn session_key_applies_to_first_segment_only() {
+        let session_key = parse_session_key(&format!(
+            "#EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"{}\",KEYID=0x00112233445566778899AABBCCDDEEFF,KEYFORMAT=\"{}\"",
+            PSSH_DATA_URI, WIDEVINE_KEY_FORMAT
+        ));
+        let playlist = parse_with_session_key(
+            &format!(
+                "#EXTM3U
+#EXT-X-TARGETDURATION:10
+#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"skd://a\",KEYFORMAT=\"{0}\"
+#EXTINF:10,
+a.mp4
+#EXT-X-KEY:METHOD=NONE
+#EXTINF:10,
+b.mp4
+#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"skd://c\",KEYFORMAT=\"{0}\",KEYID=0xFFEEDDCCBBAA99887766554433221100
+#EXTINF:10,
+c.mp4
+#EXT-X-ENDLIST
+",
+                FAIRPLAY_KEY_FORMAT
+            ),
+            session_key.as_ref(),
+        )
+        .unwrap();
+
+        let keys = playlist
+            .segments
+            .iter()
+            .map(|x| x.key.as_ref().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            keys[0].default_kid.as_deref(),
+            Some("00112233445566778899aabbccddeeff")
+        );
+        assert!(keys[0].pssh.is_some());
+
+        // METHOD=NONE clears the key for following segments.
+        assert!(matches!(keys[1].method, playlist::KeyMethod::None));
+        assert!(keys[1].default_kid.is_none() && keys[1].pssh.is_none());
+
+        // Later EXT-X-KEY tags are used as they are.
+        assert_eq!(
+            keys[2].default_kid.as_deref(),
+            Some("ffeeddccbbaa99887766554433221100")
+        );
+        assert!(keys[2].pssh.is_none());
+    }
 }
@@ -0,0 +1,173 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://datatracker.ietf.org/doc/html/draft-pantos-hls-rfc8216bis#section-4.4.2.3
+
+*/
+
+use super::playlist::parse_attributes;
+use anyhow::{bail, Result};
+use regex::{Captures, Regex};
+use reqwest::Url;
+use std::collections::HashMap;
+
+/// Resolve `#EXT-X-DEFINE` tags and substitute `{$name}` variable references in playlist.
+///
+/// # Arguments
+///
+/// - `text` - Playlist content.
+/// - `url` - Playlist url, used for resolving `QUERYPARAM` variables.
+/// - `imports` - Variables defined in master playlist, used for resolving `IMPORT` variables.
+///
+/// Returns substituted playlist content and variables defined in it.
+pub(crate) fn substitute_variables(
+    text: &str,
+    url: &Url,
+    imports: &HashMap<String, String>,
+) -> Result<(String, HashMap<String, String>)> {
+    let mut variables = HashMap::new();
+
+    for line in text.lines() {
+        if let Some(attributes) = line.trim().strip_prefix("#EXT-X-DEFINE:") {
+            let attributes = parse_attributes(attributes);
+
+            if let Some(name) = attributes.get("NAME") {
+                if let Some(value) = attributes.get("VALUE") {
+                    variables.insert(name.to_owned(), value.to_owned());
+                } else {
+                    bail!("#EXT-X-DEFINE tag with NAME={} doesn't have a VALUE.", name);
+                }
+            } else if let Some(name) = attributes.get("IMPORT") {
+                if let Some(value) = imports.get(name) {
+                    variables.insert(name.to_owned(), value.to_owned());
+                } else {
+                    bail!("#EXT-X-DEFINE tag imports undefined variable {}.", name);
+                }
+            } else if let Some(name) = attributes.get("QUERYPARAM") {
+                if let Some((_, value)) = url.query_pairs().find(|(x, _)| x == name) {
+                    variables.insert(name.to_owned(), value.into_owned());
+                } else {
+                    bail!(
+                        "#EXT-X-DEFINE tag query parameter {} is not present in {}.",
+                        name,
+                        url
+                    );
+                }
+            }
+        }
+    }
+
+    if variables.is_empty() {
+        return Ok((text.to_owned(), variables));
+    }
+
+    let re = Regex::new(r"\{\$([A-Za-z0-9_-]+)\}").unwrap();
+    let mut substituted = String::with_capacity(text.len());
+
+    for line in text.lines() {
+        if !line.trim().starts_with("#EXT-X-DEFINE:") {
+            let mut undefined = None;
+            let line = re.replace_all(line, |caps: &Captures| {
+                if let Some(value) = variables.get(&caps[1]) {
+                    value.to_owned()
+                } else {
+                    undefined = Some(caps[1].to_owned());
+                    caps[0].to_owned()
+                }
+            });
+
+            if let Some(name) = undefined {
+                bail!("playlist references undefined variable {}.", name);
+            }
+
+            substituted += &line;
+        } else {
+            substituted += line;
+        }
+
+        substituted.push('\n');
+    }
+
+    Ok((substituted, variables))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        "https://example.com/video/media.m3u8?token=abc%3D&id=1"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn name_value() {
+        let (text, variables) = substitute_variables(
+            "#EXTM3U
+#EXT-X-DEFINE:NAME=\"path\",VALUE=\"segments/1080p\"
+#EXTINF:10,
+{$path}/0.ts
+",
+            &url(),
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(text.lines().last(), Some("segments/1080p/0.ts"));
+        assert_eq!(variables["path"], "segments/1080p");
+    }
+
+    #[test]
+    fn import_and_query_param() {
+        let imports = HashMap::from([("host".to_owned(), "https://cdn.example.com".to_owned())]);
+        let (text, _) = substitute_variables(
+            "#EXTM3U
+#EXT-X-DEFINE:IMPORT=\"host\"
+#EXT-X-DEFINE:QUERYPARAM=\"token\"
+#EXTINF:10,
+{$host}/0.ts?token={$token}
+",
+            &url(),
+            &imports,
+        )
+        .unwrap();
+
+        assert_eq!(
+            text.lines().last(),
+            Some("https://cdn.example.com/0.ts?token=abc=")
+        );
+    }
+
+    #[test]
+    fn undefined_variables() {
+        let error = substitute_variables(
+            "#EXTM3U
+#EXT-X-DEFINE:NAME=\"path\",VALUE=\"segments\"
+#EXTINF:10,
+{$path}/{$quality}/0.ts
+",
+            &url(),
+            &HashMap::new(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "playlist references undefined variable quality."
+        );
+
+        assert!(substitute_variables(
+            "#EXTM3U\n#EXT-X-DEFINE:IMPORT=\"host\"\n",
+            &url(),
+            &HashMap::new()
+        )
+        .is_err());
+        assert!(substitute_variables(
+            "#EXTM3U\n#EXT-X-DEFINE:QUERYPARAM=\"key\"\n",
+            &url(),
+            &HashMap::new()
+        )
+        .is_err());
+    }
+}
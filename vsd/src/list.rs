@@ -0,0 +1,179 @@
+use crate::{
+    playlist::{KeyMethod, MediaPlaylist, MediaType},
+    utils,
+};
+use anyhow::Result;
+use kdam::term::Colorizer;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct StreamInfo<'a> {
+    id: &'a str,
+    #[serde(rename = "type")]
+    media_type: String,
+    width: Option<u64>,
+    height: Option<u64>,
+    codecs: Option<&'a str>,
+    bandwidth: Option<u64>,
+    frame_rate: Option<f32>,
+    video_range: Option<String>,
+    language: Option<&'a str>,
+    label: Option<&'a str>,
+    channels: Option<f32>,
+    /// Object based (dolby atmos) audio.
+    object_audio: bool,
+    roles: &'a [String],
+    live: bool,
+    encryption: Option<&'a str>,
+    default_kid: Option<String>,
+    /// Total duration of segments in seconds.
+    duration: Option<f32>,
+    /// Estimated size in bytes, calculated from bandwidth and duration.
+    size: Option<u64>,
+}
+
+impl<'a> StreamInfo<'a> {
+    fn new(stream: &'a MediaPlaylist) -> Self {
+        let duration = stream.segments.iter().map(|x| x.duration).sum::<f32>();
+        let duration = (duration > 0.0).then_some(duration);
+
+        Self {
+            id: &stream.id,
+            media_type: stream.media_type.to_string(),
+            width: stream.resolution.map(|x| x.0),
+            height: stream.resolution.map(|x| x.1),
+            codecs: stream.codecs.as_deref(),
+            bandwidth: stream.bandwidth,
+            frame_rate: stream.frame_rate,
+            video_range: stream.video_range.map(|x| x.to_string()),
+            language: stream.language.as_deref(),
+            label: stream.label.as_deref(),
+            channels: stream.channels,
+            object_audio: stream.object_audio,
+            roles: &stream.roles,
+            live: stream.live,
+            encryption: stream
+                .segments
+                .first()
+                .and_then(|x| x.key.as_ref())
+                .and_then(|x| match &x.method {
+                    KeyMethod::Aes128 => Some("aes-128"),
+                    KeyMethod::Cenc => Some("cenc"),
+                    KeyMethod::None => None,
+                    KeyMethod::Other(x) => Some(x.as_str()),
+                    KeyMethod::SampleAes => Some("sample-aes"),
+                }),
+            default_kid: stream.default_kid(),
+            duration,
+            size: stream
+                .bandwidth
+                .zip(duration)
+                .map(|(x, y)| (x as f32 * y / 8.0) as u64),
+        }
+    }
+
+    fn row(&self) -> [String; 13] {
+        let or_dash = |x: Option<String>| x.unwrap_or_else(|| "-".to_owned());
+
+        [
+            self.id.to_owned(),
+            self.media_type.clone(),
+            or_dash(
+                self.width
+                    .zip(self.height)
+                    .map(|(w, h)| format!("{}x{}", w, h)),
+            ),
+            or_dash(self.codecs.map(|x| x.to_owned())),
+            or_dash(
+                self.bandwidth
+                    .map(|x| format!("{}/s", utils::format_bytes(x as usize, 2).2)),
+            ),
+            or_dash(self.frame_rate.map(|x| x.to_string())),
+            or_dash(self.language.map(|x| x.to_owned())),
+            or_dash(self.channels.map(|x| {
+                if self.object_audio {
+                    format!("{} (atmos)", x)
+                } else {
+                    x.to_string()
+                }
+            })),
+            or_dash((!self.roles.is_empty()).then(|| self.roles.join(","))),
+            if self.live { "yes" } else { "no" }.to_owned(),
+            or_dash(self.encryption.map(|x| x.to_owned())),
+            or_dash(self.size.map(|x| utils::format_bytes(x as usize, 2).2)),
+            or_dash(
+                self.duration
+                    .map(|x| kdam::format::interval(x as usize, false)),
+            ),
+        ]
+    }
+}
+
+/// Print streams as a table or as a json array to stdout.
+pub(crate) fn print_streams<'a>(
+    streams: impl Iterator<Item = &'a MediaPlaylist>,
+    json: bool,
+) -> Result<()> {
+    let mut streams = streams.collect::<Vec<_>>();
+
+    // Same order as stream selection prompts.
+    streams.sort_by_key(|x| match x.media_type {
+        MediaType::Video => 0,
+        MediaType::Audio => 1,
+        MediaType::Subtitles => 2,
+        MediaType::Thumbnails => 3,
+        MediaType::Undefined => 4,
+    });
+
+    let streams = streams.into_iter().map(StreamInfo::new).collect::<Vec<_>>();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&streams)?);
+        return Ok(());
+    }
+
+    let header = [
+        "ID",
+        "TYPE",
+        "RESOLUTION",
+        "CODECS",
+        "BANDWIDTH",
+        "FPS",
+        "LANGUAGE",
+        "CHANNELS",
+        "ROLES",
+        "LIVE",
+        "ENCRYPTION",
+        "SIZE",
+        "DURATION",
+    ];
+    let rows = streams.iter().map(|x| x.row()).collect::<Vec<_>>();
+    let mut widths = header.map(|x| x.len());
+
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+
+    let format_row = |row: &[String]| {
+        row.iter()
+            .zip(widths)
+            .map(|(x, width)| format!("{:width$}", x, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
+    };
+
+    println!(
+        "{}",
+        format_row(&header.map(|x| x.to_owned())).colorize("bold")
+    );
+
+    for row in &rows {
+        println!("{}", format_row(row));
+    }
+
+    Ok(())
+}
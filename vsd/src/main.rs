@@ -2,9 +2,12 @@ mod commands;
 mod cookie;
 mod dash;
 mod downloader;
+mod filter;
 mod hls;
+mod list;
 mod merger;
 mod playlist;
+mod smooth;
 mod update;
 mod utils;
 
@@ -29,6 +32,7 @@ fn run() -> anyhow::Result<()> {
     match args.command {
         #[cfg(feature = "browser")]
         Commands::Capture(args) => args.execute()?,
+        Commands::Decrypt(args) => args.execute()?,
         Commands::Extract(args) => args.execute()?,
         Commands::Merge(args) => args.execute()?,
         Commands::Save(args) => args.execute()?,
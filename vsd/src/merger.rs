@@ -3,7 +3,7 @@ use anyhow::Result;
 use std::{
     collections::HashMap,
     fs,
-    fs::File,
+    fs::{File, OpenOptions},
     io::Write,
     path::PathBuf,
 };
@@ -34,6 +34,23 @@ impl Merger {
         })
     }
 
+    /// Append segments to an already partially downloaded file.
+    pub(super) fn resume(size: usize, filename: &str) -> Result<Self> {
+        Ok(Self {
+            size: size - 1,
+            file: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(filename)?,
+            pos: 0,
+            buffers: HashMap::new(),
+            stored_bytes: 0,
+            flushed_bytes: 0,
+            indexed: 0,
+            directory: None,
+        })
+    }
+
     pub(super) fn with_directory(size: usize, directory: &str) -> Result<Self> {
         let directory = PathBuf::from(directory);
 
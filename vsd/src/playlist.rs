@@ -7,14 +7,21 @@
 
 */
 
-use crate::commands::Quality;
+use crate::{
+    commands::{Quality, QualityFallback},
+    filter::StreamFilter,
+};
 use anyhow::{bail, Result};
+use clap::ValueEnum;
 use kdam::term::Colorizer;
 use requestty::prompt::style::Stylize;
 use reqwest::header::HeaderValue;
 use std::{fmt::Display, io::Write, path::PathBuf};
+use vsd_mp4::pssh::Pssh;
 
 pub(crate) struct MasterPlaylist {
+    /// Duration in seconds, if known before fetching media playlists.
+    pub(crate) duration: Option<f32>,
     #[allow(dead_code)]
     pub(crate) playlist_type: PlaylistType,
     #[allow(dead_code)]
@@ -23,55 +30,80 @@ pub(crate) struct MasterPlaylist {
 }
 
 impl MasterPlaylist {
+    /// Sort streams from best to worst. Languages are in order of preference.
     pub(crate) fn sort_streams(
         mut self,
-        prefer_audio_lang: Option<String>,
-        prefer_subs_lang: Option<String>,
+        audio_channels: &AudioChannels,
+        prefer_audio_codec: &[AudioCodec],
+        prefer_audio_langs: &[String],
+        prefer_codec: &[VideoCodec],
+        prefer_subs_langs: &[String],
+        video_range: &[VideoRange],
     ) -> Self {
-        let prefer_audio_lang = prefer_audio_lang.map(|x| x.to_lowercase());
-        let prefer_subs_lang = prefer_subs_lang.map(|x| x.to_lowercase());
-
         let mut video_streams = vec![];
         let mut audio_streams = vec![];
         let mut subtitle_streams = vec![];
+        let mut thumbnail_streams = vec![];
         let mut undefined_streams = vec![];
 
         for stream in self.streams {
             match stream.media_type {
                 MediaType::Audio => {
-                    let mut language_factor = 0;
-
-                    if let Some(playlist_lang) = &stream.language.as_ref().map(|x| x.to_lowercase())
-                    {
-                        if let Some(prefer_lang) = &prefer_audio_lang {
-                            if playlist_lang == prefer_lang {
-                                language_factor = 2;
-                            } else if playlist_lang.get(0..2) == prefer_lang.get(0..2) {
-                                language_factor = 1;
-                            }
-                        }
-                    }
-
-                    let channels = stream.channels.unwrap_or(0.0);
+                    let language_factor = languages_factor(&stream, prefer_audio_langs);
+
+                    let role_factor =
+                        if stream.has_role("description") || stream.has_role("commentary") {
+                            0
+                        } else if stream.has_role("main") {
+                            2
+                        } else {
+                            1
+                        };
+
+                    let codec_factor = prefer_audio_codec
+                        .iter()
+                        .position(|x| {
+                            (*x == AudioCodec::Atmos && stream.object_audio)
+                                || stream.audio_codec() == Some(*x)
+                        })
+                        .map(|x| prefer_audio_codec.len() - x)
+                        .unwrap_or(0);
+                    let channels_factor = audio_channels.factor(stream.channels.unwrap_or(0.0));
                     let bandwidth = stream.bandwidth.unwrap_or(0);
 
-                    audio_streams.push((stream, language_factor, channels, bandwidth));
+                    audio_streams.push((
+                        stream,
+                        language_factor,
+                        role_factor,
+                        codec_factor,
+                        channels_factor,
+                        bandwidth,
+                    ));
                 }
                 MediaType::Subtitles => {
-                    let mut language_factor = 0;
-
-                    if let Some(playlist_lang) = &stream.language.as_ref().map(|x| x.to_lowercase())
-                    {
-                        if let Some(prefer_lang) = &prefer_subs_lang {
-                            if playlist_lang == prefer_lang {
-                                language_factor = 2;
-                            } else if playlist_lang.get(0..2) == prefer_lang.get(0..2) {
-                                language_factor = 1;
-                            }
-                        }
-                    }
+                    let language_factor = languages_factor(&stream, prefer_subs_langs);
+
+                    let role_factor =
+                        if stream.has_role("forced-subtitle") || stream.has_role("commentary") {
+                            0
+                        } else if stream.has_role("main") || stream.has_role("subtitle") {
+                            2
+                        } else {
+                            1
+                        };
+
+                    subtitle_streams.push((stream, language_factor, role_factor));
+                }
+                MediaType::Thumbnails => {
+                    let pixels = if let Some((w, h)) = &stream.resolution {
+                        w * h
+                    } else {
+                        0
+                    };
 
-                    subtitle_streams.push((stream, language_factor));
+                    let bandwidth = stream.bandwidth.unwrap_or(0);
+
+                    thumbnail_streams.push((stream, pixels, bandwidth));
                 }
                 MediaType::Undefined => undefined_streams.push(stream),
                 MediaType::Video => {
@@ -82,31 +114,44 @@ impl MasterPlaylist {
                     };
 
                     let bandwidth = stream.bandwidth.unwrap_or(0);
-
-                    video_streams.push((stream, pixels, bandwidth));
+                    // Earlier preferences get higher factor, streams not matching any preference get 0.
+                    let range_factor = stream
+                        .video_range
+                        .as_ref()
+                        .and_then(|x| video_range.iter().position(|y| y == x))
+                        .map(|x| video_range.len() - x)
+                        .unwrap_or(0);
+                    let codec_factor = stream
+                        .video_codec()
+                        .and_then(|x| prefer_codec.iter().position(|y| *y == x))
+                        .map(|x| prefer_codec.len() - x)
+                        .unwrap_or(0);
+
+                    video_streams.push((stream, pixels, range_factor, codec_factor, bandwidth));
                 }
             }
         }
 
-        video_streams.sort_by(|x, y| y.2.cmp(&x.2));
-        video_streams.sort_by(|x, y| y.1.cmp(&x.1));
-        audio_streams.sort_by(|x, y| y.3.cmp(&x.3));
-        audio_streams.sort_by(|x, y| y.2.total_cmp(&x.2));
-        audio_streams.sort_by(|x, y| y.1.cmp(&x.1));
+        video_streams.sort_by_key(|x| std::cmp::Reverse((x.1, x.2, x.3, x.4)));
+        audio_streams.sort_by_key(|x| std::cmp::Reverse((x.1, x.2, x.3, x.4, x.5)));
+        subtitle_streams.sort_by(|x, y| y.2.cmp(&x.2));
         subtitle_streams.sort_by(|x, y| y.1.cmp(&x.1));
+        thumbnail_streams.sort_by(|x, y| y.2.cmp(&x.2));
+        thumbnail_streams.sort_by(|x, y| y.1.cmp(&x.1));
 
         self.streams = video_streams
             .into_iter()
             .map(|x| x.0)
             .chain(audio_streams.into_iter().map(|x| x.0))
             .chain(subtitle_streams.into_iter().map(|x| x.0))
+            .chain(thumbnail_streams.into_iter().map(|x| x.0))
             .chain(undefined_streams)
             .collect::<Vec<_>>();
 
         self
     }
 
-    fn select_video_stream(&self, quality: &Quality) -> Option<usize> {
+    fn select_video_stream(&self, quality: &Quality, fallback: &QualityFallback) -> Option<usize> {
         let video_streams = self
             .streams
             .iter()
@@ -132,14 +177,17 @@ impl MasterPlaylist {
             Quality::Youtube8k => (7680, 4320),
         };
 
-        for (i, stream) in video_streams {
+        for (i, stream) in video_streams.clone() {
             if has_resolution.is_some() && has_height.is_some() {
                 break;
             }
 
             if let Some((video_w, video_h)) = &stream.resolution {
                 if h == *video_h {
-                    has_height = Some(i);
+                    // Streams are sorted, so the first one with matching height is preferred.
+                    if has_height.is_none() {
+                        has_height = Some(i);
+                    }
 
                     if w == *video_w {
                         has_resolution = Some(i);
@@ -148,53 +196,196 @@ impl MasterPlaylist {
             }
         }
 
-        has_resolution.or(has_height)
+        if has_resolution.is_some() || has_height.is_some() {
+            return has_resolution.or(has_height);
+        }
+
+        // Streams are sorted, so the first one among streams of same height is preferred.
+        let heights = video_streams.filter_map(|(i, x)| x.resolution.map(|(_, y)| (i, y)));
+        let fallback_index = match fallback {
+            QualityFallback::Fail => None,
+            QualityFallback::Higher => heights.filter(|x| x.1 > h).min_by_key(|x| x.1 - h),
+            QualityFallback::Lower => heights.filter(|x| x.1 < h).min_by_key(|x| h - x.1),
+            // First stream is used when resolutions are unknown.
+            QualityFallback::Nearest => heights
+                .min_by_key(|x| (x.1.abs_diff(h), x.1 > h))
+                .or(Some((0, 0))),
+        }
+        .map(|x| x.0);
+
+        if let Some(stream) = fallback_index.and_then(|x| {
+            self.streams
+                .iter()
+                .filter(|x| x.media_type == MediaType::Video)
+                .nth(x)
+        }) {
+            println!(
+                "    {} {} video stream is not available, falling back to {}",
+                "Warning".colorize("bold yellow"),
+                if w == 0 {
+                    format!("{}p", h)
+                } else {
+                    format!("{}x{}", w, h)
+                },
+                stream.display_stream()
+            );
+        }
+
+        fallback_index
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn select_streams(
         self,
         quality: Quality,
+        quality_fallback: QualityFallback,
+        max_bitrate: Option<u64>,
+        min_bitrate: Option<u64>,
+        audio_langs: &[String],
+        subs_langs: &[String],
+        exclude_roles: &[String],
+        require_langs: bool,
+        select_audio: Option<&StreamFilter>,
+        select_ids: &[String],
+        select_subs: Option<&StreamFilter>,
+        select_video: Option<&StreamFilter>,
         skip_prompts: bool,
         raw_prompts: bool,
-    ) -> Result<(Vec<MediaPlaylist>, Vec<MediaPlaylist>)> {
-        let default_video_stream_index = self.select_video_stream(&quality);
+    ) -> Result<(Vec<MediaPlaylist>, Vec<MediaPlaylist>, Vec<MediaPlaylist>)> {
+        if !select_ids.is_empty() {
+            return self.select_ids(select_ids);
+        }
+
+        // --select-video filter takes precedence over --quality.
+        let default_video_stream_index = if select_video.is_some() {
+            Some(0)
+        } else {
+            self.select_video_stream(&quality, &quality_fallback)
+        };
 
         if let Some(default_video_stream_index) = default_video_stream_index {
             let mut video_streams = vec![];
             let mut audio_streams = vec![];
             let mut subtitle_streams = vec![];
-            // TODO - Add support for downloading undefined streams
+            let mut thumbnail_streams = vec![];
             let mut undefined_streams = vec![];
 
             for stream in self.streams {
                 match stream.media_type {
                     MediaType::Audio => audio_streams.push(stream),
                     MediaType::Subtitles => subtitle_streams.push(stream),
+                    MediaType::Thumbnails => thumbnail_streams.push(stream),
                     MediaType::Undefined => undefined_streams.push(stream),
                     MediaType::Video => video_streams.push(stream),
                 }
             }
 
+            let mut default_video_streams_index = match select_video {
+                Some(filter) => select_with_filter(filter, &video_streams, &MediaType::Video),
+                None => vec![default_video_stream_index],
+            };
+
+            // Prefer alternative renditions from groups referenced by pre-selected video stream.
+            let video_groups = |video_index: Option<&usize>| {
+                video_index
+                    .and_then(|x| video_streams.get(*x))
+                    .map(|x| x.groups.clone())
+                    .unwrap_or_default()
+            };
+            // Streams with excluded roles are skipped only during automatic selection.
+            let excluded = |x: &MediaPlaylist| exclude_roles.iter().any(|y| x.has_role(y));
+            let default_audio_index = |groups: &Groups| -> Result<Vec<usize>> {
+                Ok(match select_audio {
+                    Some(filter) => select_with_filter(filter, &audio_streams, &MediaType::Audio),
+                    None if !audio_langs.is_empty() => select_with_langs(
+                        audio_langs,
+                        &audio_streams,
+                        &MediaType::Audio,
+                        require_langs,
+                    )?
+                    .into_iter()
+                    .filter(|x| !excluded(&audio_streams[*x]))
+                    .collect(),
+                    None => audio_streams
+                        .iter()
+                        .position(|x| {
+                            !excluded(x)
+                                && x.group_id
+                                    .as_ref()
+                                    .map(|y| groups.contains(&MediaType::Audio, y))
+                                    .unwrap_or(false)
+                        })
+                        .or_else(|| audio_streams.iter().position(|x| !excluded(x)))
+                        .into_iter()
+                        .collect(),
+                })
+            };
+            let mut default_audio_streams_index =
+                default_audio_index(&video_groups(default_video_streams_index.first()))?;
+
+            if select_video.is_none() && (max_bitrate.is_some() || min_bitrate.is_some()) {
+                (default_video_streams_index, default_audio_streams_index) = select_with_bitrate(
+                    &video_streams,
+                    &audio_streams,
+                    default_video_stream_index,
+                    |x| default_audio_index(&video_groups(x)),
+                    excluded,
+                    max_bitrate,
+                    min_bitrate,
+                )?;
+            }
+
+            let groups = video_groups(default_video_streams_index.first());
+            let default_subtitle_streams_index = match select_subs {
+                Some(filter) => {
+                    select_with_filter(filter, &subtitle_streams, &MediaType::Subtitles)
+                }
+                None if !subs_langs.is_empty() => select_with_langs(
+                    subs_langs,
+                    &subtitle_streams,
+                    &MediaType::Subtitles,
+                    require_langs,
+                )?
+                .into_iter()
+                .filter(|x| !excluded(&subtitle_streams[*x]))
+                .collect(),
+                None => subtitle_streams
+                    .iter()
+                    .position(|x| {
+                        !excluded(x)
+                            && x.group_id
+                                .as_ref()
+                                .map(|y| groups.contains(&MediaType::Subtitles, y))
+                                .unwrap_or(false)
+                    })
+                    .or_else(|| subtitle_streams.iter().position(|x| !excluded(x)))
+                    .into_iter()
+                    .collect(),
+            };
+
             let mut choices_with_default = vec![];
-            let mut choices_with_default_ranges: [std::ops::Range<usize>; 4] =
-                [(0..0), (0..0), (0..0), (0..0)];
+            let mut choices_with_default_ranges: [std::ops::Range<usize>; 5] =
+                [(0..0), (0..0), (0..0), (0..0), (0..0)];
 
             choices_with_default.push(requestty::Separator(
                 "─────── Video Streams ────────".to_owned(),
             ));
             choices_with_default.extend(video_streams.iter().enumerate().map(|(i, x)| {
-                requestty::Choice((x.display_video_stream(), i == default_video_stream_index))
+                requestty::Choice((
+                    x.display_video_stream(),
+                    default_video_streams_index.contains(&i),
+                ))
             }));
             choices_with_default_ranges[0] = 1..choices_with_default.len();
             choices_with_default.push(requestty::Separator(
                 "─────── Audio Streams ────────".to_owned(),
             ));
-            choices_with_default.extend(
-                audio_streams
-                    .iter()
-                    .enumerate()
-                    .map(|(i, x)| requestty::Choice((x.display_audio_stream(), i == 0))),
-            );
+            choices_with_default.extend(audio_streams.iter().enumerate().map(|(i, x)| {
+                requestty::Choice((
+                    x.display_audio_stream(),
+                    default_audio_streams_index.contains(&i),
+                ))
+            }));
 
             if skip_prompts || raw_prompts {
                 choices_with_default_ranges[1] =
@@ -207,12 +398,12 @@ impl MasterPlaylist {
             choices_with_default.push(requestty::Separator(
                 "────── Subtitle Streams ──────".to_owned(),
             ));
-            choices_with_default.extend(
-                subtitle_streams
-                    .iter()
-                    .enumerate()
-                    .map(|(i, x)| requestty::Choice((x.display_subtitle_stream(), i == 0))),
-            );
+            choices_with_default.extend(subtitle_streams.iter().enumerate().map(|(i, x)| {
+                requestty::Choice((
+                    x.display_subtitle_stream(),
+                    default_subtitle_streams_index.contains(&i),
+                ))
+            }));
 
             if skip_prompts || raw_prompts {
                 choices_with_default_ranges[2] =
@@ -222,6 +413,40 @@ impl MasterPlaylist {
                     (choices_with_default_ranges[1].end + 1)..choices_with_default.len();
             }
 
+            choices_with_default.push(requestty::Separator(
+                "───── Thumbnail Streams ──────".to_owned(),
+            ));
+            choices_with_default.extend(
+                thumbnail_streams
+                    .iter()
+                    .map(|x| requestty::Choice((x.display_thumbnail_stream(), false))),
+            );
+
+            if skip_prompts || raw_prompts {
+                choices_with_default_ranges[3] =
+                    choices_with_default_ranges[2].end..(choices_with_default.len() - 3);
+            } else {
+                choices_with_default_ranges[3] =
+                    (choices_with_default_ranges[2].end + 1)..choices_with_default.len();
+            }
+
+            choices_with_default.push(requestty::Separator(
+                "─────── Other Streams ────────".to_owned(),
+            ));
+            choices_with_default.extend(
+                undefined_streams
+                    .iter()
+                    .map(|x| requestty::Choice((x.display_undefined_stream(), false))),
+            );
+
+            if skip_prompts || raw_prompts {
+                choices_with_default_ranges[4] =
+                    choices_with_default_ranges[3].end..(choices_with_default.len() - 4);
+            } else {
+                choices_with_default_ranges[4] =
+                    (choices_with_default_ranges[3].end + 1)..choices_with_default.len();
+            }
+
             // println!("{:?}", choices_with_default_ranges);
 
             if skip_prompts || raw_prompts {
@@ -274,9 +499,13 @@ impl MasterPlaylist {
 
                 let mut selected_streams = vec![];
                 let mut selected_subtitle_streams = vec![];
+                let mut selected_thumbnail_streams = vec![];
                 let mut video_streams_offset = 1;
                 let mut audio_streams_offset = video_streams_offset + video_streams.len();
                 let mut subtitle_streams_offset = audio_streams_offset + audio_streams.len();
+                let mut thumbnail_streams_offset = subtitle_streams_offset + subtitle_streams.len();
+                let mut undefined_streams_offset =
+                    thumbnail_streams_offset + thumbnail_streams.len();
 
                 for i in selected_choices_index {
                     if choices_with_default_ranges[0].contains(&i) {
@@ -306,10 +535,32 @@ impl MasterPlaylist {
                         );
                         selected_subtitle_streams.push(stream);
                         subtitle_streams_offset += 1;
+                    } else if choices_with_default_ranges[3].contains(&i) {
+                        let stream = thumbnail_streams.remove(i - thumbnail_streams_offset);
+                        println!(
+                            "   {} {}",
+                            "Selected".colorize("bold green"),
+                            stream.display_stream()
+                        );
+                        selected_thumbnail_streams.push(stream);
+                        thumbnail_streams_offset += 1;
+                    } else if choices_with_default_ranges[4].contains(&i) {
+                        let stream = undefined_streams.remove(i - undefined_streams_offset);
+                        println!(
+                            "   {} {}",
+                            "Selected".colorize("bold green"),
+                            stream.display_stream()
+                        );
+                        selected_streams.push(stream);
+                        undefined_streams_offset += 1;
                     }
                 }
 
-                Ok((selected_streams, selected_subtitle_streams))
+                Ok((
+                    selected_streams,
+                    selected_subtitle_streams,
+                    selected_thumbnail_streams,
+                ))
             } else {
                 let question = requestty::Question::multi_select("streams")
                     .should_loop(false)
@@ -331,9 +582,14 @@ impl MasterPlaylist {
 
                 let mut selected_streams = vec![];
                 let mut selected_subtitle_streams = vec![];
+                let mut selected_thumbnail_streams = vec![];
                 let mut video_streams_offset = 1;
                 let mut audio_streams_offset = video_streams_offset + video_streams.len() + 1;
                 let mut subtitle_streams_offset = audio_streams_offset + audio_streams.len() + 1;
+                let mut thumbnail_streams_offset =
+                    subtitle_streams_offset + subtitle_streams.len() + 1;
+                let mut undefined_streams_offset =
+                    thumbnail_streams_offset + thumbnail_streams.len() + 1;
 
                 for selected_item in answer.as_list_items().unwrap() {
                     if choices_with_default_ranges[0].contains(&selected_item.index) {
@@ -349,32 +605,281 @@ impl MasterPlaylist {
                             subtitle_streams.remove(selected_item.index - subtitle_streams_offset),
                         );
                         subtitle_streams_offset += 1;
+                    } else if choices_with_default_ranges[3].contains(&selected_item.index) {
+                        selected_thumbnail_streams.push(
+                            thumbnail_streams
+                                .remove(selected_item.index - thumbnail_streams_offset),
+                        );
+                        thumbnail_streams_offset += 1;
+                    } else if choices_with_default_ranges[4].contains(&selected_item.index) {
+                        selected_streams.push(
+                            undefined_streams
+                                .remove(selected_item.index - undefined_streams_offset),
+                        );
+                        undefined_streams_offset += 1;
                     }
                 }
 
-                Ok((selected_streams, selected_subtitle_streams))
+                Ok((
+                    selected_streams,
+                    selected_subtitle_streams,
+                    selected_thumbnail_streams,
+                ))
             }
         } else {
             bail!("playlist doesn't contain pre-selected video quality stream.")
         }
     }
+
+    /// Select streams by their ids without prompting.
+    fn select_ids(
+        mut self,
+        ids: &[String],
+    ) -> Result<(Vec<MediaPlaylist>, Vec<MediaPlaylist>, Vec<MediaPlaylist>)> {
+        for id in ids {
+            if !self.streams.iter().any(|x| x.id == *id) {
+                bail!(
+                    "playlist doesn't contain stream with id {}. Use {} flag to list ids of all streams.",
+                    id,
+                    "--list-streams".colorize("bold green")
+                );
+            }
+        }
+
+        self.streams.retain(|x| ids.contains(&x.id));
+
+        for stream in &self.streams {
+            println!(
+                "   {} {}",
+                "Selected".colorize("bold green"),
+                stream.display_stream()
+            );
+        }
+
+        Ok(self.all_streams())
+    }
+
+    /// Split all streams without selecting them, same as `select_streams`.
+    pub(crate) fn all_streams(
+        self,
+    ) -> (Vec<MediaPlaylist>, Vec<MediaPlaylist>, Vec<MediaPlaylist>) {
+        let mut streams = vec![];
+        let mut subtitle_streams = vec![];
+        let mut thumbnail_streams = vec![];
+
+        for stream in self.streams {
+            match stream.media_type {
+                MediaType::Subtitles => subtitle_streams.push(stream),
+                MediaType::Thumbnails => thumbnail_streams.push(stream),
+                _ => streams.push(stream),
+            }
+        }
+
+        (streams, subtitle_streams, thumbnail_streams)
+    }
+}
+
+fn select_with_filter(
+    filter: &StreamFilter,
+    streams: &[MediaPlaylist],
+    media_type: &MediaType,
+) -> Vec<usize> {
+    let selected = filter.select(streams);
+
+    if selected.is_empty() && !streams.is_empty() {
+        println!(
+            "    {} no {} stream matches the selection filter",
+            "Warning".colorize("bold yellow"),
+            media_type
+        );
+    }
+
+    selected
+}
+
+/// Best pair of video and audio streams whose combined bitrate is within limits.
+/// Lower quality video streams are tried first and then higher ones,
+/// along with other audio streams of same language if a single audio stream is pre-selected.
+fn select_with_bitrate(
+    video_streams: &[MediaPlaylist],
+    audio_streams: &[MediaPlaylist],
+    video_index: usize,
+    default_audio_index: impl Fn(Option<&usize>) -> Result<Vec<usize>>,
+    excluded: impl Fn(&MediaPlaylist) -> bool,
+    max_bitrate: Option<u64>,
+    min_bitrate: Option<u64>,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    let mut video_indexes = (video_index..video_streams.len())
+        .chain((0..video_index.min(video_streams.len())).rev())
+        .map(Some)
+        .collect::<Vec<_>>();
+
+    if video_indexes.is_empty() {
+        video_indexes.push(None);
+    }
+
+    for video_index in video_indexes {
+        let audio_index = default_audio_index(video_index.as_ref())?;
+        let mut audio_indexes = vec![audio_index.clone()];
+
+        if let [i] = audio_index[..] {
+            if let Some(audio_stream) = audio_streams.get(i) {
+                let mut same_language = (0..audio_streams.len())
+                    .filter(|x| {
+                        *x != i
+                            && audio_streams[*x].language == audio_stream.language
+                            && !excluded(&audio_streams[*x])
+                    })
+                    .collect::<Vec<_>>();
+                same_language.sort_by_key(|x| std::cmp::Reverse(audio_streams[*x].bandwidth));
+                audio_indexes.extend(same_language.into_iter().map(|x| vec![x]));
+            }
+        }
+
+        for audio_index in audio_indexes {
+            let bitrate = combined_bandwidth(
+                video_index.and_then(|x| video_streams.get(x)),
+                &audio_index
+                    .iter()
+                    .filter_map(|x| audio_streams.get(*x))
+                    .collect::<Vec<_>>(),
+            );
+
+            if !matches!(max_bitrate, Some(x) if bitrate > x)
+                && !matches!(min_bitrate, Some(x) if bitrate < x)
+            {
+                return Ok((video_index.into_iter().collect(), audio_index));
+            }
+        }
+    }
+
+    bail!("playlist doesn't contain video and audio streams within bitrate limits.")
+}
+
+/// Combined bandwidth of a video stream and audio streams played along with it.
+/// Bandwidth of HLS variant streams already includes their alternative audio renditions.
+pub(crate) fn combined_bandwidth(video: Option<&MediaPlaylist>, audio: &[&MediaPlaylist]) -> u64 {
+    let video_bandwidth = video.and_then(|x| x.bandwidth).unwrap_or(0);
+
+    if video.is_some_and(|x| x.is_hls() && x.groups.audio.is_some()) {
+        return video_bandwidth;
+    }
+
+    video_bandwidth + audio.iter().filter_map(|x| x.bandwidth).sum::<u64>()
+}
+
+/// Best matching stream for each language, `all` selects every stream.
+fn select_with_langs(
+    langs: &[String],
+    streams: &[MediaPlaylist],
+    media_type: &MediaType,
+    require_langs: bool,
+) -> Result<Vec<usize>> {
+    if langs.iter().any(|x| x.eq_ignore_ascii_case("all")) {
+        return Ok((0..streams.len()).collect());
+    }
+
+    let mut selected = vec![];
+    let mut missing = vec![];
+
+    for lang in langs {
+        // Streams are sorted, so the first one with highest factor is the best match.
+        let index = streams
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i, language_factor(x.language.as_deref(), lang)))
+            .filter(|(_, x)| *x > 0)
+            .max_by(|x, y| x.1.cmp(&y.1).then(y.0.cmp(&x.0)))
+            .map(|(i, _)| i);
+
+        match index {
+            Some(i) if !selected.contains(&i) => selected.push(i),
+            Some(_) => (),
+            None => missing.push(lang.as_str()),
+        }
+    }
+
+    if !missing.is_empty() {
+        if require_langs {
+            bail!(
+                "playlist doesn't contain {} streams of languages: {}",
+                media_type,
+                missing.join(", ")
+            );
+        }
+
+        println!(
+            "    {} no {} stream found for languages: {}",
+            "Warning".colorize("bold yellow"),
+            media_type,
+            missing.join(", ")
+        );
+    }
+
+    Ok(selected)
+}
+
+/// 2 for exact match of language tags, 1 if two letter language codes are same and 0 otherwise.
+fn language_factor(language: Option<&str>, prefer_lang: &str) -> usize {
+    let language = match language {
+        Some(x) => x.to_lowercase(),
+        None => return 0,
+    };
+    let prefer_lang = prefer_lang.to_lowercase();
+
+    if language == prefer_lang {
+        2
+    } else if language.get(0..2) == prefer_lang.get(0..2) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Language factor of stream against multiple languages, earlier languages have higher factors.
+fn languages_factor(stream: &MediaPlaylist, prefer_langs: &[String]) -> usize {
+    prefer_langs
+        .iter()
+        .enumerate()
+        .map(
+            |(i, x)| match language_factor(stream.language.as_deref(), x) {
+                0 => 0,
+                y => 2 * (prefer_langs.len() - i - 1) + y,
+            },
+        )
+        .max()
+        .unwrap_or(0)
 }
 
 #[derive(Default)]
 pub(crate) struct MediaPlaylist {
+    pub(crate) accessibility: Vec<String>,
     pub(crate) bandwidth: Option<u64>,
     pub(crate) channels: Option<f32>,
     pub(crate) codecs: Option<String>,
     pub(crate) extension: Option<String>,
     pub(crate) frame_rate: Option<f32>,
+    /// Group id of an alternative rendition.
+    pub(crate) group_id: Option<String>,
+    /// Group ids of alternative renditions referenced by a variant.
+    pub(crate) groups: Groups,
     pub(crate) i_frame: bool,
+    /// Identifier which stays same across reloads of manifest and sort orders.
+    pub(crate) id: String,
+    pub(crate) label: Option<String>,
     pub(crate) language: Option<String>,
     pub(crate) live: bool,
     pub(crate) media_type: MediaType,
+    /// Object based (dolby atmos) audio i.e. E-AC-3 JOC.
+    pub(crate) object_audio: bool,
     pub(crate) playlist_type: PlaylistType,
     pub(crate) resolution: Option<(u64, u64)>,
+    pub(crate) roles: Vec<String>,
     pub(crate) segments: Vec<Segment>,
+    pub(crate) tiles: Option<Tiles>,
     pub(crate) uri: String,
+    /// Dynamic range of video streams, absence of signalling implies sdr.
+    pub(crate) video_range: Option<VideoRange>,
 }
 
 impl MediaPlaylist {
@@ -396,6 +901,51 @@ impl MediaPlaylist {
         None
     }
 
+    /// Family of first audio codec from `codecs`.
+    pub(crate) fn audio_codec(&self) -> Option<AudioCodec> {
+        self.codecs
+            .as_ref()?
+            .split(',')
+            .find_map(AudioCodec::from_codec)
+    }
+
+    /// Family of first video codec from `codecs`.
+    pub(crate) fn video_codec(&self) -> Option<VideoCodec> {
+        self.codecs
+            .as_ref()?
+            .split(',')
+            .find_map(VideoCodec::from_codec)
+    }
+
+    pub(crate) fn has_role(&self, role: &str) -> bool {
+        self.roles
+            .iter()
+            .chain(self.accessibility.iter())
+            .any(|x| x == role)
+    }
+
+    pub(crate) fn dispositions(&self) -> Vec<&'static str> {
+        let mut dispositions = vec![];
+
+        for role in self.roles.iter().chain(self.accessibility.iter()) {
+            let disposition = match role.as_str() {
+                "caption" => "hearing_impaired",
+                "commentary" => "comment",
+                "description" => "visual_impaired",
+                "dub" => "dub",
+                "forced-subtitle" => "forced",
+                "karaoke" => "karaoke",
+                _ => continue,
+            };
+
+            if !dispositions.contains(&disposition) {
+                dispositions.push(disposition);
+            }
+        }
+
+        dispositions
+    }
+
     pub(crate) fn extension(&self) -> String {
         if let Some(ext) = &self.extension {
             return ext.to_owned();
@@ -404,6 +954,7 @@ impl MediaPlaylist {
         let mut ext = match &self.playlist_type {
             PlaylistType::Hls => "ts",
             PlaylistType::Dash => "m4s",
+            PlaylistType::Smooth => "mp4",
         };
 
         if let Some(segment) = self.segments.get(0) {
@@ -421,7 +972,7 @@ impl MediaPlaylist {
         ext.to_owned()
     }
 
-    pub(crate) fn file_path(&self, directory: &Option<PathBuf>, ext: &str) -> PathBuf {
+    fn file_stem(&self) -> String {
         let mut filename = self
             .uri
             .split('?')
@@ -447,16 +998,17 @@ impl MediaPlaylist {
         let prefix = match &self.media_type {
             MediaType::Audio => "vsd_audio",
             MediaType::Subtitles => "vsd_subtitles",
+            MediaType::Thumbnails => "vsd_thumbnails",
             MediaType::Undefined => "vsd_undefined",
             MediaType::Video => "vsd_video",
         };
 
-        let mut path = PathBuf::from(format!(
-            "{}_{}.{}",
-            prefix,
-            filename.to_string_lossy(),
-            ext
-        ));
+        format!("{}_{}", prefix, filename.to_string_lossy())
+    }
+
+    pub(crate) fn file_path(&self, directory: &Option<PathBuf>, ext: &str) -> PathBuf {
+        let stem = self.file_stem();
+        let mut path = PathBuf::from(format!("{}.{}", stem, ext));
 
         if let Some(directory) = directory {
             path = directory.join(path);
@@ -464,13 +1016,7 @@ impl MediaPlaylist {
 
         if path.exists() {
             for i in 1.. {
-                path.set_file_name(format!(
-                    "{}_{}_({}).{}",
-                    prefix,
-                    filename.to_string_lossy(),
-                    i,
-                    ext
-                ));
+                path.set_file_name(format!("{}_({}).{}", stem, i, ext));
 
                 if !path.exists() {
                     return path;
@@ -481,11 +1027,23 @@ impl MediaPlaylist {
         path
     }
 
+    /// Path of partially downloaded file, this is same across runs so that downloads can be resumed.
+    pub(crate) fn part_file_path(&self, directory: &Option<PathBuf>, ext: &str) -> PathBuf {
+        let path = PathBuf::from(format!("{}.{}.part", self.file_stem(), ext));
+
+        if let Some(directory) = directory {
+            directory.join(path)
+        } else {
+            path
+        }
+    }
+
     pub(crate) fn display_stream(&self) -> String {
         match self.media_type {
             MediaType::Audio => self.display_audio_stream(),
             MediaType::Subtitles => self.display_subtitle_stream(),
-            MediaType::Undefined => "".to_owned(),
+            MediaType::Thumbnails => self.display_thumbnail_stream(),
+            MediaType::Undefined => self.display_undefined_stream(),
             MediaType::Video => self.display_video_stream(),
         }
         .split_whitespace()
@@ -527,6 +1085,12 @@ impl MediaPlaylist {
             extra += &format!(", frame_rate: {}", frame_rate);
         }
 
+        if let Some(video_range) = &self.video_range {
+            if *video_range != VideoRange::Sdr {
+                extra += &format!(", {}", video_range);
+            }
+        }
+
         if self.i_frame {
             extra += ", iframe";
         }
@@ -535,7 +1099,7 @@ impl MediaPlaylist {
             extra += ", live";
         }
 
-        extra += ")";
+        extra += &format!(", id: {})", self.id);
 
         format!(
             "{:9} {:>7} {}/s {}",
@@ -543,12 +1107,39 @@ impl MediaPlaylist {
         )
     }
 
+    fn display_roles(&self) -> String {
+        let mut extra = String::new();
+
+        if let Some(group_id) = &self.group_id {
+            extra += &format!(", group: {}", group_id);
+        }
+
+        if let Some(label) = &self.label {
+            extra += &format!(", label: {}", label);
+        }
+
+        let roles = self
+            .roles
+            .iter()
+            .chain(self.accessibility.iter())
+            .map(|x| x.as_str())
+            .collect::<Vec<_>>();
+
+        if !roles.is_empty() {
+            extra += &format!(", role: {}", roles.join("/"));
+        }
+
+        extra
+    }
+
     fn display_audio_stream(&self) -> String {
         let mut extra = format!(
             "language: {}",
             self.language.as_ref().unwrap_or(&"?".to_owned())
         );
 
+        extra += &self.display_roles();
+
         if let Some(codecs) = &self.codecs {
             extra += &format!(", codecs: {}", codecs);
         }
@@ -564,10 +1155,15 @@ impl MediaPlaylist {
             extra += &format!(", channels: {}", channels);
         }
 
+        if self.object_audio {
+            extra += ", atmos";
+        }
+
         if self.live {
             extra += ", live";
         }
 
+        extra += &format!(", id: {}", self.id);
         extra
     }
 
@@ -577,12 +1173,103 @@ impl MediaPlaylist {
             self.language.as_ref().unwrap_or(&"?".to_owned())
         );
 
+        extra += &self.display_roles();
+
         if let Some(codecs) = &self.codecs {
             extra += &format!(", codecs: {}", codecs);
         }
 
+        extra += &format!(", id: {}", self.id);
         extra
     }
+
+    fn display_undefined_stream(&self) -> String {
+        let mut extra = format!(
+            "codecs: {}",
+            self.codecs.as_ref().unwrap_or(&"?".to_owned())
+        );
+
+        if let Some(extension) = &self.extension {
+            extra += &format!(", extension: {}", extension);
+        }
+
+        if let Some((w, h)) = self.resolution {
+            extra += &format!(", resolution: {}x{}", w, h);
+        }
+
+        if let Some(language) = &self.language {
+            extra += &format!(", language: {}", language);
+        }
+
+        extra += &self.display_roles();
+
+        if let Some(bandwidth) = self.bandwidth {
+            extra += &format!(
+                ", bandwidth: {}/s",
+                crate::utils::format_bytes(bandwidth as usize, 2).2
+            );
+        }
+
+        if self.live {
+            extra += ", live";
+        }
+
+        extra += &format!(", id: {}", self.id);
+        extra
+    }
+
+    pub(crate) fn display_thumbnail_stream(&self) -> String {
+        let resolution = if let Some((w, h)) = self.resolution {
+            format!("{}x{}", w, h)
+        } else {
+            "?".to_owned()
+        };
+
+        let mut extra = format!(
+            "(codecs: {}",
+            self.codecs.as_ref().unwrap_or(&"?".to_owned())
+        );
+
+        if let Some(tiles) = &self.tiles {
+            extra += &format!(", tiles: {}x{}", tiles.columns, tiles.rows);
+        }
+
+        if self.i_frame {
+            extra += ", iframe";
+        }
+
+        if self.live {
+            extra += ", live";
+        }
+
+        extra += &format!(", id: {})", self.id);
+
+        format!("{:9} {}", resolution, extra)
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Groups {
+    pub(crate) audio: Option<String>,
+    pub(crate) closed_captions: Option<String>,
+    pub(crate) subtitles: Option<String>,
+    pub(crate) video: Option<String>,
+}
+
+impl Groups {
+    /// Check whether an alternative rendition of given media type and group id is referenced.
+    pub(crate) fn contains(&self, media_type: &MediaType, group_id: &str) -> bool {
+        let group_id = Some(group_id);
+
+        match media_type {
+            MediaType::Audio => self.audio.as_deref() == group_id,
+            MediaType::Subtitles => {
+                self.subtitles.as_deref() == group_id || self.closed_captions.as_deref() == group_id
+            }
+            MediaType::Video => self.video.as_deref() == group_id,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -590,12 +1277,14 @@ pub(crate) enum PlaylistType {
     Dash,
     #[default]
     Hls,
+    Smooth,
 }
 
 #[derive(Clone, Default, PartialEq)]
 pub(crate) enum MediaType {
     Audio,
     Subtitles,
+    Thumbnails,
     #[default]
     Undefined,
     Video,
@@ -609,6 +1298,7 @@ impl Display for MediaType {
             match self {
                 Self::Audio => "audio",
                 Self::Subtitles => "subtitles",
+                Self::Thumbnails => "thumbnails",
                 Self::Undefined => "undefined",
                 Self::Video => "video",
             }
@@ -616,6 +1306,197 @@ impl Display for MediaType {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum AudioCodec {
+    #[value(alias = "mp4a")]
+    Aac,
+    #[value(alias = "ac-3")]
+    Ac3,
+    #[value(alias = "ac-4")]
+    Ac4,
+    Alac,
+    /// Object based audio (dolby atmos) of any codec.
+    #[value(alias = "joc")]
+    Atmos,
+    Dts,
+    #[value(alias = "ec-3", alias = "ddp")]
+    Eac3,
+    Flac,
+    Mp3,
+    Opus,
+    Vorbis,
+}
+
+impl AudioCodec {
+    /// Family of a single codec from `codecs`.
+    pub(crate) fn from_codec(codec: &str) -> Option<Self> {
+        let codec = codec.trim().to_lowercase();
+
+        Some(match codec.split('.').next()? {
+            "mp4a" if matches!(codec.as_str(), "mp4a.40.34" | "mp4a.69" | "mp4a.6b") => Self::Mp3,
+            "mp4a" => Self::Aac,
+            "ac-3" => Self::Ac3,
+            "ac-4" => Self::Ac4,
+            "alac" => Self::Alac,
+            "dtsc" | "dtse" | "dtsh" | "dtsl" | "dtsx" => Self::Dts,
+            "ec-3" | "ec+3" => Self::Eac3,
+            "flac" => Self::Flac,
+            "mp3" => Self::Mp3,
+            "opus" => Self::Opus,
+            "vorbis" => Self::Vorbis,
+            _ => return None,
+        })
+    }
+}
+
+/// Preferred channel count of audio streams.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AudioChannels {
+    Count(f32),
+    Max,
+}
+
+impl AudioChannels {
+    /// Higher factor for streams closer to preferred channel count.
+    /// Streams with fewer channels are preferred over streams with more channels.
+    fn factor(&self, channels: f32) -> (u8, i64) {
+        let channels_x10 = (channels * 10.0) as i64;
+
+        match self {
+            Self::Count(x) if channels == *x => (2, 0),
+            Self::Count(x) if channels < *x => (1, channels_x10),
+            Self::Count(_) => (0, -channels_x10),
+            Self::Max => (0, channels_x10),
+        }
+    }
+}
+
+impl std::str::FromStr for AudioChannels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "max" | "highest" => Ok(Self::Max),
+            "mono" => Ok(Self::Count(1.0)),
+            "stereo" => Ok(Self::Count(2.0)),
+            x => x.parse::<f32>().map(Self::Count).map_err(|_| {
+                format!(
+                    "could not parse {} as channel count, possible values: [2, 6, 8, max].",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum VideoCodec {
+    #[value(alias = "av01")]
+    Av1,
+    #[value(alias = "avc1", alias = "h264")]
+    Avc,
+    #[value(alias = "hvc1", alias = "hev1", alias = "h265")]
+    Hevc,
+    #[value(alias = "vp08")]
+    Vp8,
+    #[value(alias = "vp09")]
+    Vp9,
+}
+
+impl VideoCodec {
+    /// Family of a single codec from `codecs`, dolby vision codecs belongs to family of their base layer.
+    pub(crate) fn from_codec(codec: &str) -> Option<Self> {
+        Some(
+            match codec.trim().split('.').next()?.to_lowercase().as_str() {
+                "av01" | "dav1" => Self::Av1,
+                "avc1" | "avc3" | "dva1" | "dvav" => Self::Avc,
+                "dvh1" | "dvhe" | "hev1" | "hvc1" => Self::Hevc,
+                "vp08" => Self::Vp8,
+                "vp09" => Self::Vp9,
+                _ => return None,
+            },
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum VideoRange {
+    #[value(name = "dv", alias = "dolby-vision")]
+    DolbyVision,
+    #[value(alias = "pq")]
+    Hdr10,
+    Hlg,
+    Sdr,
+}
+
+impl VideoRange {
+    /// Dolby vision is detected from codecs (including hls `SUPPLEMENTAL-CODECS`).
+    pub(crate) fn from_codecs(codecs: &str) -> Option<Self> {
+        codecs
+            .split(',')
+            .filter_map(|x| x.trim().split('.').next())
+            .any(|x| matches!(x, "dav1" | "dva1" | "dvav" | "dvh1" | "dvhe"))
+            .then_some(Self::DolbyVision)
+    }
+
+    /// Video range from transfer characteristics code point of ITU-T H.273.
+    pub(crate) fn from_transfer_characteristics(value: &str) -> Option<Self> {
+        match value.trim() {
+            "1" | "6" | "13" | "14" | "15" => Some(Self::Sdr),
+            "16" => Some(Self::Hdr10),
+            "18" => Some(Self::Hlg),
+            _ => None,
+        }
+    }
+}
+
+impl Display for VideoRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::DolbyVision => "dv",
+                Self::Hdr10 => "hdr10",
+                Self::Hlg => "hlg",
+                Self::Sdr => "sdr",
+            }
+        )
+    }
+}
+
+/// Grid layout of thumbnails packed inside a single image (tile) segment.
+#[derive(Clone)]
+pub(crate) struct Tiles {
+    pub(crate) columns: u64,
+    /// Duration of each thumbnail inside the tile.
+    pub(crate) duration: Option<f32>,
+    /// Resolution of each thumbnail inside the tile.
+    pub(crate) resolution: Option<(u64, u64)>,
+    pub(crate) rows: u64,
+}
+
+impl Tiles {
+    /// Parse layout in `<columns>x<rows>` format.
+    pub(crate) fn from_layout(layout: &str) -> Option<Self> {
+        let (columns, rows) = layout.trim().split_once(['x', 'X'])?;
+
+        let columns = columns.parse().ok()?;
+        let rows = rows.parse().ok()?;
+
+        if columns == 0 || rows == 0 {
+            return None;
+        }
+
+        Some(Self {
+            columns,
+            duration: None,
+            resolution: None,
+            rows,
+        })
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub(crate) enum KeyMethod {
     Aes128,
@@ -658,10 +1539,10 @@ pub(crate) struct Key {
     pub(crate) iv: Option<String>,
     pub(crate) key_format: Option<String>,
     pub(crate) method: KeyMethod,
+    pub(crate) pssh: Option<Pssh>,
     pub(crate) uri: Option<String>,
 }
 
-
 #[derive(Clone, Default)]
 pub(crate) struct Segment {
     pub(crate) range: Option<Range>,
@@ -670,3 +1551,43 @@ pub(crate) struct Segment {
     pub(crate) map: Option<Map>,
     pub(crate) uri: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video_stream(resolution: (u64, u64), bandwidth: u64) -> MediaPlaylist {
+        MediaPlaylist {
+            bandwidth: Some(bandwidth),
+            media_type: MediaType::Video,
+            resolution: Some(resolution),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn select_best_stream_of_matching_height() {
+        // Sorted streams, none of them matches 1920x1080 exactly.
+        let master = MasterPlaylist {
+            duration: None,
+            playlist_type: PlaylistType::Hls,
+            uri: String::new(),
+            streams: vec![
+                video_stream((1440, 1080), 8_000_000),
+                video_stream((1440, 1080), 4_000_000),
+                video_stream((1280, 720), 2_000_000),
+            ],
+        }
+        .sort_streams(&AudioChannels::Max, &[], &[], &[], &[], &[]);
+
+        // Previously the last stream of matching height (index 1) was selected.
+        assert_eq!(
+            master.select_video_stream(&Quality::Youtube1080p, &QualityFallback::Fail),
+            Some(0)
+        );
+        assert_eq!(
+            master.select_video_stream(&Quality::Resolution(1280, 720), &QualityFallback::Fail),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,450 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://github.com/yt-dlp/yt-dlp/blob/2023.07.06/yt_dlp/downloader/ism.py
+    2. https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-sstr
+
+*/
+
+use super::manifest::{QualityLevel, SmoothStreamingMedia, StreamIndex};
+use anyhow::{bail, Result};
+
+// Smooth streaming fragments doesn't always use same track id, so fragments are
+// patched to use this track id instead.
+pub(crate) const TRACK_ID: u32 = 1;
+
+const PLAYREADY_SYSTEM_ID: [u8; 16] = [
+    0x9a, 0x04, 0xf0, 0x79, 0x98, 0x40, 0x42, 0x86, 0xab, 0x92, 0xe6, 0x5b, 0xe0, 0x88, 0x5f, 0x95,
+];
+const UNITY_MATRIX: [u32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x40000000];
+
+pub(super) struct Encryption {
+    pub(super) kid: Vec<u8>,
+    pub(super) playready_object: Vec<u8>,
+}
+
+/// Synthesize a fragmented mp4 initialization segment (`ftyp` + `moov`) for a quality level,
+/// because smooth streaming fragments only contain `moof` and `mdat` boxes.
+pub(super) fn init_segment(
+    manifest: &SmoothStreamingMedia,
+    stream_index: &StreamIndex,
+    quality_level: &QualityLevel,
+    encryption: Option<&Encryption>,
+) -> Result<Vec<u8>> {
+    let time_scale = manifest.time_scale(stream_index);
+    let duration =
+        manifest.duration.unwrap_or(0) * time_scale / manifest.time_scale.unwrap_or(10_000_000);
+    let width = quality_level
+        .max_width
+        .or(stream_index.max_width)
+        .unwrap_or(0);
+    let height = quality_level
+        .max_height
+        .or(stream_index.max_height)
+        .unwrap_or(0);
+    let four_cc = quality_level
+        .four_cc
+        .as_deref()
+        .unwrap_or("")
+        .to_uppercase();
+    let codec_private_data =
+        hex::decode(quality_level.codec_private_data.as_deref().unwrap_or("")).unwrap_or_default();
+
+    let mut ftyp = b"isml".to_vec(); // major brand
+    ftyp.extend_from_slice(&1_u32.to_be_bytes()); // minor version
+    ftyp.extend_from_slice(b"piffiso2iso6"); // compatible brands
+
+    let mut mvhd = vec![];
+    mvhd.extend_from_slice(&0_u64.to_be_bytes()); // creation time
+    mvhd.extend_from_slice(&0_u64.to_be_bytes()); // modification time
+    mvhd.extend_from_slice(&(time_scale as u32).to_be_bytes());
+    mvhd.extend_from_slice(&duration.to_be_bytes());
+    mvhd.extend_from_slice(&0x10000_u32.to_be_bytes()); // rate
+    mvhd.extend_from_slice(&0x100_u16.to_be_bytes()); // volume
+    mvhd.extend_from_slice(&[0; 10]); // reserved
+    unity_matrix(&mut mvhd);
+    mvhd.extend_from_slice(&[0; 24]); // pre defined
+    mvhd.extend_from_slice(&(TRACK_ID + 1).to_be_bytes()); // next track id
+
+    let mut moov = full_box(b"mvhd", 1, 0, &mvhd);
+
+    let mut tkhd = vec![];
+    tkhd.extend_from_slice(&0_u64.to_be_bytes()); // creation time
+    tkhd.extend_from_slice(&0_u64.to_be_bytes()); // modification time
+    tkhd.extend_from_slice(&TRACK_ID.to_be_bytes());
+    tkhd.extend_from_slice(&0_u32.to_be_bytes()); // reserved
+    tkhd.extend_from_slice(&duration.to_be_bytes());
+    tkhd.extend_from_slice(&[0; 8]); // reserved
+    tkhd.extend_from_slice(&0_u16.to_be_bytes()); // layer
+    tkhd.extend_from_slice(&0_u16.to_be_bytes()); // alternate group
+    tkhd.extend_from_slice(
+        &(if stream_index.r#type == "audio" {
+            0x100_u16
+        } else {
+            0
+        })
+        .to_be_bytes(),
+    ); // volume
+    tkhd.extend_from_slice(&0_u16.to_be_bytes()); // reserved
+    unity_matrix(&mut tkhd);
+    tkhd.extend_from_slice(&((width as u32) << 16).to_be_bytes());
+    tkhd.extend_from_slice(&((height as u32) << 16).to_be_bytes());
+
+    // track enabled | track in movie | track in preview
+    let mut trak = full_box(b"tkhd", 1, 0x7, &tkhd);
+
+    let mut mdhd = vec![];
+    mdhd.extend_from_slice(&0_u64.to_be_bytes()); // creation time
+    mdhd.extend_from_slice(&0_u64.to_be_bytes()); // modification time
+    mdhd.extend_from_slice(&(time_scale as u32).to_be_bytes());
+    mdhd.extend_from_slice(&duration.to_be_bytes());
+    mdhd.extend_from_slice(&language_code(stream_index.language.as_deref()).to_be_bytes());
+    mdhd.extend_from_slice(&0_u16.to_be_bytes()); // pre defined
+
+    let mut mdia = full_box(b"mdhd", 1, 0, &mdhd);
+
+    let (handler_type, handler_name, media_header): (&[u8; 4], &[u8], Vec<u8>) =
+        match stream_index.r#type.as_str() {
+            "audio" => (b"soun", b"SoundHandler\0", full_box(b"smhd", 0, 0, &[0; 4])),
+            "video" => (b"vide", b"VideoHandler\0", full_box(b"vmhd", 0, 1, &[0; 8])),
+            "text" => (b"subt", b"SubtitleHandler\0", full_box(b"sthd", 0, 0, &[])),
+            x => bail!(
+                "{} stream index type isn't supported in smooth streaming.",
+                x
+            ),
+        };
+
+    let mut hdlr = vec![0; 4]; // pre defined
+    hdlr.extend_from_slice(handler_type);
+    hdlr.extend_from_slice(&[0; 12]); // reserved
+    hdlr.extend_from_slice(handler_name);
+    mdia.extend_from_slice(&full_box(b"hdlr", 0, 0, &hdlr));
+
+    let mut dref = 1_u32.to_be_bytes().to_vec(); // entry count
+    dref.extend_from_slice(&full_box(b"url ", 0, 1, &[])); // self contained
+    let mut minf = media_header;
+    minf.extend_from_slice(&mp4_box(b"dinf", &full_box(b"dref", 0, 0, &dref)));
+
+    let mut sample_entry = vec![0; 6]; // reserved
+    sample_entry.extend_from_slice(&1_u16.to_be_bytes()); // data reference index
+
+    let format: &[u8; 4] = match stream_index.r#type.as_str() {
+        "audio" => {
+            let channels = quality_level.channels.unwrap_or(2);
+            let sampling_rate = quality_level.sampling_rate.unwrap_or(48000);
+
+            sample_entry.extend_from_slice(&[0; 8]); // reserved
+            sample_entry.extend_from_slice(&channels.to_be_bytes());
+            sample_entry
+                .extend_from_slice(&quality_level.bits_per_sample.unwrap_or(16).to_be_bytes());
+            sample_entry.extend_from_slice(&[0; 4]); // pre defined + reserved
+            sample_entry.extend_from_slice(&(sampling_rate.min(0xFFFF) << 16).to_be_bytes());
+
+            // 255 is the wave format tag of aac.
+            let is_aac = matches!(four_cc.as_str(), "AACL" | "AACH")
+                || (four_cc.is_empty() && quality_level.audio_tag == Some(255));
+
+            match four_cc.as_str() {
+                _ if is_aac => {
+                    let audio_specific_config = if codec_private_data.is_empty() {
+                        audio_specific_config(&four_cc, sampling_rate, channels)
+                    } else {
+                        codec_private_data.clone()
+                    };
+                    sample_entry.extend_from_slice(&esds(
+                        &audio_specific_config,
+                        quality_level.bitrate as u32,
+                    ));
+                    b"mp4a"
+                }
+                // ac-3 / ec-3 sample entries need dac3 / dec3 boxes, which aren't synthesized.
+                x => bail!(
+                    "{} fourcc isn't supported for synthesizing smooth streaming init segment.",
+                    x
+                ),
+            }
+        }
+        "video" => {
+            sample_entry.extend_from_slice(&[0; 16]); // pre defined + reserved
+            sample_entry.extend_from_slice(&(width as u16).to_be_bytes());
+            sample_entry.extend_from_slice(&(height as u16).to_be_bytes());
+            sample_entry.extend_from_slice(&0x480000_u32.to_be_bytes()); // horizontal resolution 72 dpi
+            sample_entry.extend_from_slice(&0x480000_u32.to_be_bytes()); // vertical resolution 72 dpi
+            sample_entry.extend_from_slice(&0_u32.to_be_bytes()); // reserved
+            sample_entry.extend_from_slice(&1_u16.to_be_bytes()); // frame count
+            sample_entry.extend_from_slice(&[0; 32]); // compressor name
+            sample_entry.extend_from_slice(&0x18_u16.to_be_bytes()); // depth
+            sample_entry.extend_from_slice(&(-1_i16).to_be_bytes()); // pre defined
+
+            match four_cc.as_str() {
+                "H264" | "AVC1" | "DAVC" => {
+                    sample_entry.extend_from_slice(&avcc(
+                        &codec_private_data,
+                        quality_level.nal_unit_length_field.unwrap_or(4),
+                    )?);
+                    b"avc1"
+                }
+                x => bail!(
+                    "{} fourcc isn't supported for synthesizing smooth streaming init segment.",
+                    x
+                ),
+            }
+        }
+        _ => match four_cc.as_str() {
+            "TTML" | "" => {
+                sample_entry.extend_from_slice(b"http://www.w3.org/ns/ttml\0"); // namespace
+                sample_entry.extend_from_slice(b"\0"); // schema location
+                sample_entry.extend_from_slice(b"\0"); // auxilary mime types
+                b"stpp"
+            }
+            x => bail!(
+                "{} fourcc isn't supported for synthesizing smooth streaming init segment.",
+                x
+            ),
+        },
+    };
+
+    let sample_entry = if let Some(encryption) = encryption {
+        let mut schm = b"cenc".to_vec(); // scheme type
+        schm.extend_from_slice(&0x10000_u32.to_be_bytes()); // scheme version
+
+        let mut tenc = vec![0, 0]; // reserved
+        tenc.push(1); // default is protected
+        tenc.push(8); // default per sample iv size
+        tenc.extend_from_slice(&encryption.kid);
+
+        let mut sinf = mp4_box(b"frma", format);
+        sinf.extend_from_slice(&full_box(b"schm", 0, 0, &schm));
+        sinf.extend_from_slice(&mp4_box(b"schi", &full_box(b"tenc", 0, 0, &tenc)));
+        sample_entry.extend_from_slice(&mp4_box(b"sinf", &sinf));
+
+        let format = if stream_index.r#type == "video" {
+            b"encv"
+        } else {
+            b"enca"
+        };
+        mp4_box(format, &sample_entry)
+    } else {
+        mp4_box(format, &sample_entry)
+    };
+
+    let mut stsd = 1_u32.to_be_bytes().to_vec(); // entry count
+    stsd.extend_from_slice(&sample_entry);
+
+    let mut stbl = full_box(b"stsd", 0, 0, &stsd);
+    stbl.extend_from_slice(&full_box(b"stts", 0, 0, &[0; 4]));
+    stbl.extend_from_slice(&full_box(b"stsc", 0, 0, &[0; 4]));
+    stbl.extend_from_slice(&full_box(b"stsz", 0, 0, &[0; 8]));
+    stbl.extend_from_slice(&full_box(b"stco", 0, 0, &[0; 4]));
+
+    minf.extend_from_slice(&mp4_box(b"stbl", &stbl));
+    mdia.extend_from_slice(&mp4_box(b"minf", &minf));
+    trak.extend_from_slice(&mp4_box(b"mdia", &mdia));
+    moov.extend_from_slice(&mp4_box(b"trak", &trak));
+
+    let mut mvex = full_box(b"mehd", 1, 0, &duration.to_be_bytes());
+    let mut trex = TRACK_ID.to_be_bytes().to_vec();
+    trex.extend_from_slice(&1_u32.to_be_bytes()); // default sample description index
+    trex.extend_from_slice(&[0; 12]); // default sample duration, size and flags
+    mvex.extend_from_slice(&full_box(b"trex", 0, 0, &trex));
+    moov.extend_from_slice(&mp4_box(b"mvex", &mvex));
+
+    if let Some(encryption) = encryption {
+        let mut pssh = PLAYREADY_SYSTEM_ID.to_vec();
+        pssh.extend_from_slice(&(encryption.playready_object.len() as u32).to_be_bytes());
+        pssh.extend_from_slice(&encryption.playready_object);
+        moov.extend_from_slice(&full_box(b"pssh", 0, 0, &pssh));
+    }
+
+    let mut data = mp4_box(b"ftyp", &ftyp);
+    data.extend_from_slice(&mp4_box(b"moov", &moov));
+    Ok(data)
+}
+
+/// Rewrite track id of `tfhd` boxes inside a fragment to [`TRACK_ID`].
+pub(crate) fn patch_track_id(data: &mut [u8]) {
+    patch_track_id_in(data, &[b"moof", b"traf"]);
+}
+
+fn patch_track_id_in(data: &mut [u8], parents: &[&[u8; 4]]) {
+    let mut offset = 0;
+
+    while offset + 8 <= data.len() {
+        let mut size = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let name = [
+            data[offset + 4],
+            data[offset + 5],
+            data[offset + 6],
+            data[offset + 7],
+        ];
+        let mut header = 8;
+
+        if size == 1 && offset + 16 <= data.len() {
+            size = u64::from_be_bytes(data[offset + 8..offset + 16].try_into().unwrap()) as usize;
+            header = 16;
+        } else if size == 0 {
+            size = data.len() - offset;
+        }
+
+        if size < header || offset + size > data.len() {
+            return;
+        }
+
+        if let Some((parent, children)) = parents.split_first() {
+            if &&name == parent {
+                patch_track_id_in(&mut data[offset + header..offset + size], children);
+            }
+        } else if &name == b"tfhd" && size >= header + 8 {
+            // Skip version (u8) and flags (u24) of full box.
+            data[offset + header + 4..offset + header + 8].copy_from_slice(&TRACK_ID.to_be_bytes());
+        }
+
+        offset += size;
+    }
+}
+
+fn mp4_box(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(name);
+    data.extend_from_slice(payload);
+    data
+}
+
+fn full_box(name: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Vec<u8> {
+    let mut data = ((version as u32) << 24 | flags).to_be_bytes().to_vec();
+    data.extend_from_slice(payload);
+    mp4_box(name, &data)
+}
+
+fn unity_matrix(data: &mut Vec<u8>) {
+    for value in UNITY_MATRIX {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Packed ISO-639-2/T language code.
+fn language_code(language: Option<&str>) -> u16 {
+    let language = match language {
+        Some(x) if x.len() == 3 && x.chars().all(|x| x.is_ascii_lowercase()) => x,
+        _ => "und",
+    };
+
+    language
+        .bytes()
+        .fold(0, |code, x| (code << 5) | (x - 0x60) as u16)
+}
+
+fn audio_specific_config(four_cc: &str, sampling_rate: u32, channels: u16) -> Vec<u8> {
+    let object_type = if four_cc == "AACH" { 5 } else { 2 };
+    let frequency_index = [
+        96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+    ]
+    .iter()
+    .position(|x| *x == sampling_rate)
+    .unwrap_or(3) as u16;
+
+    ((object_type << 11) | (frequency_index << 7) | ((channels & 0xf) << 3))
+        .to_be_bytes()
+        .to_vec()
+}
+
+fn esds(audio_specific_config: &[u8], bitrate: u32) -> Vec<u8> {
+    let mut decoder_config = vec![0x40]; // object type indication (audio iso/iec 14496-3)
+    decoder_config.push(0x15); // stream type (audio) + up stream + reserved
+    decoder_config.extend_from_slice(&[0; 3]); // buffer size db
+    decoder_config.extend_from_slice(&bitrate.to_be_bytes()); // max bitrate
+    decoder_config.extend_from_slice(&bitrate.to_be_bytes()); // average bitrate
+    decoder_config.extend_from_slice(&descriptor(0x05, audio_specific_config));
+
+    let mut es = TRACK_ID.to_be_bytes()[2..].to_vec(); // es id
+    es.push(0); // flags
+    es.extend_from_slice(&descriptor(0x04, &decoder_config));
+    es.extend_from_slice(&descriptor(0x06, &[0x02])); // sl config
+
+    full_box(b"esds", 0, 0, &descriptor(0x03, &es))
+}
+
+fn descriptor(tag: u8, payload: &[u8]) -> Vec<u8> {
+    let size = payload.len() as u32;
+    let mut data = vec![
+        tag,
+        0x80 | ((size >> 21) & 0x7f) as u8,
+        0x80 | ((size >> 14) & 0x7f) as u8,
+        0x80 | ((size >> 7) & 0x7f) as u8,
+        (size & 0x7f) as u8,
+    ];
+    data.extend_from_slice(payload);
+    data
+}
+
+fn avcc(codec_private_data: &[u8], nal_unit_length_field: u8) -> Result<Vec<u8>> {
+    let mut sps = vec![];
+    let mut pps = vec![];
+
+    for nal_unit in split_nal_units(codec_private_data) {
+        match nal_unit.first().map(|x| x & 0x1f) {
+            Some(7) => sps.push(nal_unit),
+            Some(8) => pps.push(nal_unit),
+            _ => (),
+        }
+    }
+
+    if sps.is_empty() || sps[0].len() < 4 {
+        bail!("couldn't find sps nal unit in smooth streaming codec private data.");
+    }
+
+    let mut data = vec![1]; // configuration version
+    data.extend_from_slice(&sps[0][1..4]); // profile indication + profile compatibility + level indication
+    data.push(0xfc | (nal_unit_length_field.clamp(1, 4) - 1)); // reserved + length size minus one
+    data.push(0xe0 | sps.len() as u8); // reserved + number of sps
+
+    for nal_unit in &sps {
+        data.extend_from_slice(&(nal_unit.len() as u16).to_be_bytes());
+        data.extend_from_slice(nal_unit);
+    }
+
+    data.push(pps.len() as u8); // number of pps
+
+    for nal_unit in &pps {
+        data.extend_from_slice(&(nal_unit.len() as u16).to_be_bytes());
+        data.extend_from_slice(nal_unit);
+    }
+
+    Ok(mp4_box(b"avcC", &data))
+}
+
+/// Split annex b byte stream into nal units (without start codes).
+fn split_nal_units(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut nal_units = vec![];
+    let mut start = None;
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        if data[i..i + 3] == [0, 0, 1] {
+            if let Some(start) = start {
+                nal_units.push(trim_trailing_zeros(&data[start..i]));
+            }
+
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(start) = start {
+        nal_units.push(trim_trailing_zeros(&data[start..]));
+    }
+
+    nal_units.into_iter().filter(|x| !x.is_empty()).collect()
+}
+
+fn trim_trailing_zeros(data: &[u8]) -> Vec<u8> {
+    let end = data
+        .iter()
+        .rposition(|x| *x != 0)
+        .map(|x| x + 1)
+        .unwrap_or(0);
+    data[..end].to_vec()
+}
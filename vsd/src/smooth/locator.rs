@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+pub(super) struct SmoothUrl {
+    pub(super) quality_level: usize,
+    pub(super) stream_index: usize,
+}
+
+impl SmoothUrl {
+    pub(super) fn new(stream_index: usize, quality_level: usize) -> Self {
+        Self {
+            quality_level,
+            stream_index,
+        }
+    }
+}
+
+impl ToString for SmoothUrl {
+    fn to_string(&self) -> String {
+        format!(
+            "smooth://stream-index.{}.quality-level.{}",
+            self.stream_index, self.quality_level
+        )
+    }
+}
+
+impl FromStr for SmoothUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if !s.starts_with("smooth://") {
+            return Err(format!(
+                "url doesn't have smooth scheme \
+            (expected: smooth://stream-index.{{}}.quality-level.{{}}, found: {})",
+                s
+            ));
+        }
+
+        let location = s
+            .replace("smooth://", "")
+            .replace("stream-index", "")
+            .replace("quality-level", "")
+            .split_terminator('.')
+            .filter_map(|x| x.parse::<usize>().ok())
+            .collect::<Vec<usize>>();
+
+        if location.len() != 2 {
+            return Err(format!(
+                "url doesn't have full location to locate smooth resource \
+            (expected: smooth://stream-index.{{}}.quality-level.{{}}, found: {})",
+                s
+            ));
+        }
+
+        Ok(Self {
+            quality_level: location[1],
+            stream_index: location[0],
+        })
+    }
+}
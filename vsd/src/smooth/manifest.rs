@@ -0,0 +1,131 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-sstr
+
+*/
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub(crate) struct SmoothStreamingMedia {
+    #[serde(rename = "@Duration")]
+    pub(super) duration: Option<u64>,
+    #[serde(rename = "@IsLive")]
+    pub(super) is_live: Option<String>,
+    #[serde(rename = "Protection")]
+    pub(super) protection: Option<Protection>,
+    #[serde(rename = "StreamIndex", default)]
+    pub(super) stream_indexes: Vec<StreamIndex>,
+    #[serde(rename = "@TimeScale")]
+    pub(super) time_scale: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct StreamIndex {
+    #[serde(rename = "c", default)]
+    pub(super) chunks: Vec<Chunk>,
+    #[serde(rename = "@Language")]
+    pub(super) language: Option<String>,
+    #[serde(rename = "@MaxHeight")]
+    pub(super) max_height: Option<u64>,
+    #[serde(rename = "@MaxWidth")]
+    pub(super) max_width: Option<u64>,
+    #[serde(rename = "@Name")]
+    pub(super) name: Option<String>,
+    #[serde(rename = "QualityLevel", default)]
+    pub(super) quality_levels: Vec<QualityLevel>,
+    #[serde(rename = "@Subtype")]
+    pub(super) subtype: Option<String>,
+    #[serde(rename = "@TimeScale")]
+    pub(super) time_scale: Option<u64>,
+    #[serde(rename = "@Type")]
+    pub(super) r#type: String,
+    #[serde(rename = "@Url")]
+    pub(super) url: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct QualityLevel {
+    #[serde(rename = "@AudioTag")]
+    pub(super) audio_tag: Option<u32>,
+    #[serde(rename = "@BitsPerSample")]
+    pub(super) bits_per_sample: Option<u16>,
+    #[serde(rename = "@Bitrate")]
+    pub(super) bitrate: u64,
+    #[serde(rename = "@Channels")]
+    pub(super) channels: Option<u16>,
+    #[serde(rename = "@CodecPrivateData")]
+    pub(super) codec_private_data: Option<String>,
+    #[serde(rename = "CustomAttributes")]
+    pub(super) custom_attributes: Option<CustomAttributes>,
+    #[serde(rename = "@FourCC")]
+    pub(super) four_cc: Option<String>,
+    #[serde(rename = "@MaxHeight")]
+    pub(super) max_height: Option<u64>,
+    #[serde(rename = "@MaxWidth")]
+    pub(super) max_width: Option<u64>,
+    #[serde(rename = "@NALUnitLengthField")]
+    pub(super) nal_unit_length_field: Option<u8>,
+    #[serde(rename = "@SamplingRate")]
+    pub(super) sampling_rate: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CustomAttributes {
+    #[serde(rename = "Attribute", default)]
+    pub(super) attributes: Vec<Attribute>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Attribute {
+    #[serde(rename = "@Name")]
+    pub(super) name: String,
+    #[serde(rename = "@Value")]
+    pub(super) value: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Chunk {
+    #[serde(rename = "@d")]
+    pub(super) d: Option<u64>,
+    #[serde(rename = "@r")]
+    pub(super) r: Option<u64>,
+    #[serde(rename = "@t")]
+    pub(super) t: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Protection {
+    #[serde(rename = "ProtectionHeader", default)]
+    pub(super) protection_headers: Vec<ProtectionHeader>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct ProtectionHeader {
+    #[serde(rename = "@SystemID")]
+    pub(super) system_id: String,
+    #[serde(rename = "$text")]
+    pub(super) data: String,
+}
+
+impl SmoothStreamingMedia {
+    pub(crate) fn parse(xml: &str) -> Result<Self> {
+        quick_xml::de::from_str(xml).map_err(|x| {
+            anyhow!(
+                "couldn't parse response as smooth streaming manifest (failed with {}).\n\n{}",
+                x,
+                xml
+            )
+        })
+    }
+
+    pub(super) fn time_scale(&self, stream_index: &StreamIndex) -> u64 {
+        stream_index
+            .time_scale
+            .or(self.time_scale)
+            .unwrap_or(10_000_000)
+    }
+}
@@ -0,0 +1,10 @@
+mod init;
+mod locator;
+mod manifest;
+mod playlist;
+
+use locator::SmoothUrl;
+
+pub(crate) use init::patch_track_id;
+pub(crate) use manifest::SmoothStreamingMedia;
+pub(crate) use playlist::{parse_as_master, push_segments};
@@ -0,0 +1,254 @@
+/*
+    REFERENCES
+    ----------
+
+    1. https://github.com/yt-dlp/yt-dlp/blob/2023.07.06/yt_dlp/extractor/common.py
+    2. https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-sstr
+
+*/
+
+use super::{
+    init::{self, Encryption},
+    manifest::{QualityLevel, SmoothStreamingMedia},
+    SmoothUrl,
+};
+use crate::{
+    playlist::{
+        Key, KeyMethod, Map, MasterPlaylist, MediaPlaylist, MediaType, PlaylistType, Segment,
+        VideoRange,
+    },
+    utils,
+};
+use anyhow::{anyhow, bail, Result};
+use base64::Engine;
+use reqwest::Url;
+use vsd_mp4::pssh::{KeyIdSystemType, Pssh};
+
+pub(crate) fn parse_as_master(manifest: &SmoothStreamingMedia, uri: &str) -> MasterPlaylist {
+    let mut streams = vec![];
+
+    for (stream_index_index, stream_index) in manifest.stream_indexes.iter().enumerate() {
+        let media_type = match stream_index.r#type.as_str() {
+            "audio" => MediaType::Audio,
+            "text" => MediaType::Subtitles,
+            "video" => MediaType::Video,
+            _ => MediaType::Undefined,
+        };
+
+        let roles = match stream_index.subtype.as_deref() {
+            Some("CAPT") => vec!["caption".to_owned()],
+            Some("SUBT") => vec!["subtitle".to_owned()],
+            Some("DESC") => vec!["description".to_owned()],
+            _ => vec![],
+        };
+
+        for (quality_level_index, quality_level) in stream_index.quality_levels.iter().enumerate() {
+            streams.push(MediaPlaylist {
+                accessibility: vec![],
+                bandwidth: Some(quality_level.bitrate),
+                channels: quality_level.channels.map(|x| x as f32),
+                codecs: parse_codecs(quality_level),
+                extension: Some("mp4".to_owned()),
+                frame_rate: None, // Cannot be comment here
+                group_id: None,
+                groups: Default::default(),
+                i_frame: false, // Cannot be comment here
+                id: format!(
+                    "{}-{}",
+                    stream_index.name.as_ref().unwrap_or(&stream_index.r#type),
+                    quality_level.bitrate
+                ),
+                label: stream_index.name.clone(),
+                language: stream_index.language.clone(),
+                live: manifest
+                    .is_live
+                    .as_ref()
+                    .map(|x| x.eq_ignore_ascii_case("true"))
+                    .unwrap_or(false),
+                media_type: media_type.clone(),
+                object_audio: false,
+                playlist_type: PlaylistType::Smooth,
+                resolution: if let (Some(width), Some(height)) = (
+                    quality_level.max_width.or(stream_index.max_width),
+                    quality_level.max_height.or(stream_index.max_height),
+                ) {
+                    Some((width, height))
+                } else {
+                    None
+                },
+                roles: roles.clone(),
+                segments: vec![], // Cannot be comment here
+                tiles: None,
+                uri: SmoothUrl::new(stream_index_index, quality_level_index).to_string(),
+                video_range: (media_type == MediaType::Video).then_some(VideoRange::Sdr),
+            });
+        }
+    }
+
+    MasterPlaylist {
+        duration: manifest
+            .duration
+            .filter(|x| *x != 0)
+            .map(|x| x as f32 / manifest.time_scale.unwrap_or(10_000_000) as f32),
+        playlist_type: PlaylistType::Smooth,
+        uri: uri.to_owned(),
+        streams,
+    }
+}
+
+pub(crate) fn push_segments(
+    manifest: &SmoothStreamingMedia,
+    playlist: &mut MediaPlaylist,
+    base_url: &str,
+) -> Result<()> {
+    let location = playlist.uri.parse::<SmoothUrl>().map_err(|x| anyhow!(x))?;
+    let stream_index = manifest
+        .stream_indexes
+        .get(location.stream_index)
+        .ok_or_else(|| anyhow!("smooth streaming stream index is out of bounds."))?;
+    let quality_level = stream_index
+        .quality_levels
+        .get(location.quality_level)
+        .ok_or_else(|| anyhow!("smooth streaming quality level is out of bounds."))?;
+
+    let url_template = if let Some(url) = &stream_index.url {
+        url.replace("{bitrate}", &quality_level.bitrate.to_string())
+            .replace("{Bitrate}", &quality_level.bitrate.to_string())
+            .replace(
+                "{CustomAttributes}",
+                &quality_level
+                    .custom_attributes
+                    .as_ref()
+                    .map(|x| {
+                        x.attributes
+                            .iter()
+                            .map(|x| format!("{}={}", x.name, x.value))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    })
+                    .unwrap_or_default(),
+            )
+    } else {
+        bail!("missing @Url on smooth streaming stream index node.");
+    };
+
+    let base_url = base_url.parse::<Url>()?;
+    let time_scale = manifest.time_scale(stream_index) as f32;
+    let stream_duration = manifest.duration.unwrap_or(0) * manifest.time_scale(stream_index)
+        / manifest.time_scale.unwrap_or(10_000_000);
+    let mut time = 0;
+
+    for (i, chunk) in stream_index.chunks.iter().enumerate() {
+        time = chunk.t.unwrap_or(time);
+        let repeat = chunk.r.filter(|x| *x != 0).unwrap_or(1);
+        let duration = if let Some(duration) = chunk.d {
+            duration
+        } else {
+            let next_time = stream_index
+                .chunks
+                .get(i + 1)
+                .and_then(|x| x.t)
+                .unwrap_or(stream_duration);
+            next_time.saturating_sub(time) / repeat
+        };
+
+        for _ in 0..repeat {
+            let uri = url_template
+                .replace("{start time}", &time.to_string())
+                .replace("{start_time}", &time.to_string());
+
+            playlist.segments.push(Segment {
+                duration: duration as f32 / time_scale,
+                uri: base_url.join(&uri)?.to_string(),
+                ..Default::default()
+            });
+
+            time += duration;
+        }
+    }
+
+    let encryption = parse_encryption(manifest);
+    let init = init::init_segment(manifest, stream_index, quality_level, encryption.as_ref())?;
+
+    if let Some(first_segment) = playlist.segments.get_mut(0) {
+        first_segment.map = Some(Map {
+            range: None,
+            uri: format!(
+                "data:video/mp4;base64,{}",
+                base64::engine::general_purpose::STANDARD.encode(init)
+            ),
+        });
+
+        if let Some(encryption) = encryption {
+            first_segment.key = Some(Key {
+                default_kid: Some(hex::encode(&encryption.kid)),
+                iv: None,
+                key_format: None,
+                method: KeyMethod::Cenc,
+                pssh: Pssh::from_playready_object(&encryption.playready_object).ok(),
+                uri: None,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_codecs(quality_level: &QualityLevel) -> Option<String> {
+    let four_cc = quality_level.four_cc.as_ref()?.to_uppercase();
+
+    Some(match four_cc.as_str() {
+        "AACH" => "mp4a.40.5".to_owned(),
+        "AACL" => "mp4a.40.2".to_owned(),
+        "AC-3" => "ac-3".to_owned(),
+        "AVC1" | "DAVC" | "H264" => {
+            // Profile, constraints and level are present after first start code and nal unit header.
+            let codec_private_data = quality_level.codec_private_data.as_deref().unwrap_or("");
+
+            if let Some(x) = codec_private_data
+                .to_lowercase()
+                .split_once("0000000167")
+                .and_then(|(_, x)| x.get(..6).map(|x| x.to_owned()))
+            {
+                format!("avc1.{}", x)
+            } else {
+                "avc1".to_owned()
+            }
+        }
+        "EC-3" => "ec-3".to_owned(),
+        "TTML" => "stpp".to_owned(),
+        x => x.to_lowercase(),
+    })
+}
+
+fn parse_encryption(manifest: &SmoothStreamingMedia) -> Option<Encryption> {
+    let protection_header = manifest
+        .protection
+        .as_ref()?
+        .protection_headers
+        .iter()
+        .find(|x| {
+            matches!(
+                KeyIdSystemType::from_system_id(&x.system_id),
+                KeyIdSystemType::PlayReady
+            )
+        })?;
+
+    let playready_object = utils::decode_base64(protection_header.data.trim()).ok()?;
+    let mut kid = Pssh::from_playready_object(&playready_object)
+        .ok()?
+        .key_ids
+        .first()
+        .and_then(|x| hex::decode(&x.value).ok())
+        .filter(|x| x.len() == 16)?;
+
+    // Key id is stored as guid in little endian byte order, whereas tenc box needs big endian.
+    kid[0..4].reverse();
+    kid[4..6].reverse();
+    kid[6..8].reverse();
+
+    Some(Encryption {
+        kid,
+        playready_object,
+    })
+}
@@ -43,6 +43,17 @@ pub(super) fn format_download_bytes(downloaded: usize, total: usize) -> String {
     }
 }
 
+pub(super) fn format_vtt_timestamp(seconds: f32) -> String {
+    let milliseconds = (seconds * 1000.0) as usize;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        milliseconds / 3_600_000,
+        (milliseconds / 60_000) % 60,
+        (milliseconds / 1000) % 60,
+        milliseconds % 1000
+    )
+}
+
 pub(super) fn scrape_playlist_links(text: &str) -> Vec<String> {
     let re = Regex::new(r"(https|ftp|http)://([\w_-]+(?:(?:\.[\w_-]+)+))([\w.,@?^=%&:/~+#-]*[\w@?^=%&/~+#-]\.(m3u8|m3u|mpd))").unwrap();
     let links = re
@@ -91,6 +102,26 @@ pub(super) fn decode_base64<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>> {
         .map_err(|x| x.into())
 }
 
+/// Decode a RFC 2397 `data:` uri having base64 or percent encoded data.
+pub(super) fn decode_data_uri(uri: &str) -> Result<Vec<u8>> {
+    let (header, data) = uri
+        .trim()
+        .strip_prefix("data:")
+        .and_then(|x| x.split_once(','))
+        .ok_or_else(|| anyhow!("invalid data uri."))?;
+    let data = percent_encoding::percent_decode_str(data).collect::<Vec<_>>();
+
+    if header.to_lowercase().ends_with(";base64") {
+        decode_base64(
+            data.into_iter()
+                .filter(|x| !x.is_ascii_whitespace())
+                .collect::<Vec<_>>(),
+        )
+    } else {
+        Ok(data)
+    }
+}
+
 // pub(super) fn encode_base64<T: AsRef<[u8]>>(input: T) -> String {
 //     base64::engine::general_purpose::STANDARD.encode(input)
 // }
@@ -103,10 +134,7 @@ pub(super) fn decrypt_aes_128_cbc(
     let key_length = key.len();
 
     if key_length != 16 {
-        bail!(
-            "invalid key size i.e. {} but expected size 16.",
-            key_length
-        );
+        bail!("invalid key size i.e. {} but expected size 16.", key_length);
     }
 
     let mut key_c = [0_u8; 16];
@@ -130,6 +158,28 @@ pub(super) fn decrypt_aes_128_cbc(
         .map_err(|x| anyhow!("{}", x))
 }
 
+/// Convert `KID | TRACK_ID -> KEY` hex keys to keys accepted by [mp4decrypt].
+/// Same as bento4, 32 characters long ids are key ids and rest are track ids.
+#[cfg(feature = "mp4decrypt")]
+pub(super) fn mp4decrypt_keys(
+    keys: std::collections::HashMap<String, String>,
+) -> Result<Vec<mp4decrypt::Key>> {
+    keys.into_iter()
+        .map(|(kid, key)| {
+            Ok(if kid.len() == 32 {
+                mp4decrypt::Key::Kid { kid, key }
+            } else {
+                mp4decrypt::Key::TrackId {
+                    id: kid.parse::<u32>().map_err(|_| {
+                        anyhow!("{} is neither a 128-bit hex kid nor a track id.", kid)
+                    })?,
+                    key,
+                }
+            })
+        })
+        .collect()
+}
+
 // fn find_ffmpeg() -> Option<String> {
 //     Some(
 //         std::env::var("PATH")